@@ -0,0 +1,502 @@
+// Integer arithmetic, for `$((...))`, `((...))` and `let`. Expressions are as
+// in C, with 64-bit integers that wrap on overflow, `**` for powers, and
+// variables, which may be named without a `$`. A variable's value is itself
+// evaluated as an expression; an unset or empty one counts as 0. Numbers may
+// be written in hex (`0x1f`), octal (`017`) or any base from 2 to 64
+// (`2#101`).
+
+use crate::{parser::is_name, variables::Variables};
+
+// How deeply variables may refer to other variables, to catch loops such as
+// `a=b b=a`.
+const MAX_DEPTH: usize = 64;
+
+pub fn evaluate(expression: &str, vars: &mut Variables) -> Result<i64, String> {
+    evaluate_at_depth(expression, vars, 0)
+}
+
+fn evaluate_at_depth(expression: &str, vars: &mut Variables, depth: usize) -> Result<i64, String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("{expression}: expression recursion level exceeded"));
+    }
+    let error = |problem: &str| format!("{}: {problem}", expression.trim());
+
+    let tokens = tokenize(expression).map_err(|problem| error(&problem))?;
+    if tokens.is_empty() {
+        return Ok(0);
+    }
+
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+    };
+    let expr = parser.comma().map_err(|problem| error(&problem))?;
+    if let Some(token) = parser.peek() {
+        return Err(error(&format!(
+            "syntax error in expression (error token is \"{}\")",
+            token.text()
+        )));
+    }
+
+    expr.evaluate(vars, depth).map_err(|problem| match problem {
+        // Errors from variables' values already say where they're from.
+        Problem::Nested(message) => message,
+        Problem::Here(problem) => error(&problem),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Operator(&'static str),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Number(n) => n.to_string(),
+            Token::Name(name) => name.clone(),
+            Token::Operator(op) => String::from(*op),
+        }
+    }
+}
+
+// Longest first, so that, e.g., `**=` isn't read as `**` and `=`.
+const OPERATORS: [&str; 40] = [
+    "<<=", ">>=", "**=", "**", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "++", "--", "+=",
+    "-=", "*=", "/=", "%=", "&=", "^=", "|=", "+", "-", "*", "/", "%", "<", ">", "&", "|", "^",
+    "!", "~", "?", ":", "=", "(", ")", ",",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = expression;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '#' || c == '@'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            if c.is_ascii_digit() {
+                tokens.push(Token::Number(number(word)?));
+            } else if is_name(word) {
+                tokens.push(Token::Name(String::from(word)));
+            } else {
+                return Err(format!(
+                    "syntax error: invalid name (error token is \"{word}\")"
+                ));
+            }
+            rest = &rest[end..];
+        } else if let Some(op) = OPERATORS.into_iter().find(|op| rest.starts_with(op)) {
+            tokens.push(Token::Operator(op));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(format!(
+                "syntax error: invalid arithmetic operator (error token is \"{rest}\")"
+            ));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn number(text: &str) -> Result<i64, String> {
+    let invalid = || format!("value too great for base (error token is \"{text}\")");
+
+    let (base, digits) = if let Some((base, digits)) = text.split_once('#') {
+        match base.parse::<u32>() {
+            Ok(base) if (2..=64).contains(&base) => (base, digits),
+            _ => {
+                return Err(format!(
+                    "invalid arithmetic base (error token is \"{text}\")"
+                ));
+            }
+        }
+    } else if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        (16, hex)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (8, &text[1..])
+    } else {
+        (10, text)
+    };
+
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+
+    // Digits beyond 9 are `a`-`z`, then `A`-`Z`, `@` and `_`; below base 37,
+    // letters of either case mean the same.
+    let mut value: i64 = 0;
+    for c in digits.chars() {
+        let digit = match c {
+            '0'..='9' => c as u32 - '0' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 10,
+            'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+            'A'..='Z' => c as u32 - 'A' as u32 + 36,
+            '@' => 62,
+            '_' => 63,
+            _ => return Err(invalid()),
+        };
+        if digit >= base {
+            return Err(invalid());
+        }
+        value = value
+            .wrapping_mul(i64::from(base))
+            .wrapping_add(i64::from(digit));
+    }
+    Ok(value)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Variable(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    // `=` or an operator such as `+=`, with the operator without its `=`.
+    Assign(String, Option<&'static str>, Box<Expr>),
+    Increment { name: String, by: i64, prefix: bool },
+}
+
+enum Problem {
+    Here(String),
+    Nested(String),
+}
+
+impl From<String> for Problem {
+    fn from(problem: String) -> Self {
+        Problem::Here(problem)
+    }
+}
+
+// A precedence-climbing parser. Each method parses one level of precedence,
+// from lowest to highest.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+// Binary operators by precedence, lowest first, each level binding left to
+// right. `**`, assignment and `?:`, which bind right to left, are handled
+// separately.
+const LEVELS: [&[&str]; 10] = [
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", ">", "<=", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_if_operator(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(&Token::Operator(op)) if ops.contains(&op) => {
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        match self.next_if_operator(&[op]) {
+            Some(_) => Ok(()),
+            None => Err(self.unexpected()),
+        }
+    }
+
+    fn unexpected(&self) -> String {
+        match self.peek() {
+            Some(token) => format!(
+                "syntax error: operand expected (error token is \"{}\")",
+                token.text()
+            ),
+            None => String::from("syntax error: operand expected"),
+        }
+    }
+
+    fn comma(&mut self) -> Result<Expr, String> {
+        let mut expr = self.assignment()?;
+        while self.next_if_operator(&[","]).is_some() {
+            expr = Expr::Binary(",", Box::new(expr), Box::new(self.assignment()?));
+        }
+        Ok(expr)
+    }
+
+    fn assignment(&mut self) -> Result<Expr, String> {
+        const ASSIGNMENTS: [&str; 11] = [
+            "=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "^=", "|=",
+        ];
+
+        if let Some(Token::Name(name)) = self.peek()
+            && let Some(Token::Operator(op)) = self.tokens.get(self.position + 1)
+            && ASSIGNMENTS.contains(op)
+        {
+            let name = name.clone();
+            self.position += 2;
+            let operator = op.strip_suffix('=').filter(|op| !op.is_empty());
+            return Ok(Expr::Assign(name, operator, Box::new(self.assignment()?)));
+        }
+
+        self.conditional()
+    }
+
+    fn conditional(&mut self) -> Result<Expr, String> {
+        let condition = self.binary(0)?;
+        if self.next_if_operator(&["?"]).is_none() {
+            return Ok(condition);
+        }
+        let then = self.comma()?;
+        self.expect(":")?;
+        let otherwise = self.assignment()?;
+        Ok(Expr::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        if level == LEVELS.len() {
+            return self.power();
+        }
+        let mut expr = self.binary(level + 1)?;
+        while let Some(op) = self.next_if_operator(LEVELS[level]) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.binary(level + 1)?));
+        }
+        Ok(expr)
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.unary()?;
+        if self.next_if_operator(&["**"]).is_some() {
+            return Ok(Expr::Binary("**", Box::new(base), Box::new(self.power()?)));
+        }
+        Ok(base)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if let Some(op) = self.next_if_operator(&["++", "--"]) {
+            let Some(Token::Name(name)) = self.peek() else {
+                return Err(self.unexpected());
+            };
+            let name = name.clone();
+            self.position += 1;
+            return Ok(Expr::Increment {
+                name,
+                by: if op == "++" { 1 } else { -1 },
+                prefix: true,
+            });
+        }
+        if let Some(op) = self.next_if_operator(&["!", "~", "+", "-"]) {
+            return Ok(Expr::Unary(op, Box::new(self.unary()?)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let token = self.peek().cloned();
+        self.position += 1;
+
+        match token {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Name(name)) => match self.next_if_operator(&["++", "--"]) {
+                Some(op) => Ok(Expr::Increment {
+                    name,
+                    by: if op == "++" { 1 } else { -1 },
+                    prefix: false,
+                }),
+                None => Ok(Expr::Variable(name)),
+            },
+            Some(Token::Operator("(")) => {
+                let expr = self.comma()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected())
+            }
+        }
+    }
+}
+
+impl Expr {
+    fn evaluate(&self, vars: &mut Variables, depth: usize) -> Result<i64, Problem> {
+        let value = match self {
+            Expr::Number(n) => *n,
+            Expr::Variable(name) => variable(name, vars, depth)?,
+            Expr::Unary(op, operand) => {
+                let operand = operand.evaluate(vars, depth)?;
+                match *op {
+                    "!" => i64::from(operand == 0),
+                    "~" => !operand,
+                    "-" => operand.wrapping_neg(),
+                    _ => operand,
+                }
+            }
+            // Only evaluate the right-hand side if it's needed.
+            Expr::Binary("&&", left, right) => {
+                i64::from(left.evaluate(vars, depth)? != 0 && right.evaluate(vars, depth)? != 0)
+            }
+            Expr::Binary("||", left, right) => {
+                i64::from(left.evaluate(vars, depth)? != 0 || right.evaluate(vars, depth)? != 0)
+            }
+            Expr::Binary(op, left, right) => {
+                let left = left.evaluate(vars, depth)?;
+                let right = right.evaluate(vars, depth)?;
+                apply(op, left, right)?
+            }
+            Expr::Conditional(condition, then, otherwise) => {
+                if condition.evaluate(vars, depth)? != 0 {
+                    then.evaluate(vars, depth)?
+                } else {
+                    otherwise.evaluate(vars, depth)?
+                }
+            }
+            Expr::Assign(name, op, expr) => {
+                let mut value = expr.evaluate(vars, depth)?;
+                if let Some(op) = op {
+                    value = apply(op, variable(name, vars, depth)?, value)?;
+                }
+                vars.set(name, &value.to_string())?;
+                value
+            }
+            Expr::Increment { name, by, prefix } => {
+                let old = variable(name, vars, depth)?;
+                let new = old.wrapping_add(*by);
+                vars.set(name, &new.to_string())?;
+                if *prefix { new } else { old }
+            }
+        };
+        Ok(value)
+    }
+}
+
+fn variable(name: &str, vars: &mut Variables, depth: usize) -> Result<i64, Problem> {
+    let value = vars.get(name).unwrap_or_default().to_string();
+    if value.trim().is_empty() {
+        return Ok(0);
+    }
+    evaluate_at_depth(&value, vars, depth + 1).map_err(Problem::Nested)
+}
+
+fn apply(op: &str, left: i64, right: i64) -> Result<i64, String> {
+    let value = match op {
+        "," => right,
+        "|" => left | right,
+        "^" => left ^ right,
+        "&" => left & right,
+        "==" => i64::from(left == right),
+        "!=" => i64::from(left != right),
+        "<" => i64::from(left < right),
+        ">" => i64::from(left > right),
+        "<=" => i64::from(left <= right),
+        ">=" => i64::from(left >= right),
+        "<<" => left.wrapping_shl(right as u32),
+        ">>" => left.wrapping_shr(right as u32),
+        "+" => left.wrapping_add(right),
+        "-" => left.wrapping_sub(right),
+        "*" => left.wrapping_mul(right),
+        "/" | "%" if right == 0 => return Err(String::from("division by 0")),
+        "/" => left.wrapping_div(right),
+        "%" => left.wrapping_rem(right),
+        "**" if right < 0 => return Err(String::from("exponent less than 0")),
+        "**" => left.wrapping_pow(u32::try_from(right).unwrap_or(u32::MAX)),
+        _ => return Err(format!("syntax error: invalid arithmetic operator ({op})")),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+    use crate::variables::Variables;
+
+    fn eval(expression: &str) -> Result<i64, String> {
+        evaluate(expression, &mut Variables::default())
+    }
+
+    #[test]
+    fn arithmetic_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9));
+        assert_eq!(eval("2 ** 3 ** 2"), Ok(512), "`**` is right-associative");
+        assert_eq!(eval("-2 ** 2"), Ok(4));
+        assert_eq!(eval("7 / 2 + 7 % 2"), Ok(4));
+        assert_eq!(eval("1 << 4 | 1"), Ok(17));
+        assert_eq!(eval("6 & 3 ^ 1"), Ok(3));
+        assert_eq!(eval("!0 + ~0"), Ok(0));
+        assert_eq!(eval("1 < 2 && 2 >= 3 || 4 == 4"), Ok(1));
+        assert_eq!(eval("0 ? 1 : 2 ? 3 : 4"), Ok(3));
+        assert_eq!(eval("1, 2, 3"), Ok(3));
+        assert_eq!(eval("   "), Ok(0));
+    }
+
+    #[test]
+    fn arithmetic_literals() {
+        assert_eq!(eval("0x1f + 010 + 2#101"), Ok(31 + 8 + 5));
+        assert_eq!(eval("16#FF + 64#_"), Ok(255 + 63));
+        assert!(eval("08").is_err());
+        assert!(eval("1#1").is_err());
+    }
+
+    #[test]
+    fn arithmetic_variables() {
+        let mut vars = Variables::default();
+        vars.set("x", "5").unwrap();
+        vars.set("y", "x * 2").unwrap();
+
+        assert_eq!(evaluate("x + y", &mut vars), Ok(15));
+        assert_eq!(evaluate("unset + 1", &mut vars), Ok(1));
+        assert_eq!(evaluate("z = x += 2", &mut vars), Ok(7));
+        assert_eq!((vars.get("x"), vars.get("z")), (Some("7"), Some("7")));
+        assert_eq!(evaluate("x++ + ++x", &mut vars), Ok(7 + 9));
+        assert_eq!(evaluate("x--", &mut vars), Ok(9));
+        assert_eq!(evaluate("x <<= 1", &mut vars), Ok(16));
+
+        assert_eq!(
+            evaluate("0 && (n = 1)", &mut vars),
+            Ok(0),
+            "`&&` should short-circuit"
+        );
+        assert_eq!(vars.get("n"), None);
+
+        vars.set("a", "b").unwrap();
+        vars.set("b", "a").unwrap();
+        assert!(evaluate("a", &mut vars).is_err(), "a loop of variables");
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        assert_eq!(eval("1 / 0"), Err(String::from("1 / 0: division by 0")));
+        assert_eq!(
+            eval("1 +"),
+            Err(String::from("1 +: syntax error: operand expected"))
+        );
+        assert_eq!(
+            eval("1 2"),
+            Err(String::from(
+                "1 2: syntax error in expression (error token is \"2\")"
+            ))
+        );
+        assert!(eval("2 ** -1").is_err());
+        assert!(eval("3 = 4").is_err());
+        assert!(eval("(1").is_err());
+        assert!(eval("1 $ 2").is_err());
+    }
+}
@@ -1,201 +1,549 @@
+pub mod alias;
 pub mod bg;
+pub mod r#break;
 pub mod cat;
 pub mod cd;
+pub mod r#continue;
 pub mod cp;
+pub mod declare;
+pub mod disown;
 pub mod echo;
 pub mod exit;
+pub mod export;
 pub mod fg;
 pub mod jobs;
 pub mod kill;
+pub mod r#let;
+pub mod local;
 pub mod ls;
 pub mod man;
 pub mod mkdir;
 pub mod mv;
+pub mod nohup;
 pub mod pwd;
+pub mod readonly;
+pub mod r#return;
 pub mod rm;
+pub mod set;
+pub mod shopt;
 pub mod sleep;
+pub mod source;
+pub mod test;
 pub mod touch;
+pub mod trap;
+pub mod unalias;
+pub mod unset;
+pub mod wait;
 
-use std::{fs::OpenOptions, os::fd::AsRawFd};
+use std::{env, rc::Rc};
 
-use crate::{commands::jobs::Job, error, fork::spawn_job};
+use crate::{
+    commands::jobs::JobTable,
+    compound, error,
+    expand::{self, ExpandedStage},
+    fork::{self, spawn_job},
+    parser::ast::{CompoundCommand, Connector, Function, List, Pipeline},
+    redirect,
+    signals::Condition,
+    variables::{Variables, quote},
+};
 
+// Commands that run inside the shell process itself.
+const BUILTINS: [&str; 28] = [
+    ".", "[", "alias", "bg", "break", "cd", "continue", "declare", "disown", "echo", "exit",
+    "export", "fg", "jobs", "kill", "let", "local", "pwd", "readonly", "return", "set", "shopt",
+    "source", "test", "trap", "unalias", "unset", "wait",
+];
+
+// How deeply function calls may be nested, so that a function that calls
+// itself forever fails rather than overflowing the stack.
+const MAX_FUNCTION_DEPTH: usize = 256;
+
+// Custom commands that run in a child process which execs this program with
+// the `--internal-worker` flag.
+const WORKERS: [&str; 10] = [
+    "cat", "cp", "ls", "mkdir", "man", "mv", "nohup", "rm", "sleep", "touch",
+];
+
+pub fn is_builtin(command: &str) -> bool {
+    BUILTINS.contains(&command)
+}
+
+pub fn is_worker(command: &str) -> bool {
+    WORKERS.contains(&command)
+}
+
+// What the shell does after running a command: usually carry on with the next
+// one, but `exit`, `break`, `continue` and `return` change that.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    Next,
+    Exit(i32),
+    Break(usize),    // Leave this many of the enclosing loops.
+    Continue(usize), // Go on to the next iteration of the nth enclosing loop.
+    Return(i32),     // Leave the function that's running, with this status.
+}
+
+impl Flow {
+    // The status that a subshell exits with when its list ends this way.
+    pub fn exit_status(self, last_status: i32) -> i32 {
+        match self {
+            Flow::Exit(code) => code,
+            _ => last_status,
+        }
+    }
+}
+
+// Run each element of a command list in turn, skipping those whose `&&` or
+// `||` condition isn't met by the previous status. Stops early if a command
+// changes the flow, or if a foreground job is interrupted with Ctrl+C. With
+// `-e`, a command that fails ends the shell, unless its status is tested, as
+// it is before `&&` or `||`; the `ERR` trap runs in the same circumstances.
+// The `DEBUG` trap runs before each element, and the actions for any signals
+// that have been caught after it.
+pub fn run_list(
+    list: &List,
+    jobs: &mut JobTable,
+    exit_attempted: &mut bool,
+    vars: &mut Variables,
+) -> Flow {
+    for (index, item) in list.items.iter().enumerate() {
+        if !item.connector.should_run(vars.last_status) {
+            continue;
+        }
+
+        let flow = trap::run_condition(Condition::Debug, jobs, exit_attempted, vars);
+        if flow != Flow::Next {
+            return flow;
+        }
+
+        let tested = list
+            .items
+            .get(index + 1)
+            .is_some_and(|next| next.connector != Connector::Always);
+        vars.conditions += usize::from(tested);
+        let flow = run_command(&item.pipeline, item.background, jobs, exit_attempted, vars);
+        vars.conditions -= usize::from(tested);
+        if flow != Flow::Next || fork::was_interrupted() {
+            return flow;
+        }
+
+        let flow = trap::run_pending(jobs, exit_attempted, vars);
+        if flow != Flow::Next {
+            return flow;
+        }
+
+        if vars.last_status != 0 && vars.conditions == 0 && !tested && !item.background {
+            let flow = trap::run_condition(Condition::Error, jobs, exit_attempted, vars);
+            if flow != Flow::Next {
+                return flow;
+            }
+            if vars.options.errexit {
+                return Flow::Exit(vars.last_status);
+            }
+        }
+    }
+    Flow::Next
+}
+
+// Run one element of a command list, printing its output or error.
 pub fn run_command(
-    args: &[String],
-    jobs: &mut Vec<Job>,
-    current: &mut usize,
-    previous: &mut usize,
+    pipeline: &Pipeline,
+    is_background_launch: bool,
+    jobs: &mut JobTable,
     exit_attempted: &mut bool,
-) -> (Option<i32>, Result<String, String>) {
-    if args.is_empty() {
-        return (None, Ok(String::new()));
+    vars: &mut Variables,
+) -> Flow {
+    vars.substituted = false;
+    let stages = match pipeline
+        .commands
+        .iter()
+        .map(|command| expand::stage(command, vars))
+        .collect::<Result<Vec<ExpandedStage>, String>>()
+    {
+        Ok(stages) => stages,
+        Err(err) => {
+            vars.last_status = 1;
+            error::handle_error("0-shell", err);
+            // As POSIX requires, a shell that isn't interactive, running a
+            // script, gives up on it, as after `${name:?message}`.
+            if !vars.interactive {
+                return Flow::Exit(1);
+            }
+            return Flow::Next;
+        }
+    };
+
+    if vars.options.xtrace {
+        trace(&stages, vars);
     }
 
-    let (clean_args, is_background_launch) = if let Some(last) = args.last() {
-        if last == "&" {
-            (&args[..args.len() - 1], true)
-        } else {
-            (args, false)
+    // A function definition on its own defines the function in the shell
+    // itself.
+    if let [ExpandedStage::Definition(function)] = stages.as_slice()
+        && !is_background_launch
+    {
+        *exit_attempted = false;
+        vars.define_function(Rc::clone(function));
+        vars.last_status = 0;
+        return Flow::Next;
+    }
+
+    // A compound command on its own, other than a subshell, runs in the
+    // shell itself, with its redirections applied once around all of it.
+    if let [ExpandedStage::Compound { body, redirections }] = stages.as_slice()
+        && !matches!(body, CompoundCommand::Subshell(_))
+        && !is_background_launch
+    {
+        let mut flow = Flow::Next;
+        let redirected = redirect::with_redirections(redirections, || {
+            flow = compound::run(body, jobs, exit_attempted, vars);
+        });
+        if let Err(err) = redirected {
+            vars.last_status = 1;
+            report("0-shell", Err(err));
         }
-    } else {
-        (args, false)
+        return flow;
+    }
+
+    let simple = match stages.as_slice() {
+        [ExpandedStage::Simple(command)] if !is_background_launch => Some(command),
+        _ => None,
     };
+    let args = simple.map_or(&[][..], |command| command.args.as_slice());
+    let command = args.first().map_or("0-shell", String::as_str);
 
-    if clean_args.is_empty() {
-        return (None, Ok(String::new()));
+    // Assignments with no command to go with them set variables in the shell
+    // itself, unless they're part of a pipeline or background job.
+    if let Some(simple) = simple
+        && args.is_empty()
+    {
+        *exit_attempted = false;
+        let result = redirect::with_redirections(&simple.redirections, || {
+            simple
+                .assignments
+                .iter()
+                .try_for_each(|(name, value)| vars.set(name, value))
+        })
+        .and_then(|assigned| assigned);
+        // As in other shells, the status is that of the last command
+        // substitution, if there was one, as in `if out=$(cmd); then`.
+        vars.last_status = match result {
+            Err(_) => 1,
+            Ok(()) if vars.substituted => vars.last_status,
+            Ok(()) => 0,
+        };
+        report(command, result.map(|()| String::new()));
+        return Flow::Next;
     }
 
-    let command = clean_args[0].as_str();
+    // A pipeline, background job, subshell, custom command or external
+    // command: every stage runs in a child process, even builtins and
+    // functions. Custom commands fork and let the child exec this program
+    // (plus command name and other args). External commands fork and let the
+    // child exec the external binary (plus other args). These set the status
+    // themselves.
+    let function = vars.function(command);
+    let Some(simple) = simple.filter(|_| function.is_some() || is_builtin(command)) else {
+        *exit_attempted = false;
+        let result = spawn_job(&stages, &pipeline.text, jobs, is_background_launch, vars);
+        report(command, result);
+        return Flow::Next;
+    };
 
     if command != "exit" {
         *exit_attempted = false;
     }
 
-    let mut exit_code: Option<i32> = None;
+    // Assignments before a builtin last only as long as it runs.
+    let saved = match vars.set_temporarily(&simple.assignments) {
+        Ok(saved) => saved,
+        Err(err) => {
+            vars.last_status = 1;
+            report(command, Err(err));
+            return Flow::Next;
+        }
+    };
 
-    let result = match command {
-        // Basic commands: internal.
-        "exit" => match exit::exit(clean_args, jobs, exit_attempted) {
-            Ok(code_str) => {
-                exit_code = Some(code_str.parse().unwrap_or(0));
-                Ok(String::new())
+    // Functions and builtins run in the shell process itself, with their
+    // redirections applied around them and undone afterwards. A function
+    // takes precedence over a builtin of the same name.
+    let mut flow = Flow::Next;
+    let redirected = redirect::with_redirections(&simple.redirections, || {
+        if let Some(function) = &function {
+            flow = call_function(function, args, jobs, exit_attempted, vars);
+            return;
+        }
+        let result = match command {
+            "exit" => match exit::exit(args, jobs, exit_attempted, vars.last_status) {
+                Ok(code_str) => {
+                    flow = Flow::Exit(code_str.parse().unwrap_or(0));
+                    Ok(String::new())
+                }
+                Err(e) => Err(e),
+            },
+            "break" | "continue" => {
+                let count = match command {
+                    "break" => r#break::r#break(args, vars.loops),
+                    _ => r#continue::r#continue(args, vars.loops),
+                };
+                vars.last_status = i32::from(count.is_err());
+                count.map(|count| {
+                    flow = match command {
+                        "break" => Flow::Break(count),
+                        _ => Flow::Continue(count),
+                    };
+                    String::new()
+                })
             }
-            Err(e) => Err(e),
-        },
-        "cd" => cd::cd(clean_args),
-        "echo" => echo::echo(clean_args),
-        "pwd" => pwd::pwd(clean_args),
+            "return" => r#return::r#return(args, vars.depth() + vars.sourced, vars.last_status)
+                .map(|status| {
+                    flow = Flow::Return(status);
+                    String::new()
+                })
+                .inspect_err(|_| vars.last_status = 1),
+            "source" | "." => source::source(args, jobs, exit_attempted, vars)
+                .map(|sourced| {
+                    flow = sourced;
+                    String::new()
+                })
+                .inspect_err(|_| vars.last_status = 1),
+            _ => run_builtin(args, jobs, vars),
+        };
+        report(command, result);
+    });
 
-        // Job control: internal.
-        "bg" => bg::bg(clean_args, jobs, current, previous),
-        "fg" => fg::fg(clean_args, jobs, current, previous),
-        "jobs" => jobs::jobs(clean_args, jobs, current, previous),
-        "kill" => kill::kill(clean_args, jobs, current, previous),
-
-        // Custom commands: fork and let the child exec this program (plus
-        // command name and other args).
-        "cat" | "cp" | "ls" | "mkdir" | "man" | "mv" | "rm" | "sleep" | "touch" => spawn_job(
-            clean_args,
-            jobs,
-            is_background_launch,
-            true,
-            current,
-            previous,
-        ),
-
-        // External commands: fork and let the child exec the external binary
-        // (plus other args).
-        _ => spawn_job(
-            clean_args,
-            jobs,
-            is_background_launch,
-            false,
-            current,
-            previous,
-        ),
-    };
+    if let Err(err) = redirected {
+        vars.last_status = 1;
+        report(command, Err(err));
+    }
 
-    (exit_code, result)
+    vars.restore(saved);
+    flow
 }
 
-pub fn run_command_as_worker(args: &[String]) {
-    if args.len() < 3 {
-        error::red_println("0-shell: Internal worker error: missing command argument");
-        return;
+// Run a function with `args` as its positional parameters, `$1` and so on.
+// Its status is that of the last command it ran, or the one given to
+// `return`. `break` and `continue` don't reach loops outside it, but `exit`
+// still ends the shell.
+pub fn call_function(
+    function: &Function,
+    args: &[String],
+    jobs: &mut JobTable,
+    exit_attempted: &mut bool,
+    vars: &mut Variables,
+) -> Flow {
+    if vars.depth() >= MAX_FUNCTION_DEPTH {
+        vars.last_status = 1;
+        report(
+            &function.name,
+            Err(String::from("Maximum function nesting level exceeded")),
+        );
+        return Flow::Next;
     }
 
-    let command = args[2].as_str();
-    let clean_args = match apply_fd_redirections(&args[2..]) {
-        Ok(filtered) => filtered,
-        Err(err) => {
-            error::handle_error(command, err);
-            return;
+    let positional = std::mem::replace(&mut vars.positional, args[1..].to_vec());
+    let loops = std::mem::take(&mut vars.loops);
+    vars.push_scope();
+
+    let flow = run_command(&function.body, false, jobs, exit_attempted, vars);
+
+    vars.pop_scope();
+    vars.loops = loops;
+    vars.positional = positional;
+
+    match flow {
+        Flow::Return(status) => {
+            vars.last_status = status;
+            Flow::Next
         }
-    };
+        Flow::Exit(code) => Flow::Exit(code),
+        Flow::Next | Flow::Break(_) | Flow::Continue(_) => Flow::Next,
+    }
+}
 
-    let result = match command {
-        "cat" => cat::cat(&clean_args),
-        "cp" => cp::cp(&clean_args),
-        "ls" => ls::ls(&clean_args),
-        "mkdir" => mkdir::mkdir(&clean_args),
-        "man" => man::man(&clean_args),
-        "mv" => mv::mv(&clean_args),
-        "rm" => rm::rm(&clean_args),
-        "sleep" => sleep::sleep(&clean_args),
-        "touch" => touch::touch(&clean_args),
-        _ => Err(format!("Command not found: {}", command)),
-    };
+// Show the simple commands of a pipeline as they'll run, after expansion,
+// each preceded by `$PS4`.
+fn trace(stages: &[ExpandedStage], vars: &Variables) {
+    let prefix = vars.get("PS4").unwrap_or("+ ");
+    for stage in stages {
+        if let ExpandedStage::Simple(command) = stage {
+            let words: Vec<String> = command
+                .assignments
+                .iter()
+                .map(|(name, value)| format!("{name}={}", quote(value)))
+                .chain(command.args.iter().map(|arg| quote(arg)))
+                .collect();
+            if !words.is_empty() {
+                eprintln!("{prefix}{}", words.join(" "));
+            }
+        }
+    }
+}
 
+fn report(command: &str, result: Result<String, String>) {
     match result {
         Ok(ok) => {
             if !ok.is_empty() {
-                print!("{}", &ok);
+                print!("{}", ok);
             }
         }
         Err(err) => error::handle_error(command, err),
     }
 }
 
-// Handle redirections with an explicit file descriptor (e.g. `2>&1`).
-// Returns the argv with those redirection tokens removed after applying them.
-fn apply_fd_redirections(args: &[String]) -> Result<Vec<String>, String> {
-    let mut filtered = Vec::with_capacity(args.len());
-    let mut i = 0;
-
-    while i < args.len() {
-        if i + 2 < args.len() && i > 0 {
-            if let Ok(fd) = args[i].parse::<i32>() {
-                let op = args[i + 1].as_str();
-                if op == ">" || op == ">>" {
-                    let target = &args[i + 2];
-                    apply_single_fd_redirection(fd, op, target)?;
-                    i += 3;
-                    continue;
-                }
-            }
+// Run a builtin and set the exit status: 0 if it returns output and 1 if it
+// returns an error, except that `fg` and `wait` take the status of the job
+// they wait for, `kill` and `disown` fail if any target can't be found or
+// signalled, and `let` and `test` set their own.
+fn run_builtin(
+    args: &[String],
+    jobs: &mut JobTable,
+    vars: &mut Variables,
+) -> Result<String, String> {
+    vars.last_status = 0;
+
+    let result = match args[0].as_str() {
+        // Basic commands: internal.
+        "cd" => {
+            let previous = env::current_dir().ok();
+            cd::cd(args, vars).inspect(|_| cd::update_variables(vars, previous))
         }
+        "echo" => echo::echo(args),
+        "pwd" => pwd::pwd(args),
+        "test" | "[" => test::test(args).map(|value| {
+            vars.last_status = i32::from(!value);
+            String::new()
+        }),
 
-        filtered.push(args[i].clone());
-        i += 1;
-    }
+        // Loops: internal. `run_command` handles these itself; in a pipeline,
+        // they have no effect.
+        "break" => r#break::r#break(args, vars.loops).map(|_| String::new()),
+        "continue" => r#continue::r#continue(args, vars.loops).map(|_| String::new()),
+
+        // Functions: internal. As with loops, `run_command` handles `return`
+        // itself.
+        "declare" => declare::declare(args, vars),
+        "local" => local::local(args, vars),
+        "return" => r#return::r#return(args, vars.depth() + vars.sourced, vars.last_status)
+            .map(|_| String::new()),
 
-    Ok(filtered)
+        // Job control: internal.
+        "bg" => bg::bg(args, jobs, vars.interactive),
+        "disown" => disown::disown(args, jobs, vars.interactive, &mut vars.last_status),
+        "fg" => fg::fg(args, jobs, vars.interactive, &mut vars.last_status),
+        "jobs" => jobs::jobs(args, jobs, vars.interactive),
+        "kill" => kill::kill(args, jobs, vars.interactive, &mut vars.last_status),
+        "wait" => wait::wait(args, jobs, vars),
+
+        // Variables, aliases and options: internal.
+        "alias" => alias::alias(args, vars),
+        "export" => export::export(args, vars),
+        "let" => r#let::r#let(args, vars),
+        "readonly" => readonly::readonly(args, vars),
+        "set" => set::set(args, vars),
+        "shopt" => shopt::shopt(args, vars),
+        "unalias" => unalias::unalias(args, vars),
+        "unset" => unset::unset(args, vars),
+
+        // Signals: internal.
+        "trap" => trap::trap(args, vars),
+
+        command => Err(format!("Command not found: {}", command)),
+    };
+
+    if result.is_err() {
+        // As in other shells, a `test` expression that can't be evaluated
+        // is told apart from one that's false.
+        vars.last_status = if matches!(args[0].as_str(), "test" | "[") {
+            2
+        } else {
+            1
+        };
+    }
+    result
 }
 
-fn apply_single_fd_redirection(fd: i32, op: &str, target: &str) -> Result<(), String> {
-    if fd < 0 {
-        return Err(String::from("Invalid file descriptor"));
+// Run a builtin that's a stage of a pipeline, in the forked child that
+// represents it. Returns the exit code for the child.
+pub fn run_builtin_in_subshell(args: &[String], jobs: &mut JobTable, vars: &mut Variables) -> i32 {
+    // Nothing but redirections, e.g. `< file`.
+    if args.is_empty() {
+        return 0;
     }
 
-    if let Some(dup_target) = target.strip_prefix('&') {
-        let target_fd = dup_target
-            .parse::<i32>()
-            .map_err(|_| format!("Bad file descriptor: {target}"))?;
+    let command = args[0].as_str();
 
-        let res = unsafe { libc::dup2(target_fd, fd) };
-        if res == -1 {
-            return Err(std::io::Error::last_os_error().to_string());
-        }
-        return Ok(());
+    if let Some(function) = vars.function(command) {
+        let flow = call_function(&function, args, jobs, &mut true, vars);
+        return flow.exit_status(vars.last_status);
     }
 
-    let mut options = OpenOptions::new();
-    options.write(true).create(true);
-
-    if op == ">>" {
-        options.append(true);
+    let result = if command == "exit" {
+        // A subshell has no stopped jobs of its own to warn about.
+        match exit::exit(args, &[], &mut true, vars.last_status) {
+            Ok(code_str) => return code_str.parse().unwrap_or(0),
+            Err(e) => Err(e),
+        }
+    } else if command == "source" || command == "." {
+        match source::source(args, jobs, &mut true, vars) {
+            Ok(flow) => return flow.exit_status(vars.last_status),
+            Err(e) => {
+                vars.last_status = 1;
+                Err(e)
+            }
+        }
     } else {
-        options.truncate(true);
+        run_builtin(args, jobs, vars)
+    };
+
+    report(command, result);
+    vars.last_status
+}
+
+// Returns the exit code for the worker process.
+pub fn run_command_as_worker(args: &[String]) -> i32 {
+    if args.len() < 3 {
+        error::red_println("0-shell: Internal worker error: missing command argument");
+        return 1;
     }
 
-    let file = options
-        .open(target)
-        .map_err(|e| format!("Failed to open redirect target `{target}`: {e}"))?;
+    let command = args[2].as_str();
+    let clean_args = &args[2..];
 
-    let res = unsafe { libc::dup2(file.as_raw_fd(), fd) };
-    if res == -1 {
-        return Err(std::io::Error::last_os_error().to_string());
+    // `nohup` becomes the command it runs, so only returns if it can't.
+    if command == "nohup" {
+        let (err, status) = nohup::nohup(clean_args);
+        error::handle_error(command, err);
+        return status;
     }
+    // `ls` reports each missing path itself, and still lists the others.
+    if command == "ls" {
+        return ls::ls(clean_args);
+    }
+
+    let result = match command {
+        "cat" => cat::cat(clean_args),
+        "cp" => cp::cp(clean_args),
+        "mkdir" => mkdir::mkdir(clean_args),
+        "man" => man::man(clean_args),
+        "mv" => mv::mv(clean_args),
+        "rm" => rm::rm(clean_args),
+        "sleep" => sleep::sleep(clean_args),
+        "touch" => touch::touch(clean_args),
+        _ => Err(format!("Command not found: {}", command)),
+    };
 
-    Ok(())
+    match result {
+        Ok(ok) => {
+            if !ok.is_empty() {
+                print!("{}", &ok);
+            }
+            0
+        }
+        Err(err) => {
+            error::handle_error(command, err);
+            1
+        }
+    }
 }
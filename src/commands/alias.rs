@@ -0,0 +1,82 @@
+use crate::variables::Variables;
+
+pub const USAGE: &str = "Usage:\talias [-p] [NAME[=VALUE] ...]";
+
+// Define aliases, or show them: all of them if there are no names.
+pub fn alias(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    let args: Vec<&String> = input.iter().skip(1).filter(|arg| *arg != "-p").collect();
+
+    if args.is_empty() {
+        return Ok(vars
+            .aliases()
+            .iter()
+            .map(|(name, value)| show(name, value))
+            .collect());
+    }
+
+    let mut output = String::new();
+    let mut errors = Vec::new();
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) if is_alias_name(name) => vars.set_alias(name, value),
+            Some(_) => errors.push(format!("`{arg}': invalid alias name")),
+            None => match vars.alias(arg) {
+                Some(value) => output.push_str(&show(arg, value)),
+                None => errors.push(format!("{arg}: not found")),
+            },
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(output)
+    } else {
+        // What was found is still shown.
+        print!("{output}");
+        Err(errors.join("\n"))
+    }
+}
+
+// An alias, as it would be defined, with its value in single quotes.
+fn show(name: &str, value: &str) -> String {
+    format!("alias {name}='{}'\n", value.replace('\'', r"'\''"))
+}
+
+// An alias can't contain anything that would end a word or be expanded, nor
+// `/` or `=`.
+fn is_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "/=$`\\'\"|&;()<>".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::alias;
+    use crate::{string_vec, variables::Variables};
+
+    #[test]
+    fn alias_defines_and_shows_aliases() {
+        let mut vars = Variables::default();
+        assert_eq!(
+            alias(
+                &string_vec!["alias", "ll=ls -l", "..=cd ..", "q=it's"],
+                &mut vars
+            ),
+            Ok(String::new())
+        );
+        assert_eq!(vars.alias(".."), Some("cd .."));
+        assert_eq!(
+            alias(&string_vec!["alias", "ll"], &mut vars),
+            Ok(String::from("alias ll='ls -l'\n"))
+        );
+        assert_eq!(
+            alias(&string_vec!["alias"], &mut vars),
+            Ok(String::from(
+                "alias ..='cd ..'\nalias ll='ls -l'\nalias q='it'\\''s'\n"
+            ))
+        );
+        assert!(alias(&string_vec!["alias", "a/b=x"], &mut vars).is_err());
+        assert!(alias(&string_vec!["alias", "nosuch"], &mut vars).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use std::{collections::HashSet, io};
 
 use crate::{
-    commands::jobs::{self, Job, State},
+    commands::jobs::{self, JobTable, State},
     error,
 };
 
@@ -24,24 +24,16 @@ impl Resumer for RealResumer {
 
 pub const USAGE: &str = "Usage:\tbg [jobspec ...]";
 
-pub fn bg(
-    input: &[String],
-    jobs: &mut Vec<Job>,
-    current: &mut usize,
-    previous: &mut usize,
-) -> Result<String, String> {
-    bg_with_resumer(input, jobs, current, previous, &RealResumer)
+pub fn bg(input: &[String], jobs: &mut JobTable, notify: bool) -> Result<String, String> {
+    jobs::check_background_jobs(jobs, notify);
+    bg_with_resumer(input, jobs, &RealResumer)
 }
 
 fn bg_with_resumer(
     input: &[String],
-    jobs: &mut Vec<Job>,
-    current: &mut usize,
-    previous: &mut usize,
+    jobs: &mut JobTable,
     resumer: &dyn Resumer,
 ) -> Result<String, String> {
-    jobs::check_background_jobs(jobs, current, previous);
-
     let mut failures = String::new();
     let mut successes = Vec::new();
 
@@ -51,22 +43,10 @@ fn bg_with_resumer(
     let mut target_ids = HashSet::new();
 
     if input.len() < 2 {
-        let fallback = if *current > 0 {
-            Some(*current)
-        } else if *previous > 0 {
-            Some(*previous)
-        } else {
-            None
-        };
-
-        if let Some(id) = fallback {
-            target_ids.insert(id);
-        } else {
-            return Err(String::from("Current: no such job"));
-        }
+        target_ids.insert(jobs.resolve("%+")?);
     } else {
         for item in &input[1..] {
-            match jobs::resolve_jobspec(item, *current, *previous) {
+            match jobs.resolve(item) {
                 Ok(id) => {
                     target_ids.insert(id);
                 }
@@ -83,24 +63,18 @@ fn bg_with_resumer(
         let pgid = job.pgid;
 
         if target_ids.contains(&jid) {
-            if matches!(job.state, State::Stopped) {
+            if job.state.is_stopped() {
                 // Send SIGCONT to the process group (negative PID)
                 // so that all members of a pipeline resume together.
                 if let Err(err) = resumer.resume(pgid) {
-                    failures.push_str(&format!(
-                        "Failed to resume job {jid} (pid {pgid}): {err}\n"
-                    ));
+                    failures.push_str(&format!("Failed to resume job {jid} (pid {pgid}): {err}\n"));
                     failure_count += 1;
                     target_ids.remove(&jid);
                     continue;
                 }
                 job.state = State::Running;
-                if jid != *current {
-                    *previous = *current;
-                    *current = jid;
-                }
                 success_count += 1;
-                successes.push(&*job);
+                successes.push(jid);
             } else {
                 failures.push_str(&format!("Job is not stopped: {jid}\n"));
                 failure_count += 1;
@@ -114,8 +88,11 @@ fn bg_with_resumer(
         failure_count += 1;
     }
 
-    for job in successes {
-        println!("[{}]+\t{} &", job.jid, job.command);
+    for jid in successes {
+        jobs.make_current(jid);
+        if let Some(job) = jobs.get(jid) {
+            println!("[{}]+\t{} &", job.jid, job.command);
+        }
     }
 
     if !failures.is_empty() {
@@ -128,6 +105,7 @@ fn bg_with_resumer(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::jobs::Job;
 
     struct NoopResumer;
 
@@ -139,117 +117,98 @@ mod tests {
 
     #[test]
     fn test_bg_updates_stopped_jobs() {
-        let mut jobs = vec![
-            Job {
-                jid: 1,
-                pgid: 101,
-                state: State::Stopped,
-                command: String::from("sleep 100"),
-            },
-            Job {
-                jid: 2,
-                pgid: 102,
-                state: State::Running,
-                command: String::from("ls"),
-            },
+        let jobs = vec![
+            Job::new(
+                1,
+                101,
+                String::from("sleep 100"),
+                State::Stopped(libc::SIGTSTP),
+            ),
+            Job::new(2, 102, String::from("ls"), State::Running),
         ];
 
         let input = vec![String::from("bg"), String::from("1")];
 
-        let mut current = 0;
-        let mut previous = 0;
-        let result = bg_with_resumer(&input, &mut jobs, &mut current, &mut previous, &NoopResumer);
+        let mut jobs = JobTable::with_jobs(jobs, 0, 0);
+        let result = bg_with_resumer(&input, &mut jobs, &NoopResumer);
 
         assert!(result.is_ok());
         assert!(matches!(jobs[0].state, State::Running));
         assert!(matches!(jobs[1].state, State::Running));
-        assert_eq!(current, 1);
-        assert_eq!(previous, 0);
+        assert_eq!(jobs.current(), 1);
+        assert_eq!(jobs.previous(), 0);
     }
 
     #[test]
     fn test_bg_no_args_resumes_current() {
-        let mut jobs = vec![
-            Job {
-                jid: 1,
-                pgid: 101,
-                state: State::Stopped,
-                command: String::from("sleep 100"),
-            },
-            Job {
-                jid: 2,
-                pgid: 102,
-                state: State::Stopped,
-                command: String::from("sleep 200"),
-            },
+        let jobs = vec![
+            Job::new(
+                1,
+                101,
+                String::from("sleep 100"),
+                State::Stopped(libc::SIGTSTP),
+            ),
+            Job::new(
+                2,
+                102,
+                String::from("sleep 200"),
+                State::Stopped(libc::SIGTSTP),
+            ),
         ];
 
-        let mut current = 2;
-        let mut previous = 1;
+        let mut jobs = JobTable::with_jobs(jobs, 2, 1);
         let input = vec![String::from("bg")];
 
-        let result = bg_with_resumer(&input, &mut jobs, &mut current, &mut previous, &NoopResumer);
+        let result = bg_with_resumer(&input, &mut jobs, &NoopResumer);
 
         assert!(result.is_ok());
         assert!(matches!(jobs[1].state, State::Running));
-        assert_eq!(current, 2);
-        assert_eq!(previous, 1);
+        assert_eq!(jobs.current(), 2);
+        assert_eq!(jobs.previous(), 1);
     }
 
     #[test]
     fn test_bg_supports_percent_syntax() {
-        let mut jobs = vec![Job {
-            jid: 1,
-            pgid: 101,
-            state: State::Stopped,
-            command: String::from("sleep 100"),
-        }];
+        let jobs = vec![Job::new(
+            1,
+            101,
+            String::from("sleep 100"),
+            State::Stopped(libc::SIGTSTP),
+        )];
 
         let input = vec![String::from("bg"), String::from("%1")];
 
-        let mut current = 0;
-        let mut previous = 0;
-        let result = bg_with_resumer(&input, &mut jobs, &mut current, &mut previous, &NoopResumer);
+        let mut jobs = JobTable::with_jobs(jobs, 0, 0);
+        let result = bg_with_resumer(&input, &mut jobs, &NoopResumer);
 
         assert!(result.is_ok());
         assert!(matches!(jobs[0].state, State::Running));
-        assert_eq!(current, 1);
+        assert_eq!(jobs.current(), 1);
     }
 
     #[test]
     fn test_bg_ignores_missing_pids() {
-        let mut jobs = vec![Job {
-            jid: 1,
-            pgid: 101,
-            state: State::Stopped,
-            command: String::from("sleep"),
-        }];
+        let jobs = vec![Job::new(
+            1,
+            101,
+            String::from("sleep"),
+            State::Stopped(libc::SIGTSTP),
+        )];
         let input = vec![String::from("bg"), String::from("999")];
 
-        let mut current = 0;
-        let mut previous = 0;
-        let _ = bg_with_resumer(&input, &mut jobs, &mut current, &mut previous, &NoopResumer);
+        let mut jobs = JobTable::with_jobs(jobs, 0, 0);
+        let _ = bg_with_resumer(&input, &mut jobs, &NoopResumer);
 
-        assert!(matches!(jobs[0].state, State::Stopped));
-        assert_eq!(current, 0);
-        assert_eq!(previous, 0);
+        assert!(matches!(jobs[0].state, State::Stopped(libc::SIGTSTP)));
+        assert_eq!(jobs.current(), 0);
+        assert_eq!(jobs.previous(), 0);
     }
 
     #[test]
     fn test_generates_correct_number_of_failure_messages() {
-        let mut jobs = vec![
-            Job {
-                jid: 1,
-                pgid: 101,
-                state: State::Stopped,
-                command: String::from("sleep"),
-            },
-            Job {
-                jid: 2,
-                pgid: 102,
-                state: State::Running,
-                command: String::from("ls"),
-            },
+        let jobs = vec![
+            Job::new(1, 101, String::from("sleep"), State::Stopped(libc::SIGTSTP)),
+            Job::new(2, 102, String::from("ls"), State::Running),
         ];
         let input = vec![
             String::from("bg"),
@@ -259,9 +218,8 @@ mod tests {
             String::from("also_not_a_job_id"),
         ];
 
-        let mut current = 0;
-        let mut previous = 0;
-        let result = bg_with_resumer(&input, &mut jobs, &mut current, &mut previous, &NoopResumer);
+        let mut jobs = JobTable::with_jobs(jobs, 0, 0);
+        let result = bg_with_resumer(&input, &mut jobs, &NoopResumer);
         let output = result.expect("bg command failed");
         let parts: Vec<&str> = output.split(':').collect();
         let success_count: usize = parts[0].parse().expect("parsing success count failed");
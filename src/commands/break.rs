@@ -0,0 +1,51 @@
+pub const USAGE: &str = "Usage:\tbreak [n]";
+
+// Leave the `n` innermost enclosing loops, by default just the one. Returns
+// how many loops to leave, which can't be more than there are.
+pub fn r#break(input: &[String], loops: usize) -> Result<usize, String> {
+    loop_count(input, loops, USAGE)
+}
+
+// The count of loops that `break` or `continue` applies to.
+pub fn loop_count(input: &[String], loops: usize, usage: &str) -> Result<usize, String> {
+    if input.len() > 2 {
+        return Err(format!("Too many arguments\n{usage}"));
+    }
+
+    let count = match input.get(1) {
+        None => 1,
+        Some(arg) => match arg.parse::<i64>() {
+            Ok(count) if count > 0 => usize::try_from(count).unwrap_or(usize::MAX),
+            Ok(_) => return Err(format!("{arg}: Loop count out of range")),
+            Err(_) => return Err(format!("Numeric argument required: {arg}")),
+        },
+    };
+
+    if loops == 0 {
+        return Err(String::from(
+            "Only meaningful in a `for', `while', or `until' loop",
+        ));
+    }
+    Ok(count.min(loops))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::r#break;
+    use crate::string_vec;
+
+    #[test]
+    fn break_counts_loops() {
+        assert_eq!(r#break(&string_vec!["break"], 2), Ok(1));
+        assert_eq!(r#break(&string_vec!["break", "2"], 3), Ok(2));
+        assert_eq!(
+            r#break(&string_vec!["break", "5"], 2),
+            Ok(2),
+            "a count beyond the loops there are should leave them all"
+        );
+        assert!(r#break(&string_vec!["break"], 0).is_err());
+        assert!(r#break(&string_vec!["break", "0"], 1).is_err());
+        assert!(r#break(&string_vec!["break", "x"], 1).is_err());
+        assert!(r#break(&string_vec!["break", "1", "2"], 1).is_err());
+    }
+}
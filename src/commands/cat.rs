@@ -1,18 +1,14 @@
 use std::{
     fs::File,
-    io::{self, Read, Write},
+    io::{self, IsTerminal, Read, Write},
     path::Path,
 };
 
 use termion::{event::Key, input::TermRead, raw::IntoRawMode};
 
-use crate::redirect;
-
 pub const USAGE: &str = "Usage:\tcat [FILE]...";
 
 pub fn cat(input: &[String]) -> Result<String, String> {
-    let (sources, targets) = redirect::separate_sources_from_targets(input);
-
     // Handle input from stdin.
     if input.len() < 2 {
         return match get_input() {
@@ -28,57 +24,33 @@ pub fn cat(input: &[String]) -> Result<String, String> {
         };
     }
 
-    let (concatenated_contents, mut errors) = assemble_contents(sources);
-
-    if targets.is_empty() {
-        print!("{}", concatenated_contents);
-    } else {
-        redirect(targets, &concatenated_contents, &mut errors);
-    }
+    let (concatenated_contents, errors) = assemble_contents(&input[1..]);
 
     if errors.is_empty() {
         Ok(concatenated_contents)
     } else {
+        // Still show whatever could be read.
+        print!("{}", concatenated_contents);
         Err(errors.join("\n"))
     }
 }
 
-fn redirect(targets: Vec<[&String; 2]>, concatenated_contents: &str, errors: &mut Vec<String>) {
-    for &target in targets.iter() {
-        let target_path = Path::new(target[1]);
-        if target_path.is_dir() {
-            errors.push(format!("0-shell: Is a directory: {}", target[1]));
-            break;
-        }
+fn get_input() -> Result<String, String> {
+    let stdin = io::stdin();
 
-        if !target_path.exists() || target[0] == ">" {
-            match File::create(target_path) {
-                Ok(mut file) => {
-                    if let Err(_) = file.write_all(concatenated_contents.as_bytes()) {
-                        errors.push(format!("0-shell: Failed to write to file: {}", target[1]));
-                    }
-                }
-                Err(_) => {
-                    errors.push(format!("0-shell: Failed to create file: {}", target[1]));
-                }
-            }
-        } else {
-            match File::options().append(true).create(true).open(target_path) {
-                Ok(mut file) => {
-                    if let Err(_) = file.write_all(concatenated_contents.as_bytes()) {
-                        errors.push(format!("0-shell: Failed to append to file: {}", target[1]));
-                    }
-                }
-                Err(_) => {
-                    errors.push(format!("0-shell: Failed to open file: {}", target[1]));
-                }
-            }
-        }
+    // When reading from a pipe or writing into one, there's no terminal to
+    // echo keystrokes to, so just read until end of file.
+    if !stdin.is_terminal() || !io::stdout().is_terminal() {
+        let mut contents = String::new();
+        return match stdin.lock().read_to_string(&mut contents) {
+            Ok(_) => Ok(contents),
+            Err(e) => Err(format!(
+                "Stdin error: {}",
+                e.to_string().split(" (os ").next().unwrap_or(" ")
+            )),
+        };
     }
-}
 
-fn get_input() -> Result<String, String> {
-    let stdin = io::stdin();
     let mut stdout = io::stdout()
         .lock()
         .into_raw_mode()
@@ -127,11 +99,11 @@ fn format(s: &str, e: io::Error) -> String {
     )
 }
 
-fn assemble_contents(sources: Vec<&String>) -> (String, Vec<String>) {
+fn assemble_contents(sources: &[String]) -> (String, Vec<String>) {
     let mut concatenated_contents = String::new();
     let mut errors = Vec::new();
 
-    for &path_str in sources.iter() {
+    for path_str in sources {
         let path = Path::new(path_str);
         if path.exists() {
             if path.is_file() {
@@ -162,7 +134,7 @@ fn assemble_contents(sources: Vec<&String>) -> (String, Vec<String>) {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs, io::Write, path::Path};
+    use std::{fs, io::Write};
 
     use super::cat;
     use crate::{string_vec, test_helpers::TempStore};
@@ -177,62 +149,6 @@ mod tests {
         assert_eq!(result, "Howdie, world!\n");
     }
 
-    #[test]
-    fn cat_rediect_from_one_file_to_one_new_file() {
-        let temp_store = TempStore::new(3);
-        let source = &temp_store.store[0];
-        let expected = "Now then, world?\n";
-
-        fs::write(source, expected).expect("failed to create source file");
-
-        redirect_helper(source, expected, ">");
-        redirect_helper(source, expected, ">>");
-    }
-
-    fn redirect_helper(source: &str, expected: &str, op: &str) {
-        let target = &TempStore::new(1).store[0];
-
-        let input = string_vec!["cat", source, op, target];
-        let result = cat(&input);
-
-        assert!(result.is_ok(), "`cat` with `{}` should be ok", op);
-        assert!(
-            Path::new(target).exists(),
-            "failed to create `{}` target file",
-            op
-        );
-
-        let contents = fs::read_to_string(target).expect("failed to read from target file");
-        assert_eq!(
-            contents, expected,
-            "contents of new `{}` target file do should match those of source file",
-            op
-        );
-    }
-
-    #[test]
-    fn cat_append_to_one_existing_file() {
-        let temp_store = TempStore::new(3);
-        let source = &temp_store.store[0];
-        let target = &temp_store.store[1];
-
-        let expected = "Hello, world!\n";
-
-        fs::write(source, "world!\n").expect("failed to write to source file");
-        fs::write(target, "Hello, ").expect("failed to write to target file");
-
-        let input = string_vec!["cat", source, ">>", target];
-        let result = cat(&input);
-
-        assert!(result.is_ok(), "`cat` with `>>` should be ok");
-
-        let contents = fs::read_to_string(target).expect("failed to read from target file");
-        assert_eq!(
-            contents, expected,
-            "target file should have source contents appended to it"
-        );
-    }
-
     #[test]
     fn cat_two_source_files() {
         let temp_store = TempStore::new(2);
@@ -257,60 +173,6 @@ mod tests {
         assert_eq!(result, "Hello, world!");
     }
 
-    #[test]
-    fn cat_two_sources_and_two_targets() {
-        let temp_store = TempStore::new(4);
-        let source1_string = &temp_store.store[0];
-        let source2_string = &temp_store.store[1];
-        let target1_string = &temp_store.store[2];
-        let target2_string = &temp_store.store[3];
-
-        let mut source1 =
-            fs::File::create(source1_string).expect("failed to create 1st source file");
-        source1
-            .write_all(b"Hello, ")
-            .expect("failed to write to 1st source file");
-
-        let mut source2 =
-            fs::File::create(source2_string).expect("failed to create 2nd source file");
-        source2
-            .write_all(b"world!")
-            .expect("failed to write to 2nd source file");
-
-        let mut target1 =
-            fs::File::create(target1_string).expect("failed to create 1st target file");
-        target1
-            .write_all(b"Oy! ")
-            .expect("failed to write to 1st target file");
-
-        let mut _target2 =
-            fs::File::create(target2_string).expect("failed to create 2nd target file");
-
-        let input = string_vec![
-            "cat",
-            source1_string,
-            source2_string,
-            ">>",
-            target1_string,
-            ">>",
-            target2_string
-        ];
-        let result = cat(&input).expect("`cat` should be ok");
-
-        assert_eq!(result, "Hello, world!");
-
-        let contents1 =
-            fs::read_to_string(target1_string).expect("failed to read from 1st target file");
-        assert_eq!(
-            contents1, "Oy! Hello, world!",
-            "1st target should have combined contents of both sources"
-        );
-
-        let contents2 =
-            fs::read_to_string(target2_string).expect("failed to read from 2nd target file");
-        assert_eq!(contents2, "Hello, world!");
-    }
-
     #[test]
     fn cat_one_nonexistent_source_file_fails() {
         let input = string_vec!["cat", "nonexistent.txt"];
@@ -331,25 +193,4 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Is a directory"));
     }
-
-    #[test]
-    fn cat_redirect_target_directory_fails_with_shell_prefix() {
-        let temp_store = TempStore::new(2);
-        let source = &temp_store.store[0];
-        let target_dir = &temp_store.store[1];
-
-        fs::write(source, "hello").expect("failed to write source file");
-        fs::create_dir(target_dir).expect("failed to create target directory");
-
-        let input = string_vec!["cat", source, ">", target_dir];
-        let result = cat(&input);
-
-        assert!(result.is_err(), "redirecting to directory should fail");
-        let err = result.unwrap_err();
-        assert!(
-            err.contains("0-shell: Is a directory"),
-            "error should include shell-prefixed directory message, got: {}",
-            err
-        );
-    }
 }
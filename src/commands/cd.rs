@@ -1,16 +1,21 @@
-use std::env;
+use std::{env, path::PathBuf};
 
 use home;
 
+use crate::variables::Variables;
+
 pub const USAGE: &str = "Usage:\tcd [DIRECTORY]";
 
-pub fn cd(input: &[String]) -> Result<String, String> {
+// With no directory, go to `$HOME`, or to the user's home directory if it's
+// unset, just as `~` does.
+pub fn cd(input: &[String], vars: &Variables) -> Result<String, String> {
     is_input_len_at_least_two(input)?;
 
     let path: &String = match input.get(1) {
         Some(path) => path,
         None => {
-            if let Some(home_path) = home::home_dir() {
+            let home_path = vars.get("HOME").map(PathBuf::from).or_else(home::home_dir);
+            if let Some(home_path) = home_path {
                 env::set_current_dir(&home_path).map_err(|err| {
                     format!("Failed to change directory: {}", err)
                         .split(" (os ")
@@ -35,6 +40,19 @@ pub fn cd(input: &[String]) -> Result<String, String> {
     }
 }
 
+// After a successful `cd`, record the new working directory in `PWD` and the
+// previous one in `OLDPWD`, for `~+` and `~-`.
+pub fn update_variables(vars: &mut Variables, previous: Option<PathBuf>) {
+    let mut update = |name: &str, dir: Option<PathBuf>| {
+        if let Some(dir) = dir {
+            // A readonly variable just stays as it is.
+            let _ = vars.set(name, &dir.to_string_lossy());
+        }
+    };
+    update("OLDPWD", previous);
+    update("PWD", env::current_dir().ok());
+}
+
 fn is_input_len_at_least_two(input: &[String]) -> Result<(), String> {
     if input.len() > 2 {
         return Err(format!("Too many arguments\n{}", USAGE));
@@ -49,6 +67,7 @@ mod test {
     use super::cd;
     use crate::string_vec;
     use crate::test_helpers;
+    use crate::variables::Variables;
 
     #[test]
     fn cd_and_return() {
@@ -58,13 +77,29 @@ mod test {
         fs::create_dir(dest_path).expect("failed to create temp folder");
         let origin = env::current_dir().expect("failed to get current directory");
 
+        let vars = &mut Variables::default();
         let mut input = string_vec!["cd", destination];
-        assert!(cd(&input).is_ok(), "`cd {}` should be ok", destination);
+        assert!(
+            cd(&input, vars).is_ok(),
+            "`cd {}` should be ok",
+            destination
+        );
 
         input = string_vec!["cd", ".."];
-        assert!(cd(&input).is_ok(), "`cd ..` should be ok");
+        assert!(cd(&input, vars).is_ok(), "`cd ..` should be ok");
 
         let current_dir = env::current_dir().expect("failed to get current directory");
         assert_eq!(origin, current_dir);
+
+        vars.set("HOME", destination).unwrap();
+        let result = cd(&string_vec!["cd"], vars);
+        let current_dir = env::current_dir().expect("failed to get current directory");
+        env::set_current_dir(&origin).expect("failed to set current directory");
+        assert!(result.is_ok(), "`cd` should be ok");
+        assert_eq!(
+            current_dir.canonicalize().ok(),
+            dest_path.canonicalize().ok(),
+            "`cd` should go to `$HOME`"
+        );
     }
 }
@@ -0,0 +1,9 @@
+use super::r#break::loop_count;
+
+pub const USAGE: &str = "Usage:\tcontinue [n]";
+
+// Go on to the next iteration of the `n`th enclosing loop, by default the
+// innermost one, leaving any loops inside it.
+pub fn r#continue(input: &[String], loops: usize) -> Result<usize, String> {
+    loop_count(input, loops, USAGE)
+}
@@ -0,0 +1,95 @@
+use crate::variables::Variables;
+
+pub const USAGE: &str = "Usage:\tdeclare -f|-F [NAME ...]";
+
+// List the shell's functions, or those named: with `-f`, their definitions,
+// and with `-F`, just their names.
+pub fn declare(input: &[String], vars: &Variables) -> Result<String, String> {
+    let mut definitions = None;
+    let mut names = Vec::new();
+
+    for arg in input.iter().skip(1) {
+        if arg.starts_with('-') && names.is_empty() {
+            for c in arg.chars().skip(1) {
+                match c {
+                    'f' => definitions = Some(definitions.unwrap_or(true)),
+                    'F' => definitions = Some(false),
+                    _ => return Err(format!("Invalid option -- '{c}'\n{USAGE}")),
+                }
+            }
+        } else {
+            names.push(arg.as_str());
+        }
+    }
+
+    let Some(definitions) = definitions else {
+        return Err(format!("Only functions can be declared\n{USAGE}"));
+    };
+
+    let show = |name: &str, body: &str| {
+        if definitions {
+            format!("{name} () {body}\n")
+        } else {
+            format!("declare -f {name}\n")
+        }
+    };
+
+    if names.is_empty() {
+        return Ok(vars
+            .functions()
+            .map(|function| show(&function.name, &function.body.text))
+            .collect());
+    }
+
+    let mut output = String::new();
+    let mut errors = Vec::new();
+    for name in names {
+        match vars.function(name) {
+            Some(function) => output.push_str(&show(name, &function.body.text)),
+            None => errors.push(format!("{name}: not found")),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(output)
+    } else {
+        // What was found is still listed.
+        print!("{output}");
+        Err(errors.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::declare;
+    use crate::{
+        commands::{jobs::JobTable, run_list, unset::unset},
+        parser::parse,
+        string_vec,
+        variables::Variables,
+    };
+
+    #[test]
+    fn declare_lists_functions() {
+        let mut vars = Variables::default();
+        let list = parse("f() { let x++; }; g() ( pwd )").expect("input should parse");
+        run_list(&list, &mut JobTable::default(), &mut false, &mut vars);
+
+        assert_eq!(
+            declare(&string_vec!["declare", "-f"], &vars),
+            Ok(String::from("f () { let x++; }\ng () ( pwd )\n"))
+        );
+        assert_eq!(
+            declare(&string_vec!["declare", "-F", "g"], &vars),
+            Ok(String::from("declare -f g\n"))
+        );
+        assert!(declare(&string_vec!["declare", "-f", "h"], &vars).is_err());
+
+        unset(&string_vec!["unset", "-f", "f"], &mut vars).expect("`unset` should be ok");
+        unset(&string_vec!["unset", "g"], &mut vars).expect("`unset` should be ok");
+        assert_eq!(
+            declare(&string_vec!["declare", "-F"], &vars),
+            Ok(String::new())
+        );
+    }
+}
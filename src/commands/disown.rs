@@ -0,0 +1,133 @@
+use crate::{
+    commands::jobs::{self, JobTable, State},
+    error,
+};
+
+pub const USAGE: &str = "Usage:\tdisown [-ahr] [jobspec ...]";
+pub const OPTIONS_USAGE: &str = "\r\n-a      -- all jobs\r\n-h      -- keep the jobs, but don't send them SIGHUP when the shell exits\r\n-r      -- only running jobs";
+
+// Remove jobs from the table, so that the shell no longer keeps track of
+// them, and doesn't hang them up when it exits. With no jobspecs, or `-a`,
+// the current job, or all of them. Each jobspec that names no job is
+// reported, and sets `last_status` to 1.
+pub fn disown(
+    input: &[String],
+    jobs: &mut JobTable,
+    notify: bool,
+    last_status: &mut i32,
+) -> Result<String, String> {
+    jobs::check_background_jobs(jobs, notify);
+
+    let mut all = false;
+    let mut keep = false;
+    let mut running_only = false;
+    let mut specs = Vec::new();
+
+    for arg in input.iter().skip(1) {
+        if arg.starts_with('-') && arg.len() > 1 && specs.is_empty() {
+            for c in arg.chars().skip(1) {
+                match c {
+                    'a' => all = true,
+                    'h' => keep = true,
+                    'r' => running_only = true,
+                    _ => return Err(format!("Invalid option -- '{c}'\n{USAGE}")),
+                }
+            }
+        } else {
+            specs.push(arg);
+        }
+    }
+
+    let mut ids = Vec::new();
+    if !specs.is_empty() {
+        for spec in specs {
+            match jobs.resolve(spec) {
+                Ok(id) => ids.push(id),
+                Err(err) => {
+                    error::handle_error("disown", err);
+                    *last_status = 1;
+                }
+            }
+        }
+    } else if all {
+        ids = jobs.iter().map(|job| job.jid).collect();
+    } else {
+        ids.push(jobs.resolve("%+")?);
+    }
+
+    for id in ids {
+        let Some(job) = jobs.get_mut(id) else {
+            continue;
+        };
+        if running_only && job.state != State::Running {
+            continue;
+        }
+        if keep {
+            job.nohup = true;
+        } else {
+            jobs.remove(id);
+        }
+    }
+
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::disown;
+    use crate::{
+        commands::jobs::{Job, JobTable, State},
+        string_vec,
+    };
+
+    fn table(current: usize, previous: usize) -> JobTable {
+        // PIDs that can't be those of children, so that checking on the jobs
+        // leaves them alone.
+        let jobs = vec![
+            Job::new(1, i32::MAX - 2, String::from("first"), State::Running),
+            Job::new(
+                2,
+                i32::MAX - 1,
+                String::from("second"),
+                State::Stopped(libc::SIGTSTP),
+            ),
+            Job::new(3, i32::MAX, String::from("third"), State::Running),
+        ];
+        JobTable::with_jobs(jobs, current, previous)
+    }
+
+    #[test]
+    fn disown_removes_or_marks_jobs() {
+        let mut status = 0;
+        let mut jobs = table(3, 2);
+        disown(&string_vec!["disown"], &mut jobs, false, &mut status).unwrap();
+        let ids: Vec<usize> = jobs.iter().map(|job| job.jid).collect();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!((jobs.current(), jobs.previous()), (2, 1));
+
+        disown(
+            &string_vec!["disown", "-h", "%1"],
+            &mut jobs,
+            false,
+            &mut status,
+        )
+        .unwrap();
+        assert!(jobs[0].nohup && !jobs[1].nohup);
+
+        let mut jobs = table(0, 0);
+        disown(&string_vec!["disown", "-ar"], &mut jobs, false, &mut status).unwrap();
+        let ids: Vec<usize> = jobs.iter().map(|job| job.jid).collect();
+        assert_eq!(ids, [2], "only running jobs should be disowned");
+
+        disown(
+            &string_vec!["disown", "%2", "%7"],
+            &mut jobs,
+            false,
+            &mut status,
+        )
+        .unwrap();
+        assert_eq!(status, 1, "a jobspec that names no job should fail");
+        assert!(jobs.is_empty());
+        assert!(disown(&string_vec!["disown"], &mut jobs, false, &mut status).is_err());
+    }
+}
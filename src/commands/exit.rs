@@ -1,10 +1,16 @@
-use crate::commands::jobs::{Job, State};
+use crate::commands::jobs::Job;
 
 pub const USAGE: &str = "Usage:\texit [status]";
 pub const STOPPED_JOBS_WARNING: &str = "There are stopped jobs.";
 
-pub fn exit(args: &[String], jobs: &[Job], exit_attempted: &mut bool) -> Result<String, String> {
-    let has_stopped = jobs.iter().any(|j| j.state == State::Stopped);
+// With no argument, exit with the status of the last command.
+pub fn exit(
+    args: &[String],
+    jobs: &[Job],
+    exit_attempted: &mut bool,
+    last_status: i32,
+) -> Result<String, String> {
+    let has_stopped = jobs.iter().any(|j| j.state.is_stopped());
 
     if has_stopped && !*exit_attempted {
         *exit_attempted = true;
@@ -15,7 +21,7 @@ pub fn exit(args: &[String], jobs: &[Job], exit_attempted: &mut bool) -> Result<
         return Err(format!("Too many arguments\n{}", USAGE));
     }
 
-    let mut exit_code = 0;
+    let mut exit_code = last_status;
     if args.len() == 2 {
         exit_code = args[1]
             .parse::<i32>()
@@ -24,3 +30,23 @@ pub fn exit(args: &[String], jobs: &[Job], exit_attempted: &mut bool) -> Result<
 
     Ok(exit_code.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::exit;
+    use crate::string_vec;
+
+    #[test]
+    fn exit_status() {
+        assert_eq!(
+            exit(&string_vec!["exit"], &[], &mut false, 127),
+            Ok(String::from("127")),
+            "with no argument, exit should use the last status"
+        );
+        assert_eq!(
+            exit(&string_vec!["exit", "3"], &[], &mut false, 127),
+            Ok(String::from("3"))
+        );
+        assert!(exit(&string_vec!["exit", "x"], &[], &mut false, 0).is_err());
+    }
+}
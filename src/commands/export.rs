@@ -0,0 +1,105 @@
+use crate::{
+    parser::is_name,
+    variables::{Variables, quote},
+};
+
+pub const USAGE: &str = "Usage:\texport [-n] [-p] [NAME[=VALUE] ...]";
+pub const OPTIONS_USAGE: &str =
+    "\r\n-n      -- stop exporting each NAME\r\n-p      -- list exported variables";
+
+pub fn export(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    let mut unexport = false;
+    let mut names = Vec::new();
+
+    for arg in input.iter().skip(1) {
+        if arg.starts_with('-') && names.is_empty() {
+            for c in arg.chars().skip(1) {
+                match c {
+                    'n' => unexport = true,
+                    'p' => {}
+                    _ => return Err(format!("Invalid option -- '{c}'\n{USAGE}")),
+                }
+            }
+        } else {
+            names.push(arg);
+        }
+    }
+
+    if names.is_empty() {
+        return Ok(list(vars));
+    }
+
+    let mut errors = Vec::new();
+    for arg in names {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        if !is_name(name) {
+            errors.push(format!("`{arg}': not a valid identifier"));
+            continue;
+        }
+        if let Some(value) = value
+            && let Err(err) = vars.set(name, value)
+        {
+            errors.push(err);
+            continue;
+        }
+        vars.export(name, !unexport);
+    }
+
+    if errors.is_empty() {
+        Ok(String::new())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+// The exported variables, in a form that could be read back in.
+fn list(vars: &Variables) -> String {
+    let mut output = String::new();
+    for (name, variable) in vars.iter().filter(|(_, v)| v.exported) {
+        match &variable.value {
+            Some(value) => output.push_str(&format!("export {name}={}\n", quote(value))),
+            None => output.push_str(&format!("export {name}\n")),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::export;
+    use crate::{string_vec, variables::Variables};
+
+    #[test]
+    fn export_sets_and_lists_variables() {
+        let mut vars = Variables::default();
+        vars.set("LOCAL", "a b").unwrap();
+
+        export(&string_vec!["export", "LOCAL", "NEW=1", "EMPTY"], &mut vars)
+            .expect("`export` should be ok");
+        assert_eq!(vars.get("NEW"), Some("1"));
+        assert_eq!(vars.environment(&[]), Ok(string_vec!["LOCAL=a b", "NEW=1"]));
+        assert_eq!(
+            export(&string_vec!["export"], &mut vars),
+            Ok(String::from(
+                "export EMPTY\nexport LOCAL='a b'\nexport NEW=1\n"
+            ))
+        );
+
+        export(&string_vec!["export", "-n", "LOCAL"], &mut vars).unwrap();
+        assert_eq!(vars.environment(&[]), Ok(string_vec!["NEW=1"]));
+        assert_eq!(vars.get("LOCAL"), Some("a b"), "`-n` should keep the value");
+    }
+
+    #[test]
+    fn export_invalid_name_fails() {
+        let mut vars = Variables::default();
+        assert_eq!(
+            export(&string_vec!["export", "1X=1", "OK=1"], &mut vars),
+            Err(String::from("`1X=1': not a valid identifier"))
+        );
+        assert_eq!(vars.get("OK"), Some("1"), "valid names should still be set");
+    }
+}
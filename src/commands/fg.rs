@@ -1,82 +1,56 @@
 use std::sync::atomic::{AtomicI32, Ordering};
 
-use crate::commands::jobs::{self, Job};
+use crate::{
+    commands::jobs::{self, JobTable, State},
+    fork,
+};
 
 // Store the PID of the currently running foreground process.
 // 0 means "no job running" (we're at the prompt).
 pub static CURRENT_CHILD_PID: AtomicI32 = AtomicI32::new(0);
 pub const USAGE: &str = "Usage:\tfg [jobspec]";
 
+// The exit status of the job, once it finishes or stops, is stored in
+// `last_status`. `notify` says whether to report other jobs that have
+// finished or stopped, as the interactive shell does.
 pub fn fg(
     args: &[String],
-    jobs: &mut Vec<Job>,
-    current: &mut usize,
-    previous: &mut usize,
+    jobs: &mut JobTable,
+    notify: bool,
+    last_status: &mut i32,
 ) -> Result<String, String> {
-    jobs::check_background_jobs(jobs, current, previous);
+    jobs::check_background_jobs(jobs, notify);
 
-    let job_id = if args.len() < 2 {
-        if let Some(last) = jobs.last() {
-            last.jid
-        } else {
-            return Err(String::from("Current: no such job"));
-        }
-    } else {
-        let arg = &args[1];
-        jobs::resolve_jobspec(arg, *current, *previous)?
-    };
-
-    let index = jobs
-        .iter()
-        .position(|j| j.jid == job_id)
-        .ok_or_else(|| format!("No such job ID: {job_id}"))?;
+    let job_id = jobs.resolve(args.get(1).map_or("%+", String::as_str))?;
+    jobs.make_current(job_id);
+    let previous = jobs.previous();
+    let job = jobs.get_mut(job_id).expect("the jobspec should name a job");
 
-    let pgid = jobs[index].pgid;
-    let command_text = jobs[index].command.clone();
-
-    *previous = *current;
-    *current = job_id;
+    let pgid = job.pgid;
+    let command_text = job.command.clone();
 
     println!("{}", command_text);
 
-    let mut status: i32 = 0;
+    let status;
 
     unsafe {
         libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
         CURRENT_CHILD_PID.store(pgid, Ordering::SeqCst);
-        libc::kill(pgid, libc::SIGCONT);
-        loop {
-            let res = libc::waitpid(pgid, &mut status, libc::WUNTRACED);
-            if res == pgid {
-                break;
-            }
-            if res == -1 {
-                let err = std::io::Error::last_os_error();
-                if err.raw_os_error() == Some(libc::EINTR) {
-                    continue;
-                }
-                return Err(format!("waitpid failed: {err}"));
-            }
-            return Err(format!("waitpid returned unexpected pid: {res}"));
-        }
+        // Signal the whole process group so that every stage of a pipeline
+        // resumes.
+        libc::kill(-pgid, libc::SIGCONT);
+        let waited = fork::wait_for_job(job);
         libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
         CURRENT_CHILD_PID.store(0, Ordering::SeqCst);
+        status = waited?;
     }
+    *last_status = fork::exit_status(status);
 
     if libc::WIFSTOPPED(status) {
-        if let Some(job) = jobs.get_mut(index) {
-            job.state = crate::commands::jobs::State::Stopped;
-        }
-
-        println!("\n[{}]+\tStopped\t\t{}", job_id, command_text);
+        job.state = State::from_wait_status(status);
+        println!("\n{}", job.notification(job_id, previous));
     } else {
-        jobs.remove(index);
-        if job_id == *current {
-            *current = *previous;
-            *previous = 0;
-        } else if job_id == *previous {
-            *previous = 0;
-        }
+        jobs.remove(job_id);
     }
 
     Ok(String::new())
@@ -85,28 +59,24 @@ pub fn fg(
 #[cfg(test)]
 mod tests {
     use super::fg;
-    use crate::{commands::jobs::Job, string_vec};
+    use crate::{commands::jobs::JobTable, string_vec};
 
     #[test]
     fn fg_errors_when_no_jobs_and_no_args() {
-        let mut jobs = Vec::<Job>::new();
-        let mut current = 0;
-        let mut previous = 0;
+        let mut jobs = JobTable::default();
         let input = string_vec!["fg"];
 
-        let result = fg(&input, &mut jobs, &mut current, &mut previous);
+        let result = fg(&input, &mut jobs, false, &mut 0);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "Current: no such job");
     }
 
     #[test]
     fn fg_errors_when_job_not_found() {
-        let mut jobs = Vec::<Job>::new();
-        let mut current = 0;
-        let mut previous = 0;
+        let mut jobs = JobTable::default();
         let input = string_vec!["fg", "5"];
 
-        let result = fg(&input, &mut jobs, &mut current, &mut previous);
+        let result = fg(&input, &mut jobs, false, &mut 0);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "No such job ID: 5");
     }
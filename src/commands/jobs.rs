@@ -2,6 +2,12 @@ use std::borrow::Borrow;
 
 use libc;
 
+use crate::signals;
+
+mod table;
+
+pub use table::JobTable;
+
 pub const USAGE: &str = "Usage:\tjobs [-lprs] [jobspec ...]";
 pub const OPTIONS_USAGE: &str = "\r\n-l      -- show process IDs\r\n-p      -- show only process IDs\r\n-r      -- show only running jobs\r\n-s      -- show only stopped jobs";
 const STATE_COL_WIDTH: usize = 24;
@@ -26,37 +32,118 @@ impl Default for JobOptions {
 }
 
 pub struct Job {
-    pub jid: usize, // Job ID: 1, 2, 3, ...
+    pub jid: usize, // Job ID: 1, 2, 3, ..., given by the table.
     pub pgid: i32,  // Process group ID: the PID of the group leader.
     pub command: String,
     pub state: State,
+    pub pids: Vec<i32>,      // Live processes of the pipeline, in order.
+    pub last_pid: i32,       // The last stage, as given by `$!`, even once it's reaped.
+    pub status: Option<i32>, // Wait status of the last stage, once reaped.
+    pub nohup: bool,         // Not sent SIGHUP when the shell exits: `disown -h`.
 }
 
 impl Job {
     pub fn new(jid: usize, pgid: i32, command: String, state: State) -> Self {
+        Self::with_pids(jid, &[pgid], command, state)
+    }
+
+    // A pipeline job. The first PID is that of the process group leader.
+    pub fn with_pids(jid: usize, pids: &[i32], command: String, state: State) -> Self {
+        assert!(!pids.is_empty(), "a job must have at least one process");
+        let pgid = pids[0];
         assert!(pgid > 0, "`pgid` must be positive");
         Self {
             jid,
             pgid,
             command,
             state,
+            pids: pids.to_vec(),
+            last_pid: pids[pids.len() - 1],
+            status: None,
+            nohup: false,
         }
     }
+
+    // Forget a process that has terminated. The job's status is that of the
+    // final stage of the pipeline, which is the last live PID until it's
+    // reaped.
+    pub fn record_termination(&mut self, pid: i32, status: i32) {
+        if self.status.is_none() && self.pids.last() == Some(&pid) {
+            self.status = Some(status);
+        }
+        self.pids.retain(|&p| p != pid);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pids.is_empty()
+    }
+
+    // The line that tells the user the job has changed state, such as
+    // `[1]+\tDone\t\tsleep 1`, marked as `jobs` would mark it, given the
+    // IDs of the current and previous jobs.
+    pub fn notification(&self, current: usize, previous: usize) -> String {
+        let state = self.state.to_string();
+        let tabs = if state.len() < 8 { "\t\t" } else { "\t" };
+        let sign = sign(self.jid, current, previous);
+        format!("[{}]{sign}\t{state}{tabs}{}", self.jid, self.command)
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+// A job is running until it's stopped, which it may be again once it's
+// restarted, or until it's over: every process in it has exited, and the
+// last one's exit code is the job's, or a signal has terminated it.
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum State {
     Running,
-    Stopped,
+    Stopped(i32),          // By this signal.
+    Done(i32),             // With this exit code.
+    Terminated(i32, bool), // By this signal, and whether it dumped core.
+}
+
+impl State {
+    // The state that a wait status shows a process to be in.
+    pub fn from_wait_status(status: i32) -> Self {
+        if libc::WIFSTOPPED(status) {
+            State::Stopped(libc::WSTOPSIG(status))
+        } else if libc::WIFSIGNALED(status) {
+            State::Terminated(libc::WTERMSIG(status), libc::WCOREDUMP(status))
+        } else if libc::WIFEXITED(status) {
+            State::Done(libc::WEXITSTATUS(status))
+        } else {
+            State::Running
+        }
+    }
+
+    // The status that `$?` has after waiting for the job: its exit code, or
+    // 128 plus the number of the signal that terminated or stopped it.
+    pub fn exit_status(self) -> i32 {
+        match self {
+            State::Running => 0,
+            State::Done(code) => code,
+            State::Stopped(sig) | State::Terminated(sig, _) => 128 + sig,
+        }
+    }
+
+    pub fn is_stopped(self) -> bool {
+        matches!(self, State::Stopped(_))
+    }
 }
 
 impl std::fmt::Display for State {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            State::Running => "Running",
-            State::Stopped => "Stopped",
-        };
-        f.write_str(s)
+        match *self {
+            State::Running => f.write_str("Running"),
+            State::Stopped(sig) => f.write_str(&signals::description(sig)),
+            State::Done(0) => f.write_str("Done"),
+            State::Done(code) => write!(f, "Exit {code}"),
+            State::Terminated(sig, core) => {
+                f.write_str(&signals::description(sig))?;
+                if core {
+                    f.write_str(" (core dumped)")?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
@@ -66,13 +153,8 @@ pub struct JobDisplay<'a> {
     pub opts: JobOptions,
 }
 
-pub fn jobs(
-    input: &[String],
-    jobs: &mut Vec<Job>,
-    current: &mut usize,
-    previous: &mut usize,
-) -> Result<String, String> {
-    check_background_jobs(jobs, current, previous);
+pub fn jobs(input: &[String], jobs: &mut JobTable, notify: bool) -> Result<String, String> {
+    check_background_jobs(jobs, notify);
 
     let mut opts = JobOptions::default();
     let mut specific_job_ids = Vec::new();
@@ -89,15 +171,32 @@ pub fn jobs(
                 }
             }
         } else {
-            let id = resolve_jobspec(arg, *current, *previous)?;
+            let id = jobs.resolve(arg)?;
             specific_job_ids.push(id);
         }
     }
 
-    let output = format_jobs(jobs, opts, &specific_job_ids, *current, *previous);
+    let output = format_jobs(
+        jobs,
+        opts,
+        &specific_job_ids,
+        jobs.current(),
+        jobs.previous(),
+    );
     Ok(output)
 }
 
+// How a job is marked: `+` for the current job, `-` for the previous one.
+fn sign(id: usize, current: usize, previous: usize) -> &'static str {
+    if id == current {
+        "+"
+    } else if id == previous {
+        "-"
+    } else {
+        " "
+    }
+}
+
 pub fn format_jobs<T: Borrow<Job>>(
     items: &[T],
     opts: JobOptions,
@@ -116,11 +215,11 @@ pub fn format_jobs<T: Borrow<Job>>(
         }
 
         // Filter: -r (Running only).
-        if opts.running_only && matches!(job.state, State::Stopped) {
+        if opts.running_only && job.state != State::Running {
             continue;
         }
         // Filter: -s (Stopped only).
-        if opts.stopped_only && matches!(job.state, State::Running) {
+        if opts.stopped_only && !job.state.is_stopped() {
             continue;
         }
 
@@ -131,14 +230,7 @@ pub fn format_jobs<T: Borrow<Job>>(
             continue;
         }
 
-        let sign = if job.jid == current {
-            "+"
-        } else if job.jid == previous {
-            "-"
-        } else {
-            " "
-        };
-
+        let sign = sign(job.jid, current, previous);
         let display = JobDisplay { job, sign, opts };
         output.push_str(&format!("{display}\n"));
     }
@@ -146,35 +238,6 @@ pub fn format_jobs<T: Borrow<Job>>(
     output
 }
 
-pub fn resolve_jobspec(spec: &str, current: usize, previous: usize) -> Result<usize, String> {
-    let raw = if spec == "%" || spec == "%+" || spec == "%%" {
-        if current > 0 {
-            return Ok(current);
-        } else if previous > 0 {
-            // No explicit current; fall back to previous.
-            return Ok(previous);
-        } else {
-            return Err(String::from("Current: no such job"));
-        }
-    } else if spec == "%-" {
-        if previous > 0 {
-            return Ok(previous);
-        } else if current > 0 {
-            // Single-job case: %- maps to current.
-            return Ok(current);
-        } else {
-            return Err(String::from("Current: no such job"));
-        }
-    } else if let Some(rest) = spec.strip_prefix('%') {
-        rest
-    } else {
-        spec
-    };
-
-    raw.parse::<usize>()
-        .map_err(|_| format!("Invalid job ID: {spec}"))
-}
-
 impl std::fmt::Display for JobDisplay<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let state_str = self.job.state.to_string();
@@ -214,12 +277,18 @@ impl std::fmt::Display for JobDisplay<'_> {
     }
 }
 
-pub fn check_background_jobs(jobs: &mut Vec<Job>, current: &mut usize, previous: &mut usize) {
+// Update the jobs table with any changes in the state of its processes,
+// including jobs restarted from outside the shell. Jobs that have finished
+// leave the table, which remembers their final status for `wait`. `notify`
+// says whether to tell the user about the changes, as the interactive shell
+// does.
+pub fn check_background_jobs(jobs: &mut JobTable, notify: bool) {
     loop {
         let mut status = 0;
 
         // WNOHANG: Poll once but don't wait. WUNTRACED: Report stopped
-        // processes too. (By default `waitpid` only reports terminated
+        // processes too. WCONTINUED: Report stopped processes that have been
+        // restarted. (By default `waitpid` only reports terminated
         // processes.)
         let pid = unsafe {
             libc::waitpid(
@@ -233,44 +302,37 @@ pub fn check_background_jobs(jobs: &mut Vec<Job>, current: &mut usize, previous:
             break; // No more children have changed state.
         }
 
-        if let Some(index) = jobs.iter().position(|j| j.pgid == pid) {
-            if libc::WIFSTOPPED(status) {
-                // The kernel stopped the process (e.g. Python background input).
-                if jobs[index].state != State::Stopped {
-                    jobs[index].state = State::Stopped;
-                    *previous = *current;
-                    *current = jobs[index].jid;
-                    println!(
-                        "\n[{}]+\tStopped\t\t{}",
-                        jobs[index].jid, jobs[index].command
-                    );
-                }
-            } else if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
-                // The process is dead.
-                let job = &jobs[index];
-
-                if libc::WIFSIGNALED(status) {
-                    println!("[{}]+\tTerminated\t{}", job.jid, job.command);
-                } else {
-                    let code = libc::WEXITSTATUS(status);
-                    if code == 0 {
-                        println!("[{}]+\tDone\t\t{}", job.jid, job.command);
-                    } else {
-                        println!("[{}]+\tExit {}\t\t{}", job.jid, code, job.command);
-                    }
+        let Some(id) = jobs.find_pid(pid).map(|job| job.jid) else {
+            continue;
+        };
+        let job = jobs.get_mut(id).expect("the job was just found");
+
+        if libc::WIFSTOPPED(status) {
+            // The kernel stopped the process (e.g. Python background input).
+            if !job.state.is_stopped() {
+                job.state = State::from_wait_status(status);
+                jobs.make_current(id);
+                if notify && let Some(job) = jobs.get(id) {
+                    println!("\n{}", job.notification(jobs.current(), jobs.previous()));
                 }
+            }
+        } else if libc::WIFCONTINUED(status) {
+            // Restarted by a signal from outside the shell.
+            job.state = State::Running;
+        } else if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+            // The process is dead. The job is only over once every stage
+            // of its pipeline has terminated.
+            job.record_termination(pid, status);
+            if !job.is_finished() {
+                continue;
+            }
 
-                let removed_id = job.jid;
-                jobs.remove(index);
-
-                // Update pointers if the removed job was the current or
-                // previous one.
-                if removed_id == *current {
-                    *current = *previous;
-                    *previous = 0;
-                } else if removed_id == *previous {
-                    *previous = 0;
-                }
+            job.state = State::from_wait_status(job.status.unwrap_or(status));
+            if notify && let Some(job) = jobs.get(id) {
+                println!("{}", job.notification(jobs.current(), jobs.previous()));
+            }
+            if let Some(job) = jobs.remove(id) {
+                jobs.remember(&job);
             }
         }
     }
@@ -282,12 +344,7 @@ mod tests {
 
     #[test]
     fn short_form_pads_state_column_and_adds_ampersand() {
-        let job = Job {
-            jid: 2,
-            pgid: 9999,
-            command: String::from("sleep 50"),
-            state: State::Running,
-        };
+        let job = Job::new(2, 9999, String::from("sleep 50"), State::Running);
 
         let display = JobDisplay {
             job: &job,
@@ -310,12 +367,12 @@ mod tests {
 
     #[test]
     fn stopped_job_has_no_ampersand() {
-        let job = Job {
-            jid: 2,
-            pgid: 9999,
-            command: String::from("sleep 50"),
-            state: State::Stopped,
-        };
+        let job = Job::new(
+            2,
+            9999,
+            String::from("sleep 50"),
+            State::Stopped(libc::SIGTSTP),
+        );
 
         let display = JobDisplay {
             job: &job,
@@ -338,12 +395,12 @@ mod tests {
 
     #[test]
     fn long_form_pads_state_and_pid_columns() {
-        let job = Job {
-            jid: 1,
-            pgid: 8287,
-            command: String::from("ls ..."),
-            state: State::Stopped,
-        };
+        let job = Job::new(
+            1,
+            8287,
+            String::from("ls ..."),
+            State::Stopped(libc::SIGTSTP),
+        );
 
         let mut opts = JobOptions::default();
         opts.show_pid = true; // -l flag.
@@ -372,7 +429,7 @@ mod tests {
     fn format_jobs_filters_by_running_only() {
         let jobs = vec![
             Job::new(1, 100, String::from("run"), State::Running),
-            Job::new(2, 101, String::from("stop"), State::Stopped),
+            Job::new(2, 101, String::from("stop"), State::Stopped(libc::SIGTSTP)),
         ];
 
         let mut opts = JobOptions::default();
@@ -394,7 +451,7 @@ mod tests {
     fn format_jobs_filters_by_specific_id() {
         let jobs = vec![
             Job::new(1, 100, String::from("run"), State::Running),
-            Job::new(2, 101, String::from("stop"), State::Stopped),
+            Job::new(2, 101, String::from("stop"), State::Stopped(libc::SIGTSTP)),
         ];
 
         let opts = JobOptions::default();
@@ -434,33 +491,73 @@ mod tests {
         assert!(output.contains("[3] "), "job 3 should have no sign");
     }
 
+    #[test]
+    fn notifications_mark_current_and_previous() {
+        let job = Job::new(2, 100, String::from("sleep 5"), State::Done(0));
+        assert_eq!(job.notification(2, 1), "[2]+\tDone\t\tsleep 5");
+        assert_eq!(job.notification(1, 2), "[2]-\tDone\t\tsleep 5");
+        assert_eq!(job.notification(1, 3), "[2] \tDone\t\tsleep 5");
+    }
+
+    #[test]
+    fn notifications_name_the_new_state() {
+        let notification =
+            |state| Job::new(1, 100, String::from("sleep 5"), state).notification(1, 0);
+        assert_eq!(notification(State::Done(0)), "[1]+\tDone\t\tsleep 5");
+        assert_eq!(notification(State::Done(2)), "[1]+\tExit 2\t\tsleep 5");
+        assert_eq!(
+            notification(State::Terminated(libc::SIGTERM, false)),
+            "[1]+\tTerminated\tsleep 5"
+        );
+        assert_eq!(
+            notification(State::Stopped(libc::SIGTSTP)),
+            "[1]+\tStopped\t\tsleep 5"
+        );
+    }
+
     #[test]
     fn resolve_jobspec_handles_current_and_previous_aliases() {
+        let mut jobs = JobTable::default();
+        for pgid in [100, 101, 102] {
+            jobs.add(Job::new(0, pgid, String::from("sleep"), State::Running));
+        }
+        jobs.make_current(2);
+        jobs.make_current(1);
+        jobs.make_current(2);
+
         assert_eq!(
-            resolve_jobspec("%+", 2, 1).unwrap(),
+            jobs.resolve("%+").unwrap(),
             2,
             "%+ should resolve to current job"
         );
         assert_eq!(
-            resolve_jobspec("%%", 2, 1).unwrap(),
+            jobs.resolve("%%").unwrap(),
             2,
             "%% should resolve to current job"
         );
         assert_eq!(
-            resolve_jobspec("%", 2, 1).unwrap(),
+            jobs.resolve("%").unwrap(),
             2,
             "% should resolve to current job"
         );
         assert_eq!(
-            resolve_jobspec("%-", 2, 1).unwrap(),
+            jobs.resolve("%-").unwrap(),
             1,
             "%- should resolve to previous job"
         );
 
-        // Single-job case: %- falls back to current.
+        jobs.remove(1);
         assert_eq!(
-            resolve_jobspec("%-", 3, 0).unwrap(),
+            jobs.resolve("%-").unwrap(),
             3,
+            "the highest other job should become the previous one"
+        );
+
+        // Single-job case: %- falls back to current.
+        jobs.remove(3);
+        assert_eq!(
+            jobs.resolve("%-").unwrap(),
+            2,
             "%- should fall back to current when previous is 0"
         );
     }
@@ -0,0 +1,342 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Job, State};
+
+// As in other shells, only so many finished jobs are remembered.
+const REMEMBERED: usize = 256;
+
+// A job that has finished, for `wait` to report.
+struct Finished {
+    id: usize,
+    pgid: i32,
+    last_pid: i32,
+    status: i32,
+}
+
+// The shell's jobs, in order of job ID, along with the current and previous
+// jobs, which `%+` and `%-` refer to, and which `fg` and `bg` act on by
+// default. Each job keeps its ID for as long as it's in the table, and a new
+// job takes the lowest ID that's free. The jobs can be looked at and changed
+// as a slice, but only added and removed through the table, so that the
+// current and previous jobs are always among them. Finished jobs are kept
+// aside, until `wait` asks for their status.
+#[derive(Default)]
+pub struct JobTable {
+    jobs: Vec<Job>,
+    current: usize,  // 0 if there's no current job.
+    previous: usize, // 0 if there's no previous job.
+    finished: Vec<Finished>,
+}
+
+impl JobTable {
+    // A table as it might be after jobs have come and gone, for testing.
+    #[cfg(test)]
+    pub fn with_jobs(jobs: Vec<Job>, current: usize, previous: usize) -> Self {
+        Self {
+            jobs,
+            current,
+            previous,
+            finished: Vec::new(),
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn previous(&self) -> usize {
+        self.previous
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.jid == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.jid == id)
+    }
+
+    // The job that a process belongs to, given its PID, or the ID of its
+    // process group.
+    pub fn find_pid(&self, pid: i32) -> Option<&Job> {
+        self.jobs
+            .iter()
+            .find(|job| job.pgid == pid || job.pids.contains(&pid))
+    }
+
+    // Give the job the lowest ID that isn't in use, and make it the current
+    // job. Returns its ID.
+    pub fn add(&mut self, mut job: Job) -> usize {
+        // A PID that's been reused no longer stands for the job that finished.
+        self.finished
+            .retain(|old| !job.pids.contains(&old.pgid) && !job.pids.contains(&old.last_pid));
+        let id = (1..)
+            .find(|&id| self.get(id).is_none())
+            .expect("job IDs shouldn't run out");
+        job.jid = id;
+        let index = self.jobs.partition_point(|other| other.jid < id);
+        self.jobs.insert(index, job);
+        self.make_current(id);
+        id
+    }
+
+    // The job that was current becomes the previous one.
+    pub fn make_current(&mut self, id: usize) {
+        if id != self.current {
+            self.previous = self.current;
+            self.current = id;
+        }
+    }
+
+    // Take a job out of the table, for instance once it's finished. If it was
+    // the current job, the previous one takes its place, and the highest of
+    // the others becomes the previous job.
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.jid == id)?;
+        let job = self.jobs.remove(index);
+        if id == self.current {
+            self.current = self.previous;
+            self.previous = id;
+        }
+        if id == self.previous {
+            self.previous = self
+                .jobs
+                .iter()
+                .rev()
+                .map(|job| job.jid)
+                .find(|&other| other != self.current)
+                .unwrap_or(0);
+        }
+        Some(job)
+    }
+
+    // Keep the status of a job that has finished, and left the table.
+    pub fn remember(&mut self, job: &Job) {
+        if self.finished.len() == REMEMBERED {
+            self.finished.remove(0);
+        }
+        self.finished.push(Finished {
+            id: job.jid,
+            pgid: job.pgid,
+            last_pid: job.last_pid,
+            status: job.state.exit_status(),
+        });
+    }
+
+    // The process group of a finished job, given by `%n` or by the PID of its
+    // first or last process.
+    pub fn find_finished(&self, spec: &str) -> Option<i32> {
+        let found = match spec.strip_prefix('%') {
+            Some(id) => {
+                let id = id.parse::<usize>().ok()?;
+                self.finished.iter().rev().find(|job| job.id == id)
+            }
+            None => {
+                let pid = spec.parse::<i32>().ok()?;
+                self.finished
+                    .iter()
+                    .rev()
+                    .find(|job| job.pgid == pid || job.last_pid == pid)
+            }
+        };
+        found.map(|job| job.pgid)
+    }
+
+    // The status of a finished job, given by its process group. Once it's
+    // been asked for, the job is forgotten.
+    pub fn take_finished(&mut self, pgid: i32) -> Option<i32> {
+        let index = self.finished.iter().rposition(|job| job.pgid == pgid)?;
+        Some(self.finished.remove(index).status)
+    }
+
+    pub fn forget_finished(&mut self) {
+        self.finished.clear();
+    }
+
+    // The jobs to send SIGHUP when the shell exits. Stopped jobs would never
+    // be woken, so they're always hung up. Those still running carry on,
+    // unless `huponexit` is set. Either way, jobs marked with `disown -h` are
+    // left alone.
+    pub fn to_hang_up(&self, huponexit: bool) -> impl Iterator<Item = &Job> {
+        self.jobs
+            .iter()
+            .filter(move |job| !job.nohup && (job.state != State::Running || huponexit))
+    }
+
+    // The ID of the job given by a jobspec: `%+`, `%%` or `%` for the
+    // current job, `%-` for the previous one, `%n` (or just `n`) for job n,
+    // `%name` for the job whose command starts with `name`, or `%?text` for
+    // the one whose command contains `text`.
+    pub fn resolve(&self, spec: &str) -> Result<usize, String> {
+        let no_such_job = || format!("{spec}: no such job");
+        let rest = match spec {
+            "%" | "%+" | "%%" => {
+                // No explicit current; fall back to previous.
+                return [self.current, self.previous]
+                    .into_iter()
+                    .find(|&id| id > 0)
+                    .ok_or_else(|| String::from("Current: no such job"));
+            }
+            "%-" => {
+                // Single-job case: %- maps to current.
+                return [self.previous, self.current]
+                    .into_iter()
+                    .find(|&id| id > 0)
+                    .ok_or_else(|| String::from("Current: no such job"));
+            }
+            _ => match spec.strip_prefix('%') {
+                Some(rest) => rest,
+                None => {
+                    let id = spec
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid job ID: {spec}"))?;
+                    return self
+                        .get(id)
+                        .map(|job| job.jid)
+                        .ok_or_else(|| format!("No such job ID: {id}"));
+                }
+            },
+        };
+
+        if let Ok(id) = rest.parse::<usize>() {
+            return self.get(id).map(|job| job.jid).ok_or_else(no_such_job);
+        }
+        let matching: Vec<usize> = match rest.strip_prefix('?') {
+            Some(text) => self
+                .jobs
+                .iter()
+                .filter(|job| job.command.contains(text))
+                .map(|job| job.jid)
+                .collect(),
+            None => self
+                .jobs
+                .iter()
+                .filter(|job| job.command.starts_with(rest))
+                .map(|job| job.jid)
+                .collect(),
+        };
+        match matching.as_slice() {
+            [] => Err(no_such_job()),
+            [id] => Ok(*id),
+            _ => Err(format!("{spec}: ambiguous job spec")),
+        }
+    }
+}
+
+impl Deref for JobTable {
+    type Target = [Job];
+
+    fn deref(&self) -> &[Job] {
+        &self.jobs
+    }
+}
+
+impl DerefMut for JobTable {
+    fn deref_mut(&mut self) -> &mut [Job] {
+        &mut self.jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JobTable;
+    use crate::commands::jobs::{Job, State};
+
+    fn job(pgid: i32, command: &str) -> Job {
+        Job::new(0, pgid, String::from(command), State::Running)
+    }
+
+    #[test]
+    fn new_jobs_take_the_lowest_free_id() {
+        let mut table = JobTable::default();
+        assert_eq!(table.add(job(100, "sleep 1")), 1);
+        assert_eq!(table.add(job(101, "sleep 2")), 2);
+        assert_eq!(table.add(job(102, "sleep 3")), 3);
+        assert_eq!((table.current(), table.previous()), (3, 2));
+
+        table.remove(1);
+        assert_eq!(table.add(job(103, "sleep 4")), 1);
+        let ids: Vec<usize> = table.iter().map(|job| job.jid).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        assert_eq!((table.current(), table.previous()), (1, 3));
+
+        table.remove(1);
+        assert_eq!(
+            (table.current(), table.previous()),
+            (3, 2),
+            "the highest other job should become the previous one"
+        );
+        assert_eq!(table.find_pid(102).map(|job| job.jid), Some(3));
+
+        table.remove(2);
+        assert_eq!((table.current(), table.previous()), (3, 0));
+    }
+
+    #[test]
+    fn exiting_hangs_up_jobs_not_marked_nohup() {
+        let mut table = JobTable::default();
+        table.add(job(100, "sleep 100"));
+        table.add(job(101, "vim notes"));
+        table.add(job(102, "sleep 200"));
+        table[1].state = State::Stopped(libc::SIGTSTP);
+        table[2].nohup = true;
+
+        let hung_up = |table: &JobTable, huponexit| -> Vec<usize> {
+            table.to_hang_up(huponexit).map(|job| job.jid).collect()
+        };
+        assert_eq!(hung_up(&table, false), [2]);
+        assert_eq!(
+            hung_up(&table, true),
+            [1, 2],
+            "a job marked with `disown -h` should survive huponexit"
+        );
+    }
+
+    #[test]
+    fn jobspecs_name_jobs_by_command() {
+        let mut table = JobTable::default();
+        table.add(job(100, "sleep 100"));
+        table.add(job(101, "cat notes | grep todo"));
+        table.add(job(102, "sleep 200"));
+
+        assert_eq!(table.resolve("%cat"), Ok(2));
+        assert_eq!(table.resolve("%?todo"), Ok(2));
+        assert_eq!(table.resolve("%?200"), Ok(3));
+        assert_eq!(table.resolve("%2"), Ok(2));
+        assert_eq!(table.resolve("1"), Ok(1));
+        assert_eq!(
+            table.resolve("%sleep"),
+            Err(String::from("%sleep: ambiguous job spec"))
+        );
+        assert_eq!(
+            table.resolve("%vim"),
+            Err(String::from("%vim: no such job"))
+        );
+        assert_eq!(table.resolve("%7"), Err(String::from("%7: no such job")));
+        assert_eq!(table.resolve("7"), Err(String::from("No such job ID: 7")));
+        assert_eq!(table.resolve("x"), Err(String::from("Invalid job ID: x")));
+    }
+
+    #[test]
+    fn finished_jobs_are_remembered_until_taken() {
+        let mut table = JobTable::default();
+        let id = table.add(Job::with_pids(
+            0,
+            &[100, 101],
+            String::from("false | true"),
+            State::Done(3),
+        ));
+        let finished = table.remove(id).unwrap();
+        table.remember(&finished);
+
+        assert_eq!(table.find_finished("%1"), Some(100));
+        assert_eq!(table.find_finished("101"), Some(100));
+        assert_eq!(table.find_finished("%2"), None);
+        assert_eq!(table.take_finished(100), Some(3));
+        assert_eq!(table.find_finished("100"), None);
+
+        table.remember(&finished);
+        table.add(job(101, "sleep 1"));
+        assert_eq!(table.find_finished("%1"), None, "PID 101 has been reused");
+    }
+}
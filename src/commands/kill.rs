@@ -1,91 +1,218 @@
 use std::{io, time::Duration};
 
-use crate::commands::jobs::{self, Job, State};
+use crate::{
+    commands::jobs::{self, JobTable},
+    error,
+    signals::{self, SIGNALS},
+};
 
-pub const USAGE: &str = "Usage:\tkill <PGID>|%[+|-|%%|<JID>]";
+pub const USAGE: &str =
+    "Usage:\tkill [-s SIGSPEC | -SIGSPEC] PID|JOBSPEC ...\n\tkill -l [SIGSPEC|STATUS ...]";
 
+// Send a signal, SIGTERM unless another is given by name or number, to each
+// process given by its PID, or each job given by its jobspec, in which case
+// the whole process group receives it. A negative PID, given after `--`,
+// also stands for a process group. Each target that can't be signalled is
+// reported in turn, and makes the status 1. With `-l`, list the signals, or
+// translate names to numbers and back, including exit statuses of processes
+// that a signal killed.
 pub fn kill(
     input: &[String],
-    jobs: &mut Vec<Job>,
-    current: &mut usize,
-    previous: &mut usize,
+    jobs: &mut JobTable,
+    notify: bool,
+    last_status: &mut i32,
 ) -> Result<String, String> {
-    jobs::check_background_jobs(jobs, current, previous);
+    jobs::check_background_jobs(jobs, notify);
 
-    if input.len() > 2 {
-        return Err(format!("Too many arguments\n{USAGE}"));
+    let mut sig = libc::SIGTERM;
+    let mut list = false;
+    let mut args = &input[1..];
+    while let Some(arg) = args.first() {
+        match arg.as_str() {
+            "--" => {
+                args = &args[1..];
+                break;
+            }
+            "-l" | "-L" => {
+                list = true;
+                args = &args[1..];
+            }
+            "-s" | "-n" => {
+                let spec = args
+                    .get(1)
+                    .ok_or_else(|| format!("{arg}: option requires an argument\n{USAGE}"))?;
+                sig = signal(spec)?;
+                args = &args[2..];
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                sig = signal(&arg[1..])?;
+                args = &args[1..];
+            }
+            _ => break,
+        }
     }
 
-    if input.len() < 2 {
+    if list {
+        return list_signals(args);
+    }
+    if args.is_empty() {
         return Err(format!("Not enough arguments\n{USAGE}"));
     }
 
-    let arg = &input[1];
-    // May represent the job ID or the PID of the group leader, depending on
-    // whether the value passed to `kill` is prefixed with `%` (in which case
-    // `-pid_to_kill` is passed to `libc::kill`.)
-    let pid_to_kill: i32;
-    let mut is_stopped = false;
-
-    if arg.starts_with('%') {
-        let job_id = jobs::resolve_jobspec(arg, *current, *previous)?;
-
-        if let Some(job) = jobs.iter().find(|j| j.jid == job_id) {
-            pid_to_kill = job.pgid;
-            if matches!(job.state, State::Stopped) {
-                is_stopped = true;
+    let mut signalled_jobs = Vec::new();
+    for arg in args {
+        match send(arg, sig, jobs) {
+            Ok(Some(pgid)) => signalled_jobs.push(pgid),
+            Ok(None) => {}
+            Err(err) => {
+                error::handle_error("kill", err);
+                *last_status = 1;
             }
-        } else {
-            return Err(format!("No such job ID: {arg}"));
         }
-    } else {
-        pid_to_kill = arg
-            .parse::<i32>()
-            .map_err(|e| format!("Failed to parse ID: {e}: {arg}\r\n{USAGE}"))?;
+    }
 
-        if pid_to_kill <= 0 {
-            return Err(String::from("ID must be positive"));
+    // Surface termination promptly instead of waiting for the next builtin
+    // call. Poll briefly so we don't block indefinitely if the jobs ignore
+    // the signal.
+    for _ in 0..5 {
+        jobs::check_background_jobs(jobs, notify);
+        if jobs.iter().all(|j| !signalled_jobs.contains(&j.pgid)) {
+            break;
         }
+        std::thread::sleep(Duration::from_millis(1));
+    }
 
-        if let Some(job) = jobs.iter().find(|j| j.pgid == pid_to_kill) {
-            if matches!(job.state, State::Stopped) {
-                is_stopped = true;
-            }
+    Ok(String::new())
+}
+
+// A signal given by name or number, or 0, which checks that the target
+// exists without sending it anything.
+fn signal(spec: &str) -> Result<i32, String> {
+    match spec {
+        "0" => Ok(0),
+        _ => signals::number(spec).ok_or_else(|| format!("{spec}: invalid signal specification")),
+    }
+}
+
+// Signal the job or process given by `arg`, returning the process group of
+// the job, if it is one.
+fn send(arg: &str, sig: i32, jobs: &JobTable) -> Result<Option<i32>, String> {
+    if arg.starts_with('%') {
+        let job_id = jobs.resolve(arg)?;
+        let job = jobs.get(job_id).expect("the jobspec should name a job");
+
+        // A negative PID stands for the process group, so that every stage
+        // of the pipeline receives the signal.
+        send_signal(-job.pgid, sig)?;
+
+        // A stopped job would only act on SIGTERM or SIGHUP once restarted.
+        if job.state.is_stopped() && (sig == libc::SIGTERM || sig == libc::SIGHUP) {
+            send_signal(-job.pgid, libc::SIGCONT)?;
         }
+        return Ok(Some(job.pgid));
     }
 
-    unsafe {
-        // If the job is running, this kills it immediately. If the job is
-        // stopped, the signal is queued. Use a negative value for the first
-        // argument to target the process group leader and all its children;
-        // `libc::kill` interprets a positive value as the PID of thegroup
-        // leader.
-        if libc::kill(-pid_to_kill, libc::SIGTERM) == -1 {
-            let err = io::Error::last_os_error();
-            return Err(format!("Failed to kill {pid_to_kill}: {err}"));
+    let pid = arg
+        .parse::<i32>()
+        .map_err(|_| format!("{arg}: arguments must be process or job IDs"))?;
+    send_signal(pid, sig)?;
+    Ok(None)
+}
+
+fn send_signal(pid: i32, sig: i32) -> Result<(), String> {
+    if unsafe { libc::kill(pid, sig) } == -1 {
+        let err = io::Error::last_os_error().to_string();
+        let err = err.split(" (os ").next().unwrap_or(&err);
+        return Err(format!("({pid}) - {err}"));
+    }
+    Ok(())
+}
+
+// With no arguments, every signal, with its number. Otherwise, the name of
+// each signal given by number, or the number of each given by name. A number
+// over 128 is the status of a process killed by the signal 128 less than it.
+fn list_signals(args: &[String]) -> Result<String, String> {
+    if args.is_empty() {
+        let mut output = String::new();
+        for (i, (name, sig, _)) in SIGNALS.iter().enumerate() {
+            let separator = if i % 5 == 4 || i == SIGNALS.len() - 1 {
+                "\n"
+            } else {
+                "\t"
+            };
+            output.push_str(&format!("{sig:2}) SIG{name}{separator}"));
         }
+        return Ok(output);
+    }
 
-        // Restart a stopped job so that it can receive the queued signal to
-        // terminate.
-        if is_stopped {
-            if libc::kill(-pid_to_kill, libc::SIGCONT) == -1 {
-                let err = io::Error::last_os_error();
-                return Err(format!(
-                    "Failed to resume {pid_to_kill} for termination: {err}"
-                ));
+    let mut output = String::new();
+    for arg in args {
+        let line = match arg.parse::<i32>() {
+            Ok(number) => {
+                let sig = if number > 128 { number - 128 } else { number };
+                signals::name(sig).map(String::from)
             }
-        }
+            Err(_) => signals::number(arg).map(|sig| sig.to_string()),
+        };
+        let line = line.ok_or_else(|| format!("{arg}: invalid signal specification"))?;
+        output.push_str(&line);
+        output.push('\n');
     }
+    Ok(output)
+}
 
-    // Surface termination promptly instead of waiting for the next builtin call.
-    // Poll briefly so we don't block indefinitely if the process ignores SIGTERM.
-    for _ in 0..5 {
-        jobs::check_background_jobs(jobs, current, previous);
-        if jobs.iter().all(|j| j.pgid != pid_to_kill) {
-            break;
-        }
-        std::thread::sleep(Duration::from_millis(1));
+#[cfg(test)]
+mod tests {
+    use super::kill;
+    use crate::{
+        commands::jobs::{Job, JobTable, State},
+        string_vec,
+    };
+
+    #[test]
+    fn kill_lists_and_translates_signals() {
+        let mut jobs = JobTable::default();
+        let mut status = 0;
+        let mut run = |args: Vec<String>| kill(&args, &mut jobs, false, &mut status);
+
+        assert_eq!(
+            run(string_vec!["kill", "-l", "137", "term", "SIGHUP"]),
+            Ok(String::from("KILL\n15\n1\n"))
+        );
+        let listing = run(string_vec!["kill", "-l"]).unwrap();
+        assert!(listing.starts_with(" 1) SIGHUP\t 2) SIGINT\t"));
+        assert!(listing.contains("\n 6) SIGABRT\t"));
+        assert!(run(string_vec!["kill", "-l", "NOPE"]).is_err());
+        assert!(run(string_vec!["kill", "-NOPE", "1"]).is_err());
+        assert!(run(string_vec!["kill", "-s"]).is_err());
+        assert!(run(string_vec!["kill", "-9"]).is_err());
     }
 
-    Ok(String::new())
+    #[test]
+    fn kill_reports_each_failure() {
+        let mut jobs = JobTable::default();
+        jobs.add(Job::new(
+            0,
+            i32::MAX,
+            String::from("sleep 1"),
+            State::Running,
+        ));
+        let mut status = 0;
+        let own_pid = std::process::id().to_string();
+
+        // Signal 0 only checks that the process exists.
+        let args = string_vec!["kill", "-s", "0", &own_pid];
+        assert_eq!(
+            kill(&args, &mut jobs, false, &mut status),
+            Ok(String::new())
+        );
+        assert_eq!(status, 0);
+
+        let args = string_vec!["kill", "-0", "%1", "%2", "x", &own_pid];
+        assert_eq!(
+            kill(&args, &mut jobs, false, &mut status),
+            Ok(String::new())
+        );
+        assert_eq!(status, 1);
+    }
 }
@@ -0,0 +1,42 @@
+use crate::{arithmetic, variables::Variables};
+
+pub const USAGE: &str = "Usage:\tlet EXPRESSION...";
+
+// Evaluate each argument as an arithmetic expression. The exit status is 0 if
+// the last one is non-zero and 1 if it's zero, so `let` and `((...))` can be
+// used as conditions.
+pub fn r#let(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    if input.len() < 2 {
+        return Err(format!("Expression expected\n{USAGE}"));
+    }
+
+    let mut value = 0;
+    for expression in &input[1..] {
+        value = arithmetic::evaluate(expression, vars)?;
+    }
+    vars.last_status = i32::from(value == 0);
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::r#let;
+    use crate::{string_vec, variables::Variables};
+
+    #[test]
+    fn let_sets_status() {
+        let mut vars = Variables::default();
+
+        assert_eq!(
+            r#let(&string_vec!["let", "x = 2", "y = x * 3"], &mut vars),
+            Ok(String::new())
+        );
+        assert_eq!((vars.get("y"), vars.last_status), (Some("6"), 0));
+
+        r#let(&string_vec!["let", "x - 2"], &mut vars).unwrap();
+        assert_eq!(vars.last_status, 1, "a zero result should fail");
+
+        assert!(r#let(&string_vec!["let"], &mut vars).is_err());
+        assert!(r#let(&string_vec!["let", "1 / 0"], &mut vars).is_err());
+    }
+}
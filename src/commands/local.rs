@@ -0,0 +1,63 @@
+use crate::{parser::is_name, variables::Variables};
+
+pub const USAGE: &str = "Usage:\tlocal NAME[=VALUE]...";
+
+// Make variables local to the function that's running, optionally giving
+// them values.
+pub fn local(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    if vars.depth() == 0 {
+        return Err(String::from("Can only be used in a function"));
+    }
+
+    let mut errors = Vec::new();
+    for arg in input.iter().skip(1) {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        if !is_name(name) {
+            errors.push(format!("`{arg}': not a valid identifier"));
+            continue;
+        }
+        let result = vars
+            .make_local(name)
+            .and_then(|()| value.map_or(Ok(()), |value| vars.set(name, value)));
+        if let Err(err) = result {
+            errors.push(err);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(String::new())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::local;
+    use crate::{string_vec, variables::Variables};
+
+    #[test]
+    fn local_variables_are_restored() {
+        let mut vars = Variables::default();
+        vars.set("A", "outer").unwrap();
+        assert!(local(&string_vec!["local", "A"], &mut vars).is_err());
+
+        vars.push_scope();
+        local(&string_vec!["local", "A=inner", "B"], &mut vars).expect("`local` should be ok");
+        assert_eq!(vars.get("A"), Some("inner"));
+        vars.set("B", "set").unwrap();
+        local(&string_vec!["local", "A"], &mut vars).expect("`local` should be ok");
+        assert_eq!(
+            vars.get("A"),
+            Some("inner"),
+            "a second `local` keeps the value"
+        );
+        vars.pop_scope();
+
+        assert_eq!(vars.get("A"), Some("outer"));
+        assert_eq!(vars.get("B"), None);
+    }
+}
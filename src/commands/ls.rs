@@ -3,15 +3,12 @@ mod system;
 
 use std::{
     collections::VecDeque,
-    fs::{self, File},
-    io::Write,
+    fs,
+    io::{self, IsTerminal},
     path::Path,
 };
 
-use crate::{
-    ansi::{BOLD, ERROR_COLOR, RESET},
-    redirect,
-};
+use crate::error;
 
 pub const USAGE: &str = "Usage:\tls [-FalrR] [FILE|DIRECTORY]...";
 pub const OPTIONS_USAGE: &str = "\r\n-F      -- append file type indicators\r\n-a      -- list entries starting with .\r\n-l      -- long listing\r\n-r      -- reverse order\r\n-R      -- recursive";
@@ -69,10 +66,29 @@ impl LsFlags {
     }
 }
 
-pub fn ls(input: &[String]) -> Result<String, String> {
-    let (sources, targets) = redirect::separate_sources_from_targets(input);
-    let is_redirect = !targets.is_empty();
+// Returns the exit status: 1 if any path is missing, though whatever exists
+// is still listed, after an error for each path that doesn't.
+pub fn ls(input: &[String]) -> i32 {
+    // Output into a file or pipe is formatted differently from output to the
+    // terminal: one entry per line and no colors.
+    match list(input, !io::stdout().is_terminal()) {
+        Ok((listing, missing)) => {
+            for err in &missing {
+                error::handle_error("ls", err.clone());
+            }
+            print!("{listing}");
+            i32::from(!missing.is_empty())
+        }
+        Err(err) => {
+            error::handle_error("ls", err);
+            1
+        }
+    }
+}
 
+// The listing, along with an error for each path that doesn't exist.
+fn list(input: &[String], is_redirect: bool) -> Result<(String, Vec<String>), String> {
+    let sources: Vec<&String> = input[1..].iter().collect();
     let flags = LsFlags::parse(&sources)?;
 
     // Default to the current directory if no path is specified so we can reuse
@@ -95,19 +111,20 @@ pub fn ls(input: &[String]) -> Result<String, String> {
     let mut path_classification = classify_paths(&paths);
     sort_path_classification(&mut path_classification, &flags);
 
-    print_non_existent_paths(&path_classification.non_existent);
+    let non_existent = std::mem::take(&mut path_classification.non_existent);
 
     let mut running_results =
         process_initial_results(&path_classification.files, &flags, is_redirect)?;
 
-    finalize_directory_listing(
+    let listing = finalize_directory_listing(
         input,
         path_classification,
         &mut running_results,
         &flags,
         is_redirect,
-        targets,
-    )
+    )?;
+
+    Ok((listing, non_existent))
 }
 
 fn sort_path_classification(classification: &mut PathClassification, flags: &LsFlags) {
@@ -122,10 +139,6 @@ fn sort_path_classification(classification: &mut PathClassification, flags: &LsF
     }
 }
 
-fn print_non_existent_paths(non_existent: &[String]) {
-    print!("{}", non_existent.join(""));
-}
-
 fn process_initial_results(
     files: &[String],
     flags: &LsFlags,
@@ -148,71 +161,14 @@ fn finalize_directory_listing(
     running_results: &mut String,
     flags: &LsFlags,
     is_redirect: bool,
-    targets: Vec<[&String; 2]>,
 ) -> Result<String, String> {
-    let results = process_directories(
+    process_directories(
         input,
         path_classification.directories,
         running_results.to_string(),
         flags,
         is_redirect,
-    );
-
-    if targets.is_empty() || results.is_err() {
-        results
-    } else {
-        // This can't fail as we checked for errors above.
-        redirect(targets, results.unwrap());
-        Ok(String::new())
-    }
-}
-
-fn redirect(targets: Vec<[&String; 2]>, contents: String) {
-    for &target in targets.iter() {
-        let target_path = Path::new(target[1]);
-        if target_path.is_dir() {
-            println!(
-                "{ERROR_COLOR}0-shell: Is a directory: {path}{RESET}{BOLD}",
-                path = target[1]
-            );
-        }
-
-        if !target_path.exists() || target[0] == ">" {
-            match File::create(target_path) {
-                Ok(mut file) => {
-                    if let Err(_) = file.write_all(contents.as_bytes()) {
-                        println!(
-                            "{ERROR_COLOR}0-shell: Failed to write to file: {path}{RESET}{BOLD}",
-                            path = target[1]
-                        );
-                    }
-                }
-                Err(_) => {
-                    println!(
-                        "{ERROR_COLOR}0-shell: Failed to create file: {path}{RESET}{BOLD}",
-                        path = target[1]
-                    );
-                }
-            }
-        } else {
-            match File::options().append(true).create(true).open(target_path) {
-                Ok(mut file) => {
-                    if let Err(_) = file.write_all(contents.as_bytes()) {
-                        println!(
-                            "{ERROR_COLOR}0-shell: Failed to append to file: {path}{RESET}{BOLD}",
-                            path = target[1]
-                        );
-                    }
-                }
-                Err(_) => {
-                    println!(
-                        "{ERROR_COLOR}0-shell: Failed to open file: {path}{RESET}{BOLD}",
-                        path = target[1]
-                    );
-                }
-            }
-        }
-    }
+    )
 }
 
 fn process_files(
@@ -253,10 +209,7 @@ fn classify_paths(paths: &[&String]) -> PathClassification {
         } else if path.exists() {
             files.push(path_str.to_string());
         } else {
-            non_existent.push(format!(
-                "{ERROR_COLOR}ls: {path}: No such file or directory{RESET}{BOLD}\n",
-                path = path_str
-            ));
+            non_existent.push(format!("{path_str}: No such file or directory"));
         }
     }
 
@@ -340,7 +293,7 @@ fn process_directories(
 mod tests {
     use std::{env, fs, path::Path};
 
-    use super::ls;
+    use super::{list, ls};
     use crate::test_helpers::TempStore;
 
     #[test]
@@ -358,7 +311,7 @@ mod tests {
         let inputs = [basic, a, l, f, alf, l_a_f];
 
         for input in inputs {
-            assert!(ls(&input).is_ok(), "`ls` should be ok for {:?}", input);
+            assert_eq!(ls(&input), 0, "`ls` should be ok for {:?}", input);
         }
     }
 
@@ -397,44 +350,43 @@ mod tests {
         let original_dir = env::current_dir().expect("failed to get current directory");
         env::set_current_dir(root).expect("failed to set current directory");
 
-        let v = Path::new("v");
-        fs::write(v, "prefix").expect("failed to write to temp file");
+        let input: Vec<String> = vec!["ls", "file_1", "file_2", "folder_1", "folder_2"]
+            .into_iter()
+            .map(String::from)
+            .collect();
 
-        let input: Vec<String> = vec![
-            "ls", "file_1", "file_2", "folder_1", ">", "u", "folder_2", ">>", "v",
-        ]
-        .into_iter()
-        .map(String::from)
-        .collect();
-
-        let result = ls(&input);
-        assert!(
-            result.is_ok(),
-            "Result of `ls file_1 file_2 folder_1 > u folder_2 >> v` should be ok"
-        );
+        let result = list(&input, true);
+        env::set_current_dir(original_dir).expect("failed to set current directory");
 
-        let u = Path::new("u");
-        assert!(u.exists(), "Target file `u` should have been created");
-        assert!(v.exists(), "Target file `v` should have been created");
+        let contents = result
+            .expect("result of `ls file_1 file_2 folder_1 folder_2` should be ok")
+            .0
+            .replace("\r\n", "\n");
+        let expected = "file_1\nfile_2\n\nfolder_1:\nfile_a\nfile_b\nfolder_a\n\nfolder_2:\nfile_c\nfolder_c\nfolder_d\n";
 
-        let mut contents_of_u = fs::read_to_string(u).expect("failed to read target file `u`");
-        contents_of_u = contents_of_u.replace("\r\n", "\n");
-        let expected_u = "file_1\nfile_2\n\nfolder_1:\nfile_a\nfile_b\nfolder_a\n\nfolder_2:\nfile_c\nfolder_c\nfolder_d\n";
+        assert_eq!(
+            contents, expected,
+            "Redirected output of `ls` did not match expected"
+        );
+    }
 
-        let mut contents_of_v = fs::read_to_string(v).expect("failed to read target file `u`");
-        contents_of_v = contents_of_v.replace("\r\n", "\n");
-        let mut expected_v = String::from("prefix");
-        expected_v.push_str(expected_u);
+    #[test]
+    fn ls_fails_for_missing_paths() {
+        let temp_store = TempStore::new(1);
+        let root = Path::new(&temp_store.store[0]);
+        fs::create_dir_all(root).expect("failed to create dirs");
+        fs::write(root.join("file_1"), "").expect("failed to create file");
 
-        env::set_current_dir(original_dir).expect("failed to set current directory");
+        let file = root.join("file_1").to_string_lossy().into_owned();
+        let missing = root.join("missing").to_string_lossy().into_owned();
+        let input = vec![String::from("ls"), file.clone(), missing.clone()];
 
         assert_eq!(
-            contents_of_u, expected_u,
-            "Contents of new target file `u` did not match expected"
-        );
-        assert_eq!(
-            contents_of_v, expected_v,
-            "Contents of existing target file `v` did not match expected"
+            list(&input, true),
+            Ok((
+                format!("{file}\n"),
+                vec![format!("{missing}: No such file or directory")]
+            ))
         );
     }
 
@@ -448,25 +400,16 @@ mod tests {
         fs::write(root.join("file_root"), "").expect("failed to create file");
         fs::write(root.join("dir_a").join("file_a1"), "").expect("failed to create file");
 
-        let out_path = root.join("out");
         let root_abs = fs::canonicalize(root).expect("failed to canonicalize root");
-        let input: Vec<String> = vec![
-            "ls",
-            "-R",
-            root_abs.to_string_lossy().as_ref(),
-            ">",
-            out_path.to_string_lossy().as_ref(),
-        ]
-        .into_iter()
-        .map(String::from)
-        .collect();
+        let input: Vec<String> = vec!["ls", "-R", root_abs.to_string_lossy().as_ref()]
+            .into_iter()
+            .map(String::from)
+            .collect();
 
-        let result = ls(&input);
+        let result = list(&input, true);
         assert!(result.is_ok(), "`ls -R` should succeed on populated tree");
 
-        let mut contents =
-            fs::read_to_string(&out_path).expect("failed to read redirected ls output");
-        contents = contents.replace("\r\n", "\n");
+        let contents = result.unwrap().0.replace("\r\n", "\n");
 
         let dir_a = root_abs.join("dir_a");
         let dir_b = root_abs.join("dir_b");
@@ -508,25 +451,16 @@ file_a1\n\
         fs::write(root.join("dir_a").join("file_a1"), "").expect("failed to create file");
         fs::write(root.join("dir_b").join(".hidden_b"), "").expect("failed to create file");
 
-        let out_path = root.join("out_hidden");
         let root_abs = fs::canonicalize(root).expect("failed to canonicalize root");
-        let input: Vec<String> = vec![
-            "ls",
-            "-Rra",
-            root_abs.to_string_lossy().as_ref(),
-            ">",
-            out_path.to_string_lossy().as_ref(),
-        ]
-        .into_iter()
-        .map(String::from)
-        .collect();
+        let input: Vec<String> = vec!["ls", "-Rra", root_abs.to_string_lossy().as_ref()]
+            .into_iter()
+            .map(String::from)
+            .collect();
 
-        let result = ls(&input);
+        let result = list(&input, true);
         assert!(result.is_ok(), "`ls -Rra` should succeed on populated tree");
 
-        let mut contents =
-            fs::read_to_string(&out_path).expect("failed to read redirected ls output");
-        contents = contents.replace("\r\n", "\n");
+        let contents = result.unwrap().0.replace("\r\n", "\n");
 
         let dir_a = root_abs.join("dir_a");
         let dir_b = root_abs.join("dir_b");
@@ -17,23 +17,42 @@ pub fn man(input: &[String]) -> Result<String, String> {
         }
 
         let next = match cmd.as_str() {
+            "alias" => alias::USAGE,
             "bg" => bg::USAGE,
+            "break" => r#break::USAGE,
             "cat" => cat::USAGE,
             "cd" => cd::USAGE,
+            "continue" => r#continue::USAGE,
             "cp" => cp::USAGE,
+            "declare" => declare::USAGE,
+            "disown" => &format!("{}{}", disown::USAGE, disown::OPTIONS_USAGE),
             "echo" => echo::USAGE,
             "exit" => exit::USAGE,
+            "export" => &format!("{}{}", export::USAGE, export::OPTIONS_USAGE),
             "fg" => fg::USAGE,
             "jobs" => &format!("{}{}", jobs::USAGE, jobs::OPTIONS_USAGE),
             "kill" => kill::USAGE,
+            "let" => r#let::USAGE,
+            "local" => local::USAGE,
             "ls" => &format!("{}{}", ls::USAGE, ls::OPTIONS_USAGE),
             "man" => man::USAGE,
             "mkdir" => mkdir::USAGE,
             "mv" => mv::USAGE,
+            "nohup" => nohup::USAGE,
             "pwd" => pwd::USAGE,
+            "readonly" => readonly::USAGE,
+            "return" => r#return::USAGE,
             "rm" => &format!("{}{}", rm::USAGE, rm::OPTIONS_USAGE),
+            "set" => set::USAGE,
+            "shopt" => &format!("{}{}", shopt::USAGE, shopt::OPTIONS_USAGE),
             "sleep" => sleep::USAGE,
+            "source" | "." => source::USAGE,
+            "test" | "[" => test::USAGE,
             "touch" => touch::USAGE,
+            "trap" => trap::USAGE,
+            "unalias" => unalias::USAGE,
+            "unset" => unset::USAGE,
+            "wait" => wait::USAGE,
             _ => {
                 wouldbe_next = format!("{ERROR_COLOR}No manual entry for {cmd}{RESET}{BOLD}");
                 &wouldbe_next
@@ -1,18 +1,31 @@
 use std::{fs, path::Path};
 
-pub const USAGE: &str = "Usage:\tmkdir <DIRECTORY>...";
+pub const USAGE: &str = "Usage:\tmkdir [-p] <DIRECTORY>...";
 
 pub fn mkdir(input: &[String]) -> Result<String, String> {
     is_input_len_at_least_two(input)?;
 
+    // With `-p`, create any missing parents too, and don't mind if the
+    // directory already exists.
+    let parents = input[1] == "-p";
+    let paths = &input[1 + usize::from(parents)..];
+    if paths.is_empty() {
+        return Err(format!("Not enough arguments\n{}", USAGE));
+    }
+
     let mut errors = Vec::new();
 
-    for path_str in input[1..].iter() {
+    for path_str in paths {
         let path = Path::new(path_str);
+        let create = if parents {
+            fs::create_dir_all
+        } else {
+            fs::create_dir
+        };
 
-        if path.exists() {
+        if path.exists() && !parents {
             errors.push(format!("File exists: {}", path.display()));
-        } else if let Err(err) = fs::create_dir(path) {
+        } else if let Err(err) = create(path) {
             errors.push(format!(
                 "Failed to create {}: {}",
                 path.display(),
@@ -65,6 +78,24 @@ mod tests {
         fs::remove_dir(test_dir).expect("failed to remove directory");
     }
 
+    #[test]
+    fn mkdir_parents() {
+        let temp = TempStore::new(1);
+        let nested = format!("{}/a/b", temp.store[0]);
+
+        assert_eq!(
+            mkdir(&string_vec!["mkdir", "-p", &nested]),
+            Ok(String::new())
+        );
+        assert!(Path::new(&nested).is_dir());
+        assert_eq!(
+            mkdir(&string_vec!["mkdir", "-p", &nested]),
+            Ok(String::new()),
+            "an existing directory is fine with `-p`"
+        );
+        assert!(mkdir(&string_vec!["mkdir", "-p"]).is_err());
+    }
+
     #[test]
     fn mkdir_missing_argument_fails() {
         let input = string_vec!["mkdir"];
@@ -0,0 +1,151 @@
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    io::{self, IsTerminal},
+    os::{
+        fd::AsRawFd,
+        unix::{fs::OpenOptionsExt, process::CommandExt},
+    },
+    process::Command,
+};
+
+use crate::{commands, fork};
+
+pub const USAGE: &str = "Usage:\tnohup COMMAND [ARG ...]";
+
+// Run a command that ignores SIGHUP, so that it carries on after the terminal
+// hangs up or the shell sends it SIGHUP on exiting. Output that would go to
+// the terminal goes to `nohup.out` instead, in the current directory if
+// possible, or else the home directory, and input comes from nowhere. As it
+// replaces itself with the command, this only returns if that fails, with an
+// error and the status to exit with: 127 if the command isn't found, 126 if it
+// can't be run, and 125 if `nohup` itself failed. A command that this shell
+// runs in a worker, such as `ls`, is this shell's own, not the one in `PATH`.
+pub fn nohup(input: &[String]) -> (String, i32) {
+    if input.len() < 2 {
+        return (format!("Not enough arguments\n{USAGE}"), 125);
+    }
+
+    unsafe { libc::signal(libc::SIGHUP, libc::SIG_IGN) };
+
+    let ignoring_input = io::stdin().is_terminal();
+    if ignoring_input {
+        match File::open("/dev/null") {
+            Ok(null) => unsafe {
+                libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO);
+            },
+            Err(err) => return (format!("Failed to ignore input: {err}"), 125),
+        }
+    }
+
+    if io::stdout().is_terminal() {
+        let (file, name) = match output_file() {
+            Ok(opened) => opened,
+            Err(err) => return (format!("Failed to open 'nohup.out': {err}"), 125),
+        };
+        if ignoring_input {
+            eprintln!("nohup: ignoring input and appending output to '{name}'");
+        } else {
+            eprintln!("nohup: appending output to '{name}'");
+        }
+        unsafe { libc::dup2(file.as_raw_fd(), libc::STDOUT_FILENO) };
+    } else if ignoring_input {
+        eprintln!("nohup: ignoring input");
+    }
+
+    if io::stderr().is_terminal() {
+        unsafe { libc::dup2(libc::STDOUT_FILENO, libc::STDERR_FILENO) };
+    }
+
+    let args = fork::get_exec_args(commands::is_worker(&input[1]), &input[1..]);
+    let err = Command::new(&args[0]).args(&args[1..]).exec();
+    match err.kind() {
+        io::ErrorKind::NotFound => (format!("{}: command not found", input[1]), 127),
+        _ => (format!("{}: {err}", input[1]), 126),
+    }
+}
+
+// `nohup.out`, opened for appending, and its name, as the user should look
+// for it.
+fn output_file() -> io::Result<(File, String)> {
+    let open = |path: &str| {
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .mode(0o600)
+            .open(path)
+    };
+    match open("nohup.out") {
+        Ok(file) => Ok((file, String::from("nohup.out"))),
+        Err(err) => {
+            let Ok(home) = env::var("HOME") else {
+                return Err(err);
+            };
+            let path = format!("{home}/nohup.out");
+            open(&path).map(|file| (file, path))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CString, fs, os::unix::ffi::OsStrExt, path::Path, ptr};
+
+    use super::nohup;
+    use crate::{string_vec, test_helpers::TempStore};
+
+    // Run `nohup` in a child process, in `dir`, with its output going to a
+    // terminal. Returns the child's exit code.
+    fn run_in_terminal(dir: &Path, input: &[String]) -> i32 {
+        let (mut leader, mut terminal) = (0, 0);
+        let opened = unsafe {
+            libc::openpty(
+                &mut leader,
+                &mut terminal,
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null(),
+            )
+        };
+        assert_eq!(opened, 0, "failed to open a terminal");
+        let dir = CString::new(dir.as_os_str().as_bytes()).expect("dir should be a C string");
+
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            unsafe {
+                libc::chdir(dir.as_ptr());
+                libc::dup2(terminal, libc::STDOUT_FILENO);
+                libc::dup2(terminal, libc::STDERR_FILENO);
+                let (_, status) = nohup(input);
+                libc::_exit(status);
+            }
+        }
+
+        let mut status = 0;
+        unsafe {
+            libc::waitpid(pid, &mut status, 0);
+            libc::close(terminal);
+            libc::close(leader);
+        }
+        assert!(libc::WIFEXITED(status), "the command should exit");
+        libc::WEXITSTATUS(status)
+    }
+
+    #[test]
+    fn nohup_ignores_hangups_and_keeps_output() {
+        let temp_store = TempStore::new(1);
+        fs::create_dir_all(&temp_store.store[0]).expect("failed to create dir");
+        let dir = fs::canonicalize(&temp_store.store[0]).expect("failed to find dir");
+
+        let input = string_vec!["nohup", "sh", "-c", "kill -HUP $$; echo survived"];
+        assert_eq!(run_in_terminal(&dir, &input), 0);
+        assert_eq!(
+            fs::read_to_string(dir.join("nohup.out")).ok().as_deref(),
+            Some("survived\n"),
+            "output should go to `nohup.out`, even after SIGHUP"
+        );
+
+        let input = string_vec!["nohup", "no-such-command"];
+        assert_eq!(run_in_terminal(&dir, &input), 127);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    parser::is_name,
+    variables::{Variables, quote},
+};
+
+pub const USAGE: &str = "Usage:\treadonly [-p] [NAME[=VALUE] ...]";
+
+pub fn readonly(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    let names: Vec<&String> = input.iter().skip(1).filter(|arg| *arg != "-p").collect();
+
+    if names.is_empty() {
+        return Ok(list(vars));
+    }
+
+    let mut errors = Vec::new();
+    for arg in names {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        if !is_name(name) {
+            errors.push(format!("`{arg}': not a valid identifier"));
+            continue;
+        }
+        if let Some(value) = value
+            && let Err(err) = vars.set(name, value)
+        {
+            errors.push(err);
+            continue;
+        }
+        vars.make_readonly(name);
+    }
+
+    if errors.is_empty() {
+        Ok(String::new())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+fn list(vars: &Variables) -> String {
+    let mut output = String::new();
+    for (name, variable) in vars.iter().filter(|(_, v)| v.readonly) {
+        match &variable.value {
+            Some(value) => output.push_str(&format!("readonly {name}={}\n", quote(value))),
+            None => output.push_str(&format!("readonly {name}\n")),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::readonly;
+    use crate::{string_vec, variables::Variables};
+
+    #[test]
+    fn readonly_fixes_variables() {
+        let mut vars = Variables::default();
+        readonly(&string_vec!["readonly", "FIXED=1"], &mut vars).expect("`readonly` should be ok");
+
+        assert!(vars.set("FIXED", "2").is_err());
+        assert_eq!(
+            readonly(&string_vec!["readonly", "FIXED=3"], &mut vars),
+            Err(String::from("0-shell: FIXED: readonly variable"))
+        );
+        assert_eq!(
+            readonly(&string_vec!["readonly", "-p"], &mut vars),
+            Ok(String::from("readonly FIXED=1\n"))
+        );
+    }
+}
@@ -0,0 +1,22 @@
+pub const USAGE: &str = "Usage:\treturn [n]";
+
+// Leave the function or sourced file that's running, with status `n`, by
+// default the status of the last command. `depth` is how many of those are
+// running. Returns the status to leave with.
+pub fn r#return(input: &[String], depth: usize, last_status: i32) -> Result<i32, String> {
+    if depth == 0 {
+        return Err(String::from(
+            "Can only `return' from a function or sourced script",
+        ));
+    }
+    if input.len() > 2 {
+        return Err(format!("Too many arguments\n{USAGE}"));
+    }
+
+    match input.get(1) {
+        None => Ok(last_status),
+        Some(arg) => arg
+            .parse::<i32>()
+            .map_err(|_| format!("Numeric argument required: {arg}")),
+    }
+}
@@ -0,0 +1,63 @@
+use crate::variables::{Variables, quote};
+
+pub const USAGE: &str = "Usage:\tset [-+benx]";
+
+// List the shell's variables, exported or not. With arguments, set options
+// by letter, as in `set -e`, or unset them, as in `set +e`.
+pub fn set(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    if input.len() > 1 {
+        for arg in &input[1..] {
+            let (on, flags) = match arg.split_at_checked(1) {
+                Some(("-", flags)) if !flags.is_empty() => (true, flags),
+                Some(("+", flags)) if !flags.is_empty() => (false, flags),
+                _ => return Err(format!("{arg}: invalid argument\n{USAGE}")),
+            };
+            for c in flags.chars() {
+                if !vars.options.set_flag(c, on) {
+                    return Err(format!("-{c}: invalid option\n{USAGE}"));
+                }
+            }
+        }
+        return Ok(String::new());
+    }
+
+    let mut output = String::new();
+    for (name, variable) in vars.iter() {
+        if let Some(value) = &variable.value {
+            output.push_str(&format!("{name}={}\n", quote(value)));
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::set;
+    use crate::{string_vec, variables::Variables};
+
+    #[test]
+    fn set_lists_variables() {
+        let mut vars = Variables::default();
+        vars.set("B", "two words").unwrap();
+        vars.set("A", "1").unwrap();
+        vars.export("UNSET", true);
+
+        assert_eq!(
+            set(&string_vec!["set"], &mut vars),
+            Ok(String::from("A=1\nB='two words'\n"))
+        );
+    }
+
+    #[test]
+    fn set_changes_options() {
+        let mut vars = Variables::default();
+        set(&string_vec!["set", "-bx", "-e"], &mut vars).unwrap();
+        assert!(vars.options.notify && vars.options.xtrace && vars.options.errexit);
+
+        set(&string_vec!["set", "+xe"], &mut vars).unwrap();
+        assert!(vars.options.notify && !vars.options.xtrace && !vars.options.errexit);
+
+        assert!(set(&string_vec!["set", "-q"], &mut vars).is_err());
+        assert!(set(&string_vec!["set", "b"], &mut vars).is_err());
+    }
+}
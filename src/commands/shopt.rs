@@ -0,0 +1,112 @@
+use crate::{options::SHOPT_NAMES, variables::Variables};
+
+pub const USAGE: &str = "Usage:\tshopt [-s|-u] [-p] [OPTNAME ...]";
+pub const OPTIONS_USAGE: &str = "\r\n-s      -- set each OPTNAME\r\n-u      -- unset each OPTNAME\r\n-p      -- list in a form that could be read back in";
+
+pub fn shopt(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    let mut setting = None;
+    let mut reusable = false;
+    let mut names = Vec::new();
+
+    for arg in input.iter().skip(1) {
+        if arg.starts_with('-') && names.is_empty() {
+            for c in arg.chars().skip(1) {
+                match c {
+                    's' if setting != Some(false) => setting = Some(true),
+                    'u' if setting != Some(true) => setting = Some(false),
+                    's' | 'u' => {
+                        return Err(format!(
+                            "Cannot set and unset shell options simultaneously\n{USAGE}"
+                        ));
+                    }
+                    'p' => reusable = true,
+                    _ => return Err(format!("Invalid option -- '{c}'\n{USAGE}")),
+                }
+            }
+        } else {
+            names.push(arg.as_str());
+        }
+    }
+
+    if let Some(name) = names.iter().find(|name| vars.options.shopt(name).is_none()) {
+        return Err(format!("{name}: invalid shell option name"));
+    }
+
+    match setting {
+        // With no names, list the options that are set (or unset).
+        Some(on) if names.is_empty() => {
+            let listed = SHOPT_NAMES
+                .into_iter()
+                .filter(|name| vars.options.shopt(name) == Some(on));
+            Ok(list(listed, vars, reusable))
+        }
+        Some(on) => {
+            for name in names {
+                vars.options.set_shopt(name, on);
+            }
+            Ok(String::new())
+        }
+        None if names.is_empty() => Ok(list(SHOPT_NAMES.into_iter(), vars, reusable)),
+        None => Ok(list(names.into_iter(), vars, reusable)),
+    }
+}
+
+fn list<'a>(names: impl Iterator<Item = &'a str>, vars: &Variables, reusable: bool) -> String {
+    let mut output = String::new();
+    for name in names {
+        let on = vars.options.shopt(name) == Some(true);
+        if reusable {
+            let flag = if on { 's' } else { 'u' };
+            output.push_str(&format!("shopt -{flag} {name}\n"));
+        } else {
+            let state = if on { "on" } else { "off" };
+            output.push_str(&format!("{name}\t{state}\n"));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shopt;
+    use crate::{string_vec, variables::Variables};
+
+    #[test]
+    fn shopt_sets_and_lists_options() {
+        let mut vars = Variables::default();
+
+        assert_eq!(
+            shopt(
+                &string_vec!["shopt", "-s", "nullglob", "dotglob"],
+                &mut vars
+            ),
+            Ok(String::new())
+        );
+        assert!(vars.options.nullglob && vars.options.dotglob);
+        assert_eq!(
+            shopt(&string_vec!["shopt", "nullglob", "failglob"], &mut vars),
+            Ok(String::from("nullglob\ton\nfailglob\toff\n"))
+        );
+        assert_eq!(
+            shopt(&string_vec!["shopt", "-s"], &mut vars),
+            Ok(String::from("dotglob\ton\nnullglob\ton\n")),
+            "`-s` on its own should list the options that are set"
+        );
+
+        shopt(&string_vec!["shopt", "-u", "dotglob"], &mut vars).unwrap();
+        assert_eq!(
+            shopt(&string_vec!["shopt", "-p", "dotglob"], &mut vars),
+            Ok(String::from("shopt -u dotglob\n"))
+        );
+    }
+
+    #[test]
+    fn shopt_rejects_unknown_options() {
+        let mut vars = Variables::default();
+        assert_eq!(
+            shopt(&string_vec!["shopt", "-s", "nosuch"], &mut vars),
+            Err(String::from("nosuch: invalid shell option name"))
+        );
+        assert!(shopt(&string_vec!["shopt", "-su", "nullglob"], &mut vars).is_err());
+    }
+}
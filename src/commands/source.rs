@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use crate::{
+    commands::{Flow, jobs::JobTable},
+    script,
+    variables::Variables,
+};
+
+pub const USAGE: &str = "Usage:\tsource FILENAME [ARGUMENTS]\n\t. FILENAME [ARGUMENTS]";
+
+// Run the commands in a file in the shell itself, so that any variables,
+// functions or options they set last. Any arguments become the positional
+// parameters while it runs. `return` leaves the file early.
+pub fn source(
+    input: &[String],
+    jobs: &mut JobTable,
+    exit_attempted: &mut bool,
+    vars: &mut Variables,
+) -> Result<Flow, String> {
+    let Some(name) = input.get(1) else {
+        return Err(format!("Filename argument required\n{USAGE}"));
+    };
+    let path = find(name, vars.get("PATH"));
+    let text = script::read_script(&path).map_err(|(err, _)| format!("{name}: {err}"))?;
+
+    let positional = (input.len() > 2).then(|| {
+        let args = input[2..].to_vec();
+        std::mem::replace(&mut vars.positional, args)
+    });
+    vars.sourced += 1;
+    vars.last_status = 0;
+
+    let result = script::run_text(name, &text, jobs, exit_attempted, vars);
+
+    vars.sourced -= 1;
+    if let Some(positional) = positional {
+        vars.positional = positional;
+    }
+
+    match result? {
+        Flow::Return(status) => {
+            vars.last_status = status;
+            Ok(Flow::Next)
+        }
+        flow => Ok(flow),
+    }
+}
+
+// A name without a `/` is looked for in the directories of `PATH`, as a
+// command would be, though the file needn't be executable, and then in the
+// current directory.
+fn find(name: &str, path: Option<&str>) -> String {
+    if name.contains('/') {
+        return String::from(name);
+    }
+    path.unwrap_or_default()
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Path::new(dir).join(name))
+        .find(|candidate| candidate.is_file())
+        .map_or_else(
+            || String::from(name),
+            |candidate| candidate.to_string_lossy().into_owned(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::source;
+    use crate::{
+        commands::{Flow, jobs::JobTable},
+        string_vec,
+        test_helpers::TempStore,
+        variables::Variables,
+    };
+
+    #[test]
+    fn source_runs_in_the_shell() {
+        let store = TempStore::new(1);
+        let file = store.store[0].clone();
+        fs::write(&file, "f() { let n=$1+1; }\nf $1\nreturn\nn=never\n").unwrap();
+
+        let mut vars = Variables::default();
+        vars.positional = string_vec!["outer"];
+        let flow = source(
+            &string_vec!["source", &file, "41"],
+            &mut JobTable::default(),
+            &mut false,
+            &mut vars,
+        );
+
+        assert_eq!(flow, Ok(Flow::Next));
+        assert_eq!(vars.get("n"), Some("42"));
+        assert_eq!(vars.positional, string_vec!["outer"]);
+        assert_eq!(vars.sourced, 0);
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    ffi::CString,
+    fs::{self, Metadata},
+    os::unix::fs::{FileTypeExt, MetadataExt},
+};
+
+pub const USAGE: &str = "Usage:\ttest EXPRESSION\n\t[ EXPRESSION ]";
+
+// The operators that take one operand, and those that take two. `[[ ... ]]`
+// has these too, along with `=~`.
+pub const UNARY_OPERATORS: [&str; 19] = [
+    "-b", "-c", "-d", "-e", "-f", "-g", "-h", "-k", "-L", "-n", "-p", "-r", "-s", "-S", "-t", "-u",
+    "-w", "-x", "-z",
+];
+pub const BINARY_OPERATORS: [&str; 15] = [
+    "=", "==", "!=", "<", ">", "-eq", "-ne", "-lt", "-le", "-gt", "-ge", "-nt", "-ot", "-ef", "=~",
+];
+
+// Evaluate a conditional expression, for the status to be 0 if it's true and
+// 1 if it's false. As `[`, the last argument must be `]`. An expression that
+// can't be evaluated is an error.
+pub fn test(input: &[String]) -> Result<bool, String> {
+    let mut args: Vec<&str> = input.iter().skip(1).map(String::as_str).collect();
+    if input[0] == "[" && args.pop() != Some("]") {
+        return Err(String::from("Missing `]'"));
+    }
+    evaluate(&args)
+}
+
+// With four arguments or fewer, the number of them decides how they're read,
+// so that, e.g., `[ "$x" = ! ]` compares strings whatever `$x` is. Longer
+// expressions are parsed with `!`, `-a`, `-o` and parentheses, in that order
+// of precedence.
+fn evaluate(args: &[&str]) -> Result<bool, String> {
+    match *args {
+        [] => Ok(false),
+        [arg] => Ok(!arg.is_empty()),
+        ["!", arg] => Ok(arg.is_empty()),
+        [op, operand] if UNARY_OPERATORS.contains(&op) => unary(op, operand),
+        [op, _] => Err(format!("{op}: unary operator expected")),
+        [left, op, right] if BINARY_OPERATORS.contains(&op) && op != "=~" => {
+            binary(left, op, right)
+        }
+        [left, "-a", right] => Ok(!left.is_empty() && !right.is_empty()),
+        [left, "-o", right] => Ok(!left.is_empty() || !right.is_empty()),
+        ["!", ..] if args.len() <= 4 => evaluate(&args[1..]).map(|value| !value),
+        ["(", arg, ")"] => Ok(!arg.is_empty()),
+        ["(", op, operand, ")"] if UNARY_OPERATORS.contains(&op) => unary(op, operand),
+        [_, op, _] => Err(format!("{op}: binary operator expected")),
+        _ => {
+            let mut expression = Expression { args, position: 0 };
+            let value = expression.or()?;
+            match expression.args.get(expression.position) {
+                None => Ok(value),
+                Some(arg) => Err(format!("{arg}: unexpected argument")),
+            }
+        }
+    }
+}
+
+struct Expression<'a> {
+    args: &'a [&'a str],
+    position: usize,
+}
+
+impl<'a> Expression<'a> {
+    fn peek(&self, offset: usize) -> Option<&'a str> {
+        self.args.get(self.position + offset).copied()
+    }
+
+    fn or(&mut self) -> Result<bool, String> {
+        let mut value = self.and()?;
+        while self.peek(0) == Some("-o") {
+            self.position += 1;
+            value = self.and()? || value;
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<bool, String> {
+        let mut value = self.not()?;
+        while self.peek(0) == Some("-a") {
+            self.position += 1;
+            value = self.not()? && value;
+        }
+        Ok(value)
+    }
+
+    fn not(&mut self) -> Result<bool, String> {
+        if self.peek(0) == Some("!") {
+            self.position += 1;
+            return self.not().map(|value| !value);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<bool, String> {
+        let Some(arg) = self.peek(0) else {
+            return Err(String::from("Argument expected"));
+        };
+
+        if let (Some(op), Some(right)) = (self.peek(1), self.peek(2))
+            && BINARY_OPERATORS.contains(&op)
+            && op != "=~"
+        {
+            self.position += 3;
+            return binary(arg, op, right);
+        }
+        if arg == "(" {
+            self.position += 1;
+            let value = self.or()?;
+            if self.peek(0) != Some(")") {
+                return Err(String::from("Missing `)'"));
+            }
+            self.position += 1;
+            return Ok(value);
+        }
+        if UNARY_OPERATORS.contains(&arg)
+            && let Some(operand) = self.peek(1)
+        {
+            self.position += 2;
+            return unary(arg, operand);
+        }
+        self.position += 1;
+        Ok(!arg.is_empty())
+    }
+}
+
+// A test of a string, or of the file it names. Symbolic links are followed,
+// except by `-h` and `-L`.
+pub fn unary(op: &str, operand: &str) -> Result<bool, String> {
+    let metadata = || fs::metadata(operand).ok();
+    let is = |test: fn(&Metadata) -> bool| metadata().is_some_and(|m| test(&m));
+
+    Ok(match op {
+        "-n" => !operand.is_empty(),
+        "-z" => operand.is_empty(),
+        "-e" => metadata().is_some(),
+        "-f" => is(Metadata::is_file),
+        "-d" => is(Metadata::is_dir),
+        "-s" => is(|m| m.len() > 0),
+        "-b" => is(|m| m.file_type().is_block_device()),
+        "-c" => is(|m| m.file_type().is_char_device()),
+        "-p" => is(|m| m.file_type().is_fifo()),
+        "-S" => is(|m| m.file_type().is_socket()),
+        "-g" => is(|m| m.mode() & libc::S_ISGID != 0),
+        "-u" => is(|m| m.mode() & libc::S_ISUID != 0),
+        "-k" => is(|m| m.mode() & libc::S_ISVTX != 0),
+        "-h" | "-L" => fs::symlink_metadata(operand).is_ok_and(|m| m.file_type().is_symlink()),
+        "-r" => access(operand, libc::R_OK),
+        "-w" => access(operand, libc::W_OK),
+        "-x" => access(operand, libc::X_OK),
+        "-t" => {
+            let fd = integer(operand)?;
+            i32::try_from(fd).is_ok_and(|fd| unsafe { libc::isatty(fd) } == 1)
+        }
+        _ => return Err(format!("{op}: unary operator expected")),
+    })
+}
+
+// A comparison of strings, integers or files. Strings are compared byte by
+// byte.
+pub fn binary(left: &str, op: &str, right: &str) -> Result<bool, String> {
+    let files = || {
+        let left = fs::metadata(left).ok();
+        let right = fs::metadata(right).ok();
+        (left, right)
+    };
+
+    Ok(match op {
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        ">" => left > right,
+        "-eq" => integer(left)? == integer(right)?,
+        "-ne" => integer(left)? != integer(right)?,
+        "-lt" => integer(left)? < integer(right)?,
+        "-le" => integer(left)? <= integer(right)?,
+        "-gt" => integer(left)? > integer(right)?,
+        "-ge" => integer(left)? >= integer(right)?,
+        // A file that exists is newer than one that doesn't.
+        "-nt" => match files() {
+            (Some(left), Some(right)) => modified(&left) > modified(&right),
+            (left, _) => left.is_some(),
+        },
+        "-ot" => match files() {
+            (Some(left), Some(right)) => modified(&left) < modified(&right),
+            (_, right) => right.is_some(),
+        },
+        "-ef" => match files() {
+            (Some(left), Some(right)) => left.dev() == right.dev() && left.ino() == right.ino(),
+            _ => false,
+        },
+        _ => return Err(format!("{op}: binary operator expected")),
+    })
+}
+
+fn integer(arg: &str) -> Result<i64, String> {
+    arg.trim()
+        .parse()
+        .map_err(|_| format!("{arg}: integer expression expected"))
+}
+
+fn modified(metadata: &Metadata) -> (i64, i64) {
+    (metadata.mtime(), metadata.mtime_nsec())
+}
+
+// Whether the shell may read, write or execute a file, as its permissions and
+// the user's identity allow.
+fn access(path: &str, mode: i32) -> bool {
+    CString::new(path).is_ok_and(|path| unsafe { libc::access(path.as_ptr(), mode) } == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::test;
+    use crate::{string_vec, test_helpers::TempStore};
+
+    #[test]
+    fn test_evaluates_expressions() {
+        let store = TempStore::new(2);
+        let (file, dir) = (&store.store[0], &store.store[1]);
+        fs::write(file, "x").unwrap();
+        fs::create_dir(dir).unwrap();
+
+        let cases = [
+            (string_vec!["test"], false),
+            (string_vec!["test", ""], false),
+            (string_vec!["[", "-f", file, "]"], true),
+            (string_vec!["[", "-d", file, "]"], false),
+            (string_vec!["test", "-d", dir, "-a", "-s", file], true),
+            (string_vec!["test", "!", "-e", "nosuch"], true),
+            (string_vec!["test", "abc", "=", "abc"], true),
+            (string_vec!["test", "!", "=", "!"], true),
+            (string_vec!["test", "-n", "x", "-o", "-z", "x"], true),
+            (string_vec!["test", "10", "-gt", "9"], true),
+            (string_vec!["test", "10", ">", "9"], false),
+            (
+                string_vec!["test", "(", "1", "-eq", "2", ")", "-o", "x"],
+                true,
+            ),
+            (
+                string_vec!["test", "!", "(", "a", "=", "a", ")", "-a", "b"],
+                false,
+            ),
+            (string_vec!["test", file, "-ef", file], true),
+            (string_vec!["test", file, "-nt", "nosuch"], true),
+        ];
+        for (args, expected) in cases {
+            assert_eq!(test(&args), Ok(expected), "{args:?}");
+        }
+
+        assert!(test(&string_vec!["[", "x"]).is_err());
+        assert!(test(&string_vec!["test", "a", "-eq", "1"]).is_err());
+        assert!(test(&string_vec!["test", "a", "b"]).is_err());
+        assert!(test(&string_vec!["test", "(", "a", "b", "c", "d"]).is_err());
+    }
+}
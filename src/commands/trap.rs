@@ -0,0 +1,237 @@
+use crate::{
+    commands::{Flow, jobs::JobTable},
+    error, script,
+    signals::{self, Condition},
+    variables::{Variables, quote},
+};
+
+pub const USAGE: &str = "Usage:\ttrap [-p] [[ACTION] CONDITION ...]";
+
+// Set the action to run when the shell receives a signal, or for `EXIT`,
+// `ERR` or `DEBUG`. An empty action ignores the signal, and `-` sets it back
+// to what it was when the shell started, as does giving the conditions
+// alone. With no arguments, or `-p`, show the traps.
+pub fn trap(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    let mut args = &input[1..];
+    let mut print = false;
+    match args.first().map(String::as_str) {
+        Some("-p") => {
+            print = true;
+            args = &args[1..];
+        }
+        Some("--") => args = &args[1..],
+        Some(arg) if arg.starts_with('-') && arg != "-" => {
+            return Err(format!("{arg}: invalid option\n{USAGE}"));
+        }
+        _ => {}
+    }
+
+    if print || args.is_empty() {
+        let conditions: Vec<Condition> = match args.is_empty() {
+            true => vars.traps().keys().copied().collect(),
+            false => args
+                .iter()
+                .map(|arg| condition(arg))
+                .collect::<Result<_, _>>()?,
+        };
+        return Ok(conditions
+            .into_iter()
+            .filter_map(|condition| {
+                let action = vars.trap(condition)?;
+                Some(format!("trap -- {} {condition}\n", quote(action)))
+            })
+            .collect());
+    }
+
+    // As POSIX says, a first argument that's a number is a condition.
+    let (action, names) = match args[0].as_str() {
+        "-" => (None, &args[1..]),
+        first if args.len() == 1 || first.parse::<u32>().is_ok() => (None, args),
+        first => (Some(first), &args[1..]),
+    };
+    let conditions = names
+        .iter()
+        .map(|name| condition(name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for condition in conditions {
+        if let Condition::Signal(sig) = condition {
+            // A script can't change what happens to a signal that was ignored
+            // when it started.
+            if !vars.interactive && signals::was_ignored_on_entry(sig) {
+                continue;
+            }
+            match action {
+                None => signals::reset(sig),
+                Some("") => signals::ignore(sig),
+                Some(_) => signals::catch(sig),
+            }
+            .map_err(|err| format!("{condition}: {err}"))?;
+        }
+        vars.set_trap(condition, action);
+    }
+    Ok(String::new())
+}
+
+// `EXIT` (or `0`), `ERR`, `DEBUG`, or a signal.
+fn condition(name: &str) -> Result<Condition, String> {
+    match name.to_ascii_uppercase().as_str() {
+        "EXIT" | "0" => Ok(Condition::Exit),
+        "ERR" => Ok(Condition::Error),
+        "DEBUG" => Ok(Condition::Debug),
+        _ => match signals::number(name) {
+            Some(libc::SIGKILL | libc::SIGSTOP) => Err(format!("{name}: cannot be trapped")),
+            Some(sig) => Ok(Condition::Signal(sig)),
+            None => Err(format!("{name}: invalid signal specification")),
+        },
+    }
+}
+
+// Run the actions for any signals the shell has caught since it last
+// checked. The shell does this between commands, rather than when the signal
+// arrives, so that it's never interrupted halfway through changing something.
+pub fn run_pending(jobs: &mut JobTable, exit_attempted: &mut bool, vars: &mut Variables) -> Flow {
+    if vars.trapping {
+        return Flow::Next;
+    }
+    let caught: Vec<(i32, String)> = vars
+        .traps()
+        .iter()
+        .filter_map(|(condition, action)| match condition {
+            Condition::Signal(sig) => Some((*sig, action.clone())),
+            _ => None,
+        })
+        .collect();
+
+    for (sig, action) in caught {
+        if signals::take_pending(sig) {
+            let flow = run_action(&action, jobs, exit_attempted, vars);
+            if flow != Flow::Next {
+                return flow;
+            }
+        }
+    }
+    Flow::Next
+}
+
+// Run the action for `ERR` or `DEBUG`, if there is one. Neither runs during
+// another trap's action.
+pub fn run_condition(
+    condition: Condition,
+    jobs: &mut JobTable,
+    exit_attempted: &mut bool,
+    vars: &mut Variables,
+) -> Flow {
+    match vars.trap(condition) {
+        Some(action) if !vars.trapping && !action.is_empty() => {
+            let action = String::from(action);
+            run_action(&action, jobs, exit_attempted, vars)
+        }
+        _ => Flow::Next,
+    }
+}
+
+// Run the action for `EXIT`, if there is one, as the shell is about to exit
+// with `status`. Returns the status to exit with, which `exit` in the action
+// may change.
+pub fn run_exit(status: i32, jobs: &mut JobTable, vars: &mut Variables) -> i32 {
+    let Some(action) = vars.trap(Condition::Exit).map(String::from) else {
+        return status;
+    };
+    vars.set_trap(Condition::Exit, None);
+    vars.last_status = status;
+    match run_action(&action, jobs, &mut true, vars) {
+        Flow::Exit(code) => code,
+        _ => status,
+    }
+}
+
+// An action runs in the shell itself, like `eval`, and leaves `$?` as it
+// was, unless it exits.
+fn run_action(
+    action: &str,
+    jobs: &mut JobTable,
+    exit_attempted: &mut bool,
+    vars: &mut Variables,
+) -> Flow {
+    let status = vars.last_status;
+    vars.trapping = true;
+    let result = script::run_text("trap", action, jobs, exit_attempted, vars);
+    vars.trapping = false;
+
+    match result {
+        Ok(Flow::Exit(code)) => return Flow::Exit(code),
+        Ok(_) => {}
+        Err(err) => error::red_println(&err),
+    }
+    vars.last_status = status;
+    Flow::Next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::trap;
+    use crate::{
+        commands::{jobs::JobTable, run_list},
+        parser::parse,
+        signals::Condition,
+        string_vec,
+        variables::Variables,
+    };
+
+    fn run(line: &str, vars: &mut Variables) {
+        let list = parse(line).expect("input should parse");
+        run_list(&list, &mut JobTable::default(), &mut false, vars);
+    }
+
+    #[test]
+    fn trap_sets_and_shows_actions() {
+        let mut vars = Variables::default();
+        assert_eq!(
+            trap(&string_vec!["trap", "echo bye", "EXIT", "ERR"], &mut vars),
+            Ok(String::new())
+        );
+        assert_eq!(
+            trap(&string_vec!["trap", "", "sigusr2"], &mut vars),
+            Ok(String::new())
+        );
+        assert_eq!(
+            trap(&string_vec!["trap"], &mut vars),
+            Ok(String::from(
+                "trap -- 'echo bye' EXIT\ntrap -- '' SIGUSR2\ntrap -- 'echo bye' ERR\n"
+            ))
+        );
+        assert_eq!(
+            trap(&string_vec!["trap", "-p", "ERR", "DEBUG"], &mut vars),
+            Ok(String::from("trap -- 'echo bye' ERR\n"))
+        );
+
+        trap(&string_vec!["trap", "-", "USR2", "ERR"], &mut vars).unwrap();
+        trap(&string_vec!["trap", "0"], &mut vars).unwrap();
+        assert!(vars.traps().is_empty());
+
+        assert!(trap(&string_vec!["trap", "x", "NOSUCH"], &mut vars).is_err());
+        assert!(trap(&string_vec!["trap", "x", "KILL"], &mut vars).is_err());
+        assert_eq!(vars.trap(Condition::Exit), None);
+    }
+
+    #[test]
+    fn traps_run_between_commands() {
+        let mut vars = Variables::default();
+        run(
+            "trap 'out=${out}[$?]' ERR; trap 'out=${out}usr1' USR1; ((0)); out=$out:$?",
+            &mut vars,
+        );
+        assert_eq!(vars.get("out"), Some("[1]:1"));
+
+        unsafe { libc::raise(libc::SIGUSR1) };
+        run("out=$out:; out=${out}after", &mut vars);
+        assert_eq!(vars.get("out"), Some("[1]:1:usr1after"));
+
+        run(
+            "trap 'out=${out}.' DEBUG; ((1)) && ((1)); trap - DEBUG",
+            &mut vars,
+        );
+        assert_eq!(vars.get("out"), Some("[1]:1:usr1after..."));
+    }
+}
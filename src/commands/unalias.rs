@@ -0,0 +1,27 @@
+use crate::variables::Variables;
+
+pub const USAGE: &str = "Usage:\tunalias [-a] NAME ...";
+
+// Remove aliases, or, with `-a`, all of them.
+pub fn unalias(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    if input.get(1).is_some_and(|arg| arg == "-a") {
+        vars.clear_aliases();
+        return Ok(String::new());
+    }
+    if input.len() < 2 {
+        return Err(format!("Not enough arguments\n{USAGE}"));
+    }
+
+    let errors: Vec<String> = input
+        .iter()
+        .skip(1)
+        .filter(|name| !vars.unset_alias(name))
+        .map(|name| format!("{name}: not found"))
+        .collect();
+
+    if errors.is_empty() {
+        Ok(String::new())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
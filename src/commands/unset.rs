@@ -0,0 +1,63 @@
+use crate::{parser::is_name, variables::Variables};
+
+pub const USAGE: &str = "Usage:\tunset [-f|-v] NAME...";
+
+// Unset variables, or, with `-f`, functions. With neither flag, a name that
+// isn't a variable is taken to be a function.
+pub fn unset(input: &[String], vars: &mut Variables) -> Result<String, String> {
+    let mut functions = None;
+    let mut names = Vec::new();
+
+    for arg in input.iter().skip(1) {
+        match arg.as_str() {
+            "-f" if names.is_empty() => functions = Some(true),
+            "-v" if names.is_empty() => functions = Some(false),
+            _ => names.push(arg),
+        }
+    }
+
+    let mut errors = Vec::new();
+    for name in names {
+        if functions == Some(true) {
+            vars.unset_function(name);
+        } else if !is_name(name) {
+            errors.push(format!("`{name}': not a valid identifier"));
+        } else if functions.is_none() && vars.get(name).is_none() && vars.unset_function(name) {
+            continue;
+        } else if let Err(err) = vars.unset(name) {
+            errors.push(err);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(String::new())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unset;
+    use crate::{string_vec, variables::Variables};
+
+    #[test]
+    fn unset_removes_variables() {
+        let mut vars = Variables::default();
+        vars.set("A", "1").unwrap();
+        vars.set("B", "2").unwrap();
+        vars.set("FIXED", "3").unwrap();
+        vars.make_readonly("FIXED");
+
+        assert_eq!(
+            unset(
+                &string_vec!["unset", "A", "FIXED", "NEVER_SET", "B"],
+                &mut vars
+            ),
+            Err(String::from("FIXED: cannot unset: readonly variable"))
+        );
+        assert_eq!(vars.get("A"), None);
+        assert_eq!(vars.get("B"), None);
+        assert_eq!(vars.get("FIXED"), Some("3"));
+    }
+}
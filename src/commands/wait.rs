@@ -0,0 +1,180 @@
+use crate::{
+    commands::jobs::{self, Job, JobTable, State},
+    error,
+    signals::{self, Condition},
+    variables::Variables,
+};
+
+pub const USAGE: &str = "Usage:\twait [-n] [jobspec|pid ...]";
+
+// Wait for background jobs to finish: those given, by jobspec or by the PID of
+// one of their processes, or else all those running. With `-n`, wait for
+// just one of them. A job that stops is still waited for, until it's over. The status is that of the last job waited for, which may have
+// finished before `wait` was run, or 127 if it isn't one of the shell's jobs.
+// Once reported, a finished job's status is forgotten. A signal that the
+// shell traps ends the wait, with 128 plus its number as the status, and its
+// action runs afterwards. So does Ctrl+C, in the interactive shell.
+pub fn wait(input: &[String], jobs: &mut JobTable, vars: &mut Variables) -> Result<String, String> {
+    let mut any = false;
+    let mut specs = Vec::new();
+    for arg in &input[1..] {
+        match arg.as_str() {
+            "-n" => any = true,
+            _ if arg.starts_with('-') => {
+                return Err(format!("Invalid option -- '{arg}'\n{USAGE}"));
+            }
+            _ => specs.push(arg),
+        }
+    }
+
+    // Find the jobs before any of them can finish and leave the table. Each
+    // is known by its process group.
+    let given = !specs.is_empty();
+    let mut status = 0;
+    let mut targets = Vec::new();
+    for spec in specs {
+        match target(spec, jobs) {
+            Ok(pgid) => targets.push(pgid),
+            Err(err) => {
+                error::handle_error("wait", err);
+                status = 127;
+            }
+        }
+    }
+    if !given {
+        jobs.forget_finished();
+        targets = jobs
+            .iter()
+            .filter(|job| job.state == State::Running)
+            .map(|job| job.pgid)
+            .collect();
+        if any && targets.is_empty() {
+            status = 127;
+        }
+    }
+    if targets.is_empty() {
+        vars.last_status = status;
+        return Ok(String::new());
+    }
+
+    // The shell is woken when a child changes state, or a signal arrives,
+    // rather than checking over and over.
+    let children = signals::watch_children()?;
+
+    // Ctrl+C is otherwise ignored by the interactive shell.
+    let catch_int = vars.interactive && vars.trap(Condition::Signal(libc::SIGINT)).is_none();
+    if catch_int {
+        signals::catch(libc::SIGINT)?;
+    }
+
+    let interrupted = loop {
+        // Anything that happens from here on wakes the shell below.
+        signals::clear_child_notifications();
+        jobs::check_background_jobs(jobs, vars.interactive);
+
+        let waiting = targets
+            .iter()
+            .filter(|&&pgid| is_unfinished(jobs, pgid))
+            .count();
+        if waiting == 0 || (any && waiting < targets.len()) {
+            break None;
+        }
+        if let Some(sig) = interruption(vars, catch_int) {
+            break Some(sig);
+        }
+        let mut pollfd = libc::pollfd {
+            fd: children,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut pollfd, 1, -1) };
+    };
+
+    if catch_int {
+        signals::reset(libc::SIGINT)?;
+    }
+
+    vars.last_status = match interrupted {
+        Some(sig) => 128 + sig,
+        // The job that finished, with `-n`, or else the last one given. Waiting
+        // for every job leaves the status 0.
+        None => match (any, given) {
+            (true, _) => targets
+                .iter()
+                .find(|&&pgid| !is_unfinished(jobs, pgid))
+                .map_or(status, |&pgid| job_status(jobs, pgid)),
+            (false, true) => targets
+                .iter()
+                .fold(status, |_, &pgid| job_status(jobs, pgid)),
+            (false, false) => status,
+        },
+    };
+    if interrupted == Some(libc::SIGINT) {
+        println!();
+    }
+    Ok(String::new())
+}
+
+// The process group of the job given by a jobspec, or a PID, whether it's
+// still in the table or has finished.
+fn target(spec: &str, jobs: &JobTable) -> Result<i32, String> {
+    let job = if spec.starts_with('%') {
+        match jobs.resolve(spec) {
+            Ok(id) => jobs.get(id),
+            Err(err) => return jobs.find_finished(spec).ok_or(err),
+        }
+    } else {
+        let pid: i32 = spec
+            .parse()
+            .map_err(|_| format!("`{spec}': not a pid or valid job spec"))?;
+        jobs.find_pid(pid)
+    };
+    job.map(|job| job.pgid)
+        .or_else(|| jobs.find_finished(spec))
+        .ok_or_else(|| format!("{spec}: no such job"))
+}
+
+// Whether the job is still in the table, running or stopped.
+fn is_unfinished(jobs: &[Job], pgid: i32) -> bool {
+    jobs.iter().any(|job| job.pgid == pgid)
+}
+
+// The status of a job that has finished, which is then forgotten.
+fn job_status(jobs: &mut JobTable, pgid: i32) -> i32 {
+    jobs.take_finished(pgid).unwrap_or(127)
+}
+
+// A trapped signal is left pending for its action to run once `wait` is
+// over.
+fn interruption(vars: &Variables, catch_int: bool) -> Option<i32> {
+    let trapped = vars
+        .traps()
+        .iter()
+        .find_map(|(condition, action)| match condition {
+            Condition::Signal(sig) if !action.is_empty() && signals::is_pending(*sig) => Some(*sig),
+            _ => None,
+        });
+    trapped.or_else(|| (catch_int && signals::take_pending(libc::SIGINT)).then_some(libc::SIGINT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wait;
+    use crate::{commands::jobs::JobTable, string_vec, variables::Variables};
+
+    #[test]
+    fn wait_reports_unknown_jobs() {
+        let mut vars = Variables::default();
+        let mut jobs = JobTable::default();
+
+        let result = wait(&string_vec!["wait"], &mut jobs, &mut vars);
+        assert_eq!((result, vars.last_status), (Ok(String::new()), 0));
+
+        for args in [string_vec!["wait", "%1"], string_vec!["wait", "-n"]] {
+            vars.last_status = 0;
+            wait(&args, &mut jobs, &mut vars).unwrap();
+            assert_eq!(vars.last_status, 127, "{args:?}");
+        }
+        assert!(wait(&string_vec!["wait", "-x"], &mut jobs, &mut vars).is_err());
+    }
+}
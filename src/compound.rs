@@ -0,0 +1,454 @@
+// Running compound commands. Each command list inside one runs through
+// `commands::run_list`, so builtins, custom commands and programs all work
+// there as they do anywhere else. The status of a compound command is that of
+// the last command it ran, or 0 if it ran none.
+
+use regex::Regex;
+
+use crate::{
+    commands::{Flow, jobs::JobTable, run_list, test},
+    error, expand, fork,
+    parser::ast::{CaseArm, CompoundCommand, Conditional, List, Word},
+    variables::Variables,
+};
+
+// Run a compound command in the shell itself. For a subshell, the caller has
+// already forked, so its list is simply run.
+pub fn run(
+    body: &CompoundCommand,
+    jobs: &mut JobTable,
+    exit_attempted: &mut bool,
+    vars: &mut Variables,
+) -> Flow {
+    let mut shell = Shell {
+        jobs,
+        exit_attempted,
+        vars,
+    };
+
+    match body {
+        CompoundCommand::Subshell(list) | CompoundCommand::Group(list) => shell.list(list),
+        CompoundCommand::If {
+            branches,
+            otherwise,
+        } => shell.if_command(branches, otherwise.as_ref()),
+        CompoundCommand::Loop {
+            condition,
+            body,
+            until,
+        } => shell.loop_command(condition, body, *until),
+        CompoundCommand::For { name, words, body } => {
+            let values = match words {
+                Some(words) => expand::words(words, shell.vars),
+                None => Ok(shell.vars.positional.clone()),
+            };
+            match values {
+                Ok(values) => shell.for_command(name, &values, body),
+                Err(err) => shell.fail(err),
+            }
+        }
+        CompoundCommand::ArithmeticFor {
+            init,
+            condition,
+            step,
+            body,
+        } => shell.arithmetic_for(init, condition, step, body),
+        CompoundCommand::Case { word, arms } => match expand::value(word, shell.vars) {
+            Ok(value) => shell.case_command(&value, arms),
+            Err(err) => shell.fail(err),
+        },
+        CompoundCommand::Conditional(expression) => {
+            // As with `test`, an expression that can't be evaluated gives
+            // status 2.
+            shell.vars.last_status = match conditional(expression, shell.vars) {
+                Ok(value) => i32::from(!value),
+                Err(err) => {
+                    error::handle_error("0-shell", format!("[[: {err}"));
+                    2
+                }
+            };
+            Flow::Next
+        }
+    }
+}
+
+// The state that every command list is run with.
+struct Shell<'a> {
+    jobs: &'a mut JobTable,
+    exit_attempted: &'a mut bool,
+    vars: &'a mut Variables,
+}
+
+// What a loop does once its condition or body has run: carry on, or stop,
+// passing on whatever flow should continue outside the loop.
+enum Iteration {
+    Carry,
+    Stop(Flow),
+}
+
+impl Shell<'_> {
+    fn list(&mut self, list: &List) -> Flow {
+        run_list(list, self.jobs, self.exit_attempted, self.vars)
+    }
+
+    // Report an error from expanding part of a compound command, which ends
+    // it with status 1.
+    fn fail(&mut self, err: String) -> Flow {
+        self.vars.last_status = 1;
+        error::handle_error("0-shell", err);
+        Flow::Next
+    }
+
+    // The condition of `if`, `while` or `until`, where `-e` doesn't apply.
+    fn condition(&mut self, condition: &List) -> Flow {
+        self.vars.conditions += 1;
+        let flow = self.list(condition);
+        self.vars.conditions -= 1;
+        flow
+    }
+
+    fn if_command(&mut self, branches: &[(List, List)], otherwise: Option<&List>) -> Flow {
+        for (condition, body) in branches {
+            let flow = self.condition(condition);
+            if flow != Flow::Next || fork::was_interrupted() {
+                return flow;
+            }
+            if self.vars.last_status == 0 {
+                return self.list(body);
+            }
+        }
+
+        match otherwise {
+            Some(otherwise) => self.list(otherwise),
+            None => {
+                self.vars.last_status = 0;
+                Flow::Next
+            }
+        }
+    }
+
+    fn loop_command(&mut self, condition: &List, body: &List, until: bool) -> Flow {
+        self.repeat(
+            |shell| {
+                let flow = shell.condition(condition);
+                if flow != Flow::Next {
+                    return Ok(Some(flow));
+                }
+                Ok(((shell.vars.last_status == 0) != until).then_some(Flow::Next))
+            },
+            |_| Ok(()),
+            body,
+        )
+    }
+
+    fn for_command(&mut self, name: &str, values: &[String], body: &List) -> Flow {
+        let mut values = values.iter();
+        self.repeat(
+            |shell| {
+                let Some(value) = values.next() else {
+                    return Ok(None);
+                };
+                shell.vars.set(name, value)?;
+                Ok(Some(Flow::Next))
+            },
+            |_| Ok(()),
+            body,
+        )
+    }
+
+    // An empty condition counts as true.
+    fn arithmetic_for(&mut self, init: &Word, condition: &Word, step: &Word, body: &List) -> Flow {
+        if let Err(err) = expand::evaluate_optional(init, self.vars) {
+            return self.fail(err);
+        }
+        self.repeat(
+            |shell| {
+                let value = expand::evaluate_optional(condition, shell.vars)?;
+                Ok(value.is_none_or(|value| value != 0).then_some(Flow::Next))
+            },
+            |shell| expand::evaluate_optional(step, shell.vars).map(|_| ()),
+            body,
+        )
+    }
+
+    // Run a loop's body for as long as `next` says to, with `step` run after
+    // each iteration, including one cut short by `continue`. `next` returns
+    // `None` when the loop is over, or the flow from running the loop's
+    // condition, which `break` or `continue` may have changed.
+    fn repeat(
+        &mut self,
+        mut next: impl FnMut(&mut Self) -> Result<Option<Flow>, String>,
+        mut step: impl FnMut(&mut Self) -> Result<(), String>,
+        body: &List,
+    ) -> Flow {
+        self.vars.loops += 1;
+        let mut status = 0;
+
+        let flow = loop {
+            let flow = match next(self) {
+                Ok(Some(Flow::Next)) if !fork::was_interrupted() => self.list(body),
+                Ok(Some(flow)) => flow,
+                Ok(None) => break Flow::Next,
+                Err(err) => {
+                    status = 1;
+                    error::handle_error("0-shell", err);
+                    break Flow::Next;
+                }
+            };
+            status = self.vars.last_status;
+
+            if fork::was_interrupted() {
+                break Flow::Next;
+            }
+            if let Iteration::Stop(flow) = iteration(flow) {
+                break flow;
+            }
+            if let Err(err) = step(self) {
+                status = 1;
+                error::handle_error("0-shell", err);
+                break Flow::Next;
+            }
+        };
+
+        self.vars.loops -= 1;
+        self.vars.last_status = status;
+        flow
+    }
+
+    // Run the body of the first arm with a pattern that matches `value`.
+    fn case_command(&mut self, value: &str, arms: &[CaseArm]) -> Flow {
+        for arm in arms {
+            for pattern in &arm.patterns {
+                match expand::matches(pattern, value, self.vars) {
+                    Ok(true) => {
+                        // An arm may have no commands at all.
+                        self.vars.last_status = 0;
+                        return self.list(&arm.body);
+                    }
+                    Ok(false) => {}
+                    Err(err) => return self.fail(err),
+                }
+            }
+        }
+        self.vars.last_status = 0;
+        Flow::Next
+    }
+}
+
+// Evaluate the expression of `[[ ... ]]`. `&&` and `||` only evaluate their
+// right side if they need to.
+fn conditional(expression: &Conditional, vars: &mut Variables) -> Result<bool, String> {
+    Ok(match expression {
+        Conditional::Word(word) => !expand::value(word, vars)?.is_empty(),
+        Conditional::Unary { op, operand } => test::unary(op, &expand::value(operand, vars)?)?,
+        Conditional::Binary { left, op, right } => {
+            let left = expand::value(left, vars)?;
+            match op.as_str() {
+                "==" | "=" => expand::matches(right, &left, vars)?,
+                "!=" => !expand::matches(right, &left, vars)?,
+                "=~" => {
+                    let pattern = expand::regex(right, vars)?;
+                    let regex = Regex::new(&pattern)
+                        .map_err(|_| format!("{pattern}: Invalid regular expression"))?;
+                    regex.is_match(&left)
+                }
+                _ => test::binary(&left, op, &expand::value(right, vars)?)?,
+            }
+        }
+        Conditional::Not(expression) => !conditional(expression, vars)?,
+        Conditional::And(left, right) => conditional(left, vars)? && conditional(right, vars)?,
+        Conditional::Or(left, right) => conditional(left, vars)? || conditional(right, vars)?,
+    })
+}
+
+// How a loop carries on after `flow`. `break` and `continue` with a count of
+// more than one pass on what's left of the count to the enclosing loop.
+fn iteration(flow: Flow) -> Iteration {
+    match flow {
+        Flow::Next | Flow::Continue(1) => Iteration::Carry,
+        Flow::Break(1) => Iteration::Stop(Flow::Next),
+        Flow::Break(n) => Iteration::Stop(Flow::Break(n - 1)),
+        Flow::Continue(n) => Iteration::Stop(Flow::Continue(n - 1)),
+        Flow::Exit(code) => Iteration::Stop(Flow::Exit(code)),
+        Flow::Return(status) => Iteration::Stop(Flow::Return(status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        commands::{jobs::JobTable, run_list},
+        parser::parse,
+        test_helpers::TempStore,
+        variables::Variables,
+    };
+
+    // Run a line in the shell itself, and return the value of `$out`. Only
+    // assignments and `let` are used, so nothing is forked.
+    fn run(line: &str) -> String {
+        run_with(line, Variables::default())
+    }
+
+    fn run_with(line: &str, mut vars: Variables) -> String {
+        let list = parse(line).expect("input should parse");
+        run_list(&list, &mut JobTable::default(), &mut false, &mut vars);
+        vars.get("out").unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn compound_commands_run() {
+        assert_eq!(run("if ((0)); then out=a; elif ((1)); then out=b; fi"), "b");
+        assert_eq!(run("if ((0)); then out=a; else out=c; fi"), "c");
+        assert_eq!(run("for i in 1 {2..3}; do out=$out$i; done"), "123");
+        let mut vars = Variables::default();
+        vars.positional = vec![String::from("x"), String::from("y")];
+        assert_eq!(run_with("for i; do out=$out$i; done", vars), "xy");
+        assert_eq!(run("for ((i = 0; i < 3; i++)); do out=$out$i; done"), "012");
+        assert_eq!(
+            run("i=0; while ((i < 2)); do ((i++)); out=$out$i; done"),
+            "12"
+        );
+        assert_eq!(
+            run("i=0; until ((i == 2)); do ((i++)); out=$out$i; done"),
+            "12"
+        );
+        assert_eq!(
+            run(
+                "for x in a.rs b.txt c; do case $x in *.rs) out=${out}r;; *.txt | c) out=${out}t;; esac; done"
+            ),
+            "rtt"
+        );
+        assert_eq!(
+            run(r#"case '*' in \*) out=star;; *) out=any;; esac"#),
+            "star"
+        );
+    }
+
+    #[test]
+    fn conditional_expressions() {
+        assert_eq!(run("x=abc; [[ $x == a* && ! -z $x ]] && out=yes"), "yes");
+        assert_eq!(run("[[ 'a*' == \"a*\" ]] && out=quoted"), "quoted");
+        assert_eq!(run("[[ abc == 'a*' ]] || out=literal"), "literal");
+        assert_eq!(
+            run("[[ ab =~ ^(a|b)+$ && (x < y || -z '') ]] && out=regex"),
+            "regex"
+        );
+        assert_eq!(
+            run("[[ a.c =~ 'a.c' && ! abc =~ 'a.c' ]] && out=dot"),
+            "dot"
+        );
+        assert_eq!(run("[[ 10 -gt 9 ]]; out=$?"), "0");
+        assert_eq!(run("[[ a -gt 9 ]]; out=$?"), "2");
+        assert_eq!(run("x=; [[ $x ]]; out=$?"), "1");
+    }
+
+    #[test]
+    fn break_and_continue() {
+        assert_eq!(
+            run(
+                "for i in 1 2 3 4; do ((i == 2)) && continue; ((i == 4)) && break; out=$out$i; done"
+            ),
+            "13"
+        );
+        assert_eq!(
+            run(
+                "for i in 1 2; do for j in 1 2; do ((j == 2)) && continue 2; out=$out$i$j; done; out=${out}x; done"
+            ),
+            "1121"
+        );
+        assert_eq!(
+            run("while ((1)); do while ((1)); do break 2; done; out=no; done; out=${out}done"),
+            "done"
+        );
+    }
+
+    #[test]
+    fn errexit_ignores_tested_failures() {
+        let mut vars = Variables::default();
+        vars.options.errexit = true;
+        assert_eq!(
+            run_with(
+                "((0)) || out=a; if ((0)); then :; fi; while ((0)); do :; done; f() { ((0)); out=${out}b; }; f && out=${out}c; ((0)); out=${out}d",
+                vars
+            ),
+            "abc"
+        );
+    }
+
+    #[test]
+    fn expansion_errors_end_a_script() {
+        assert_eq!(run("out=a; out=${u:?}; out=b"), "a");
+        assert_eq!(run("f() { out=${u:?}; }; out=a; f; out=b"), "a");
+    }
+
+    #[test]
+    fn functions_take_arguments() {
+        assert_eq!(
+            run("f() { out=$#:$1:$2; }; f a 'b c'; out=$out:$#"),
+            "2:a:b c:0"
+        );
+        assert_eq!(
+            run("f() { local out=inner x=1; out=changed; }; out=outer; f; out=$out$x"),
+            "outer"
+        );
+        assert_eq!(
+            run(
+                "f() { for i in 1 2 3; do ((i == 2)) && return 4; out=$out$i; done; }; f; out=$out$?"
+            ),
+            "14"
+        );
+        assert_eq!(
+            run("for i in 1 2; do f() { break; }; f; out=$out$i; done"),
+            "12",
+            "`break` shouldn't reach a loop outside the function"
+        );
+        assert_eq!(
+            run("f() { out=$out$1; (($1 < 3)) && f $(($1 + 1)); }; f 1"),
+            "123"
+        );
+    }
+
+    #[test]
+    fn subshells_keep_their_changes_to_themselves() {
+        assert_eq!(run("out=a; (out=b; x=1); out=$out$x"), "a");
+        assert_eq!(
+            run("(cd / && x=1); out=$x:$PWD"),
+            ":",
+            "the subshell's `cd` shouldn't reach the shell"
+        );
+        assert_eq!(run("out=a; { out=b; x=1; }; out=$out$x"), "b1");
+    }
+
+    #[test]
+    fn brace_groups_share_their_redirections() {
+        let temp_store = TempStore::new(1);
+        fs::write(&temp_store.store[0], "hello").expect("failed to create file");
+        let file = fs::canonicalize(&temp_store.store[0]).expect("failed to find file");
+        let line = format!(
+            "{{ x=1; out=$(/bin/cat); out=$out$(/bin/cat); }} < {}; out=$out$x",
+            file.display()
+        );
+        assert_eq!(
+            run(&line),
+            "hello1",
+            "the group should read the file once, in the shell itself"
+        );
+    }
+
+    #[test]
+    fn assignments_see_earlier_ones() {
+        assert_eq!(run("a=1; a=$((a+1)) b=$a; out=$a$b"), "22");
+        assert_eq!(run("f() { out=$b$a; }; a=x; a=1 b=$a f; out=$out$a"), "11x");
+    }
+
+    #[test]
+    fn assignments_keep_the_substitution_status() {
+        assert_eq!(run("x=$(exit 3); out=$?"), "3");
+        assert_eq!(run("$(exit 5); out=$?"), "5");
+        assert_eq!(run("if x=$(exit 1); then out=yes; else out=no; fi"), "no");
+        assert_eq!(run("((0)); x=1; out=$?"), "0");
+        assert_eq!(run("((0)); x=$?; out=$x$?"), "10");
+    }
+}
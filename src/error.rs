@@ -13,13 +13,14 @@ pub fn red_println(text: &str) {
     eprintln!("{ERROR_COLOR}{BOLD}{text}{RESET_FG}", text = text);
 }
 
-// Emit a minimal, async-signal-safe error message after execvp failure. POSIX
-// guarantees write(2) is async-signal-safe, so calling `libc::write` in a
-// signal handler or post-fork child is allowed. `println!` goes through Rust’s
-// buffered stdout, which takes locks and can allocate, so it's not
-// async-signal-safe.
-pub fn print_exec_failure(cmd_bytes: &[u8]) {
-    let prefix = b"0-shell: command not found: ";
+// Emit a minimal, async-signal-safe error message after execve failure, e.g.
+// `0-shell: command not found: foo`. POSIX guarantees write(2) is
+// async-signal-safe, so calling `libc::write` in a signal handler or post-fork
+// child is allowed. `println!` goes through Rust’s buffered stdout, which takes
+// locks and can allocate, so it's not async-signal-safe.
+pub fn print_exec_failure(reason: &[u8], cmd_bytes: &[u8]) {
+    let prefix = b"0-shell: ";
+    let separator = b": ";
     unsafe {
         let _ = libc::write(
             libc::STDERR_FILENO,
@@ -36,6 +37,16 @@ pub fn print_exec_failure(cmd_bytes: &[u8]) {
             prefix.as_ptr() as *const libc::c_void,
             prefix.len(),
         );
+        let _ = libc::write(
+            libc::STDERR_FILENO,
+            reason.as_ptr() as *const libc::c_void,
+            reason.len(),
+        );
+        let _ = libc::write(
+            libc::STDERR_FILENO,
+            separator.as_ptr() as *const libc::c_void,
+            separator.len(),
+        );
         let _ = libc::write(
             libc::STDERR_FILENO,
             cmd_bytes.as_ptr() as *const libc::c_void,
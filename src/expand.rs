@@ -0,0 +1,739 @@
+mod braces;
+mod glob;
+mod pattern;
+mod substitution;
+mod tilde;
+
+use std::rc::Rc;
+
+use crate::{
+    arithmetic,
+    options::Options,
+    parser::{
+        ast::{
+            Command, CompoundCommand, Function, ParamOp, Redirect, RedirectOp, Segment,
+            SimpleCommand, Word,
+        },
+        is_name,
+    },
+    redirect::{self, Redirection},
+    variables::Variables,
+};
+
+// Characters on which the results of unquoted expansions are split into
+// separate arguments, unless `$IFS` gives others.
+const FIELD_SEPARATORS: &str = " \t\n";
+
+// A simple command after expansion, ready to run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedCommand {
+    pub assignments: Vec<(String, String)>, // `NAME=value` before the command.
+    pub args: Vec<String>,
+    pub redirections: Vec<Redirection>,
+}
+
+// A stage of a pipeline, ready to run. Only the redirections of a compound
+// command are expanded beforehand; the commands in it are expanded as they
+// run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpandedStage {
+    Simple(ExpandedCommand),
+    Compound {
+        body: CompoundCommand,
+        redirections: Vec<Redirection>,
+    },
+    Definition(Rc<Function>),
+}
+
+pub fn stage(stage: &Command, vars: &mut Variables) -> Result<ExpandedStage, String> {
+    match stage {
+        Command::Simple(simple) => command(simple, vars).map(ExpandedStage::Simple),
+        Command::Compound { body, redirects } => Ok(ExpandedStage::Compound {
+            body: body.clone(),
+            redirections: redirections(redirects, vars)?,
+        }),
+        Command::Function(function) => Ok(ExpandedStage::Definition(Rc::clone(function))),
+    }
+}
+
+// Assignments are expanded in order, each one seeing those before it, as in
+// `a=1 b=$a`, but the words after them see the variables as they were.
+pub fn command(command: &SimpleCommand, vars: &mut Variables) -> Result<ExpandedCommand, String> {
+    let mut assignments = Vec::new();
+    let mut saved = Vec::new();
+    let mut expanded = Ok(());
+    for assignment in &command.assignments {
+        let value = tilde::assignment(&assignment.value, vars);
+        let value = match word(&value, vars) {
+            Ok(value) => value,
+            Err(err) => {
+                expanded = Err(err);
+                break;
+            }
+        };
+        // A readonly variable is reported when it's actually assigned.
+        let assigned = [(assignment.name.clone(), value)];
+        if let Ok(previous) = vars.set_temporarily(&assigned) {
+            saved.extend(previous);
+        }
+        assignments.extend(assigned);
+    }
+    vars.restore(saved);
+    expanded?;
+
+    Ok(ExpandedCommand {
+        assignments,
+        args: words(&command.words, vars)?,
+        redirections: redirections(&command.redirects, vars)?,
+    })
+}
+
+// Expand words into the arguments they stand for, as for the words of a
+// command or of `for name in words`. Brace expansion comes first, turning
+// each word into any number.
+pub fn words(words: &[Word], vars: &mut Variables) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for word in words.iter().flat_map(braces::expand) {
+        args.extend(fields(&tilde::expand(&word, vars), vars)?);
+    }
+    Ok(args)
+}
+
+// Expand a word that isn't split into fields or matched against file names,
+// such as the word of a `case` command.
+pub fn value(unexpanded: &Word, vars: &mut Variables) -> Result<String, String> {
+    word(&tilde::expand(unexpanded, vars), vars)
+}
+
+// Whether `text` matches a pattern, as in a `case` command.
+pub fn matches(pattern: &Word, text: &str, vars: &mut Variables) -> Result<bool, String> {
+    let pattern = pattern_text(&tilde::expand(pattern, vars), vars)?;
+    Ok(pattern::matches(&pattern, text))
+}
+
+// Expand a word into a regular expression, as for `=~` in `[[ ... ]]`, in
+// which, as in a pattern, quoted parts match themselves.
+pub fn regex(word: &Word, vars: &mut Variables) -> Result<String, String> {
+    special_text(&tilde::expand(word, vars), vars, regex::escape)
+}
+
+fn redirections(redirects: &[Redirect], vars: &mut Variables) -> Result<Vec<Redirection>, String> {
+    let mut redirections = Vec::new();
+    for redirect in redirects {
+        let target = word(&redirect.target, vars)?;
+        // An empty here-document or here-string is still input: nothing, or
+        // a newline.
+        let is_input = matches!(redirect.op, RedirectOp::HereDoc | RedirectOp::HereString);
+        if target.is_empty() && !is_input {
+            return Err(String::from("0-shell: Ambiguous redirect"));
+        }
+        redirections.extend(redirect::resolve(redirect.fd, redirect.op, target)?);
+    }
+    Ok(redirections)
+}
+
+// What a parameter expands to: either a value, or, for operators such as
+// `${NAME:-word}`, another word, which is expanded in its place.
+enum Expansion<'a> {
+    Value(String),
+    Word(&'a Word),
+}
+
+fn parameter<'a>(
+    name: &str,
+    op: &'a ParamOp,
+    vars: &mut Variables,
+) -> Result<Expansion<'a>, String> {
+    let value = vars.parameter(name);
+    // Whether the parameter counts as set for an operator with or without a
+    // colon.
+    let is_set = |null: bool| value.as_ref().is_some_and(|v| !(null && v.is_empty()));
+
+    let expansion = match op {
+        ParamOp::Value => Expansion::Value(value.unwrap_or_default()),
+        ParamOp::Length => {
+            Expansion::Value(value.map_or(0, |value| value.chars().count()).to_string())
+        }
+        ParamOp::Default { word, null } => {
+            if is_set(*null) {
+                Expansion::Value(value.unwrap_or_default())
+            } else {
+                Expansion::Word(word)
+            }
+        }
+        ParamOp::Alternative { word, null } => {
+            if is_set(*null) {
+                Expansion::Word(word)
+            } else {
+                Expansion::Value(String::new())
+            }
+        }
+        ParamOp::Assign {
+            word: default,
+            null,
+        } => {
+            if is_set(*null) {
+                Expansion::Value(value.unwrap_or_default())
+            } else {
+                if !is_name(name) {
+                    return Err(format!("0-shell: ${name}: cannot assign in this way"));
+                }
+                let default = word(default, vars)?;
+                vars.set(name, &default)?;
+                Expansion::Value(default)
+            }
+        }
+        ParamOp::Error { message, null } => {
+            if !is_set(*null) {
+                let message = match word(message, vars)? {
+                    message if message.is_empty() => String::from("parameter null or not set"),
+                    message => message,
+                };
+                return Err(format!("0-shell: {name}: {message}"));
+            }
+            Expansion::Value(value.unwrap_or_default())
+        }
+        ParamOp::RemovePrefix {
+            pattern: p,
+            longest,
+        } => Expansion::Value(String::from(pattern::remove_prefix(
+            &value.unwrap_or_default(),
+            &pattern_text(p, vars)?,
+            *longest,
+        ))),
+        ParamOp::RemoveSuffix {
+            pattern: p,
+            longest,
+        } => Expansion::Value(String::from(pattern::remove_suffix(
+            &value.unwrap_or_default(),
+            &pattern_text(p, vars)?,
+            *longest,
+        ))),
+        ParamOp::Replace {
+            pattern: p,
+            replacement,
+            mode,
+        } => Expansion::Value(pattern::replace(
+            &value.unwrap_or_default(),
+            &pattern_text(p, vars)?,
+            &word(replacement, vars)?,
+            *mode,
+        )),
+    };
+
+    Ok(expansion)
+}
+
+fn parameter_value(name: &str, op: &ParamOp, vars: &mut Variables) -> Result<String, String> {
+    match parameter(name, op, vars)? {
+        Expansion::Value(value) => Ok(value),
+        Expansion::Word(w) => word(w, vars),
+    }
+}
+
+// Expand a word into a single string, as for a redirection target or the body
+// of a here-document.
+pub fn word(word: &Word, vars: &mut Variables) -> Result<String, String> {
+    let mut text = String::new();
+    for segment in &word.0 {
+        match segment {
+            Segment::Literal(s) | Segment::Quoted(s) => text.push_str(s),
+            Segment::Parameter { name, op, .. } => text.push_str(&parameter_value(name, op, vars)?),
+            Segment::Command { list, .. } => text.push_str(&substitution::output(list, vars)?),
+            Segment::Arithmetic { expression, .. } => text.push_str(&arithmetic(expression, vars)?),
+        }
+    }
+    Ok(text)
+}
+
+// The value of `$((...))`. Parameters and commands in the expression are
+// expanded first.
+fn arithmetic(expression: &Word, vars: &mut Variables) -> Result<String, String> {
+    evaluate(expression, vars).map(|value| value.to_string())
+}
+
+// The value of an arithmetic expression, once expanded, or `None` if it's
+// empty, as the parts of `for ((...))` may be.
+pub fn evaluate_optional(expression: &Word, vars: &mut Variables) -> Result<Option<i64>, String> {
+    let text = word(expression, vars)?;
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    match arithmetic::evaluate(&text, vars) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => Err(format!("0-shell: {err}")),
+    }
+}
+
+fn evaluate(expression: &Word, vars: &mut Variables) -> Result<i64, String> {
+    let text = word(expression, vars)?;
+    arithmetic::evaluate(&text, vars).map_err(|err| format!("0-shell: {err}"))
+}
+
+// Expand a word into a pattern, in which only the unquoted parts keep their
+// special meaning.
+fn pattern_text(word: &Word, vars: &mut Variables) -> Result<String, String> {
+    special_text(word, vars, pattern::escape)
+}
+
+// Expand a word in which unquoted characters are special, using `escape` to
+// make quoted text match itself.
+fn special_text(
+    word: &Word,
+    vars: &mut Variables,
+    escape: fn(&str) -> String,
+) -> Result<String, String> {
+    let mut text = String::new();
+    for segment in &word.0 {
+        match segment {
+            Segment::Literal(s) => text.push_str(s),
+            Segment::Quoted(s) => text.push_str(&escape(s)),
+            Segment::Parameter {
+                name,
+                op,
+                quoted: false,
+            } => text.push_str(&parameter_value(name, op, vars)?),
+            Segment::Parameter { name, op, .. } => {
+                text.push_str(&escape(&parameter_value(name, op, vars)?))
+            }
+            Segment::Command {
+                list,
+                quoted: false,
+            } => text.push_str(&substitution::output(list, vars)?),
+            Segment::Command { list, .. } => {
+                text.push_str(&escape(&substitution::output(list, vars)?))
+            }
+            // A number needs no escaping.
+            Segment::Arithmetic { expression, .. } => text.push_str(&arithmetic(expression, vars)?),
+        }
+    }
+    Ok(text)
+}
+
+// Expand a word into the arguments it stands for. The values of unquoted
+// parameters are split on the characters in `$IFS`, whitespace by default, so
+// `$EMPTY` stands for no argument at all, while `"$EMPTY"` is one empty
+// argument. Whitespace in `$IFS` runs together, but each other separator
+// ends a field, even an empty one, as in `a::b`. An empty `$IFS` splits
+// nothing. Then any argument with unquoted wildcards is replaced by the names
+// of the files it matches.
+pub fn fields(word: &Word, vars: &mut Variables) -> Result<Vec<String>, String> {
+    let mut fields = Fields::default();
+    fields.push_word(word, vars, false)?;
+    fields.finish(&vars.options)
+}
+
+// The characters that split fields: those in `$IFS`, if it's set.
+fn separators(vars: &Variables) -> String {
+    String::from(vars.get("IFS").unwrap_or(FIELD_SEPARATORS))
+}
+
+#[derive(Default)]
+struct Fields {
+    fields: Vec<(String, Option<String>)>, // Each with its pattern, if it has one.
+    current: String,
+    pattern: String,  // `current` with its quoted parts escaped.
+    has_field: bool,  // Whether `current` is an argument, even if empty.
+    is_pattern: bool, // Whether `current` has unquoted wildcards.
+    spaced: bool,     // Whether whitespace has just ended a field.
+}
+
+impl Fields {
+    // A word `nested` within `${...}` is split like the value it stands for.
+    fn push_word(&mut self, word: &Word, vars: &mut Variables, nested: bool) -> Result<(), String> {
+        for segment in &word.0 {
+            match segment {
+                // Only a word within `${...}` can have unquoted whitespace.
+                Segment::Literal(text) if nested => self.push_split(text, &separators(vars)),
+                Segment::Literal(text) => self.push_split(text, FIELD_SEPARATORS),
+                Segment::Quoted(text) => self.push_quoted(text),
+                // `"$@"` is each positional parameter as a separate argument,
+                // or no argument at all if there are none.
+                Segment::Parameter {
+                    name,
+                    op: ParamOp::Value,
+                    quoted: true,
+                } if name == "@" => {
+                    for (i, arg) in vars.positional.iter().enumerate() {
+                        if i > 0 {
+                            self.end_field();
+                        }
+                        self.push_quoted(arg);
+                    }
+                }
+                Segment::Parameter {
+                    name,
+                    op,
+                    quoted: true,
+                } => self.push_quoted(&parameter_value(name, op, vars)?),
+                Segment::Parameter {
+                    name,
+                    op,
+                    quoted: false,
+                } => match parameter(name, op, vars)? {
+                    Expansion::Value(value) => self.push_split(&value, &separators(vars)),
+                    // The word keeps its own quoting, so `${X:-"a b"}` is one
+                    // argument.
+                    Expansion::Word(word) => self.push_word(word, vars, true)?,
+                },
+                Segment::Command { list, quoted: true } => {
+                    self.push_quoted(&substitution::output(list, vars)?)
+                }
+                Segment::Command {
+                    list,
+                    quoted: false,
+                } => {
+                    let output = substitution::output(list, vars)?;
+                    self.push_split(&output, &separators(vars))
+                }
+                Segment::Arithmetic {
+                    expression,
+                    quoted: true,
+                } => self.push_quoted(&arithmetic(expression, vars)?),
+                Segment::Arithmetic {
+                    expression,
+                    quoted: false,
+                } => self.push_split(&arithmetic(expression, vars)?, &separators(vars)),
+            }
+        }
+        Ok(())
+    }
+
+    fn push_quoted(&mut self, text: &str) {
+        self.current.push_str(text);
+        self.pattern.push_str(&pattern::escape(text));
+        self.has_field = true;
+        self.spaced = false;
+    }
+
+    fn push_unquoted(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.current.push_str(text);
+        self.pattern.push_str(text);
+        self.is_pattern |= glob::has_wildcards(text);
+        self.has_field = true;
+        self.spaced = false;
+    }
+
+    fn push_split(&mut self, value: &str, separators: &str) {
+        let mut text = String::new();
+        for c in value.chars() {
+            if !separators.contains(c) {
+                text.push(c);
+                continue;
+            }
+            self.push_unquoted(&std::mem::take(&mut text));
+            if FIELD_SEPARATORS.contains(c) {
+                if self.has_field {
+                    self.end_field();
+                    self.spaced = true;
+                }
+            } else if self.has_field || !self.spaced {
+                // Along with any whitespace before it.
+                self.end_field();
+            } else {
+                self.spaced = false;
+            }
+        }
+        self.push_unquoted(&text);
+    }
+
+    fn end_field(&mut self) {
+        let pattern = std::mem::take(&mut self.pattern);
+        let pattern = self.is_pattern.then_some(pattern);
+        self.fields
+            .push((std::mem::take(&mut self.current), pattern));
+        self.has_field = false;
+        self.is_pattern = false;
+        self.spaced = false;
+    }
+
+    // A pattern that matches nothing is left as it is, unless `nullglob` or
+    // `failglob` is set.
+    fn finish(mut self, options: &Options) -> Result<Vec<String>, String> {
+        if self.has_field {
+            self.end_field();
+        }
+
+        let mut args = Vec::new();
+        for (text, pattern) in self.fields {
+            let Some(pattern) = pattern else {
+                args.push(text);
+                continue;
+            };
+            let paths = glob::expand(&pattern, options);
+            if !paths.is_empty() {
+                args.extend(paths);
+            } else if options.failglob {
+                return Err(format!("0-shell: no match: {text}"));
+            } else if !options.nullglob {
+                args.push(text);
+            }
+        }
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExpandedCommand, command, fields};
+    use crate::{
+        parser::parse,
+        redirect::Action,
+        test_helpers::{TempStore, simple_command},
+        variables::Variables,
+    };
+
+    fn expand_command(input: &str, vars: &mut Variables) -> Result<ExpandedCommand, String> {
+        let list = parse(input).expect("input should parse");
+        command(simple_command(&list, 0, 0), vars)
+    }
+
+    fn expand(input: &str, vars: &mut Variables) -> Vec<String> {
+        expand_command(input, vars)
+            .expect("expansion should succeed")
+            .args
+    }
+
+    #[test]
+    fn expand_removes_quotes_and_escapes() {
+        let vars = &mut Variables::default();
+        assert_eq!(
+            expand(r#"cat "my file" 'a b' a\ b "a"b"#, vars),
+            vec!["cat", "my file", "a b", "a b", "ab"]
+        );
+        assert_eq!(expand(r#"echo "" ''"#, vars), vec!["echo", "", ""]);
+    }
+
+    #[test]
+    fn expand_parameters() {
+        let vars = &mut Variables::default();
+        vars.set("USER", "testuser").unwrap();
+        vars.set("SPACES", " a  b ").unwrap();
+
+        assert_eq!(
+            expand("echo $USER x${USER}y", vars),
+            vec!["echo", "testuser", "xtestusery"]
+        );
+        assert_eq!(
+            expand("echo '$USER' \\$USER", vars),
+            vec!["echo", "$USER", "$USER"],
+            "quoted or escaped parameters should be left alone"
+        );
+        assert_eq!(
+            expand("echo $UNSET \"$UNSET\"", vars),
+            vec!["echo", ""],
+            "an unquoted empty parameter should disappear"
+        );
+        assert_eq!(
+            expand("echo x$SPACES\"$SPACES\"", vars),
+            vec!["echo", "x", "a", "b", " a  b "],
+            "only unquoted parameters should be split into fields"
+        );
+    }
+
+    #[test]
+    fn expand_splits_on_ifs() {
+        let vars = &mut Variables::default();
+        vars.set("LIST", "a:b::c:").unwrap();
+        vars.set("SPACED", " a : b ").unwrap();
+
+        vars.set("IFS", ":").unwrap();
+        assert_eq!(
+            expand("echo $LIST a:b ${UNSET:-x:y}", vars),
+            vec!["echo", "a", "b", "", "c", "a:b", "x", "y"],
+            "each `:` should end a field, but only in expansions"
+        );
+        vars.set("IFS", " :").unwrap();
+        assert_eq!(
+            expand("echo $SPACED", vars),
+            vec!["echo", "a", "b"],
+            "whitespace around a separator should run into it"
+        );
+        vars.set("IFS", "").unwrap();
+        assert_eq!(
+            expand("echo $SPACED", vars),
+            vec!["echo", " a : b "],
+            "an empty IFS should split nothing"
+        );
+        vars.unset("IFS").unwrap();
+        assert_eq!(expand("echo $SPACED", vars), vec!["echo", "a", ":", "b"]);
+    }
+
+    #[test]
+    fn expand_special_parameters() {
+        let vars = &mut Variables::default();
+        vars.positional = vec![String::from("a b"), String::from("c")];
+        vars.last_status = 2;
+
+        assert_eq!(
+            expand("echo $? ${?}x $# $1 ${2}", vars),
+            vec!["echo", "2", "2x", "2", "a", "b", "c"]
+        );
+        assert_eq!(
+            expand(r#"echo "$@" "x$@y" $@ "$*""#, vars),
+            vec!["echo", "a b", "c", "xa b", "cy", "a", "b", "c", "a b c"],
+            "\"$@\" should keep each positional parameter as one argument"
+        );
+
+        vars.positional.clear();
+        assert_eq!(
+            expand(r#"echo "$@" ${#} ${#:-x}"#, vars),
+            vec!["echo", "0", "0"],
+            "\"$@\" with no positional parameters should vanish"
+        );
+    }
+
+    #[test]
+    fn expand_parameter_operators() {
+        let vars = &mut Variables::default();
+        vars.set("FILE", "/usr/lib/lib.tar.gz").unwrap();
+        vars.set("EMPTY", "").unwrap();
+
+        assert_eq!(
+            expand(
+                "echo ${#FILE} ${FILE##*/} ${FILE%%.*} ${FILE#/*/} ${FILE%.*}",
+                vars
+            ),
+            vec![
+                "echo",
+                "19",
+                "lib.tar.gz",
+                "/usr/lib/lib",
+                "lib/lib.tar.gz",
+                "/usr/lib/lib.tar"
+            ]
+        );
+        assert_eq!(
+            expand("echo ${FILE/lib/LIB} ${FILE//lib/LIB}", vars),
+            vec!["echo", "/usr/LIB/lib.tar.gz", "/usr/LIB/LIB.tar.gz"]
+        );
+        assert_eq!(
+            expand(r"echo ${FILE#'/*/'} ${FILE/\/usr}", vars),
+            vec!["echo", "/usr/lib/lib.tar.gz", "/lib/lib.tar.gz"],
+            "quoted pattern characters should match only themselves"
+        );
+
+        assert_eq!(
+            expand(
+                r#"echo ${EMPTY:-a b} ${EMPTY-x} "${UNSET:-a  b}" ${UNSET:-"c  d"}"#,
+                vars
+            ),
+            vec!["echo", "a", "b", "a  b", "c  d"],
+            "defaults should be split only where they are unquoted"
+        );
+        assert_eq!(
+            expand("echo ${EMPTY:+set} ${EMPTY+set} '${EMPTY+set}'", vars),
+            vec!["echo", "set", "${EMPTY+set}"]
+        );
+
+        assert_eq!(
+            expand("echo ${ASSIGNED:=new} $ASSIGNED", vars),
+            vec!["echo", "new", "new"]
+        );
+        assert_eq!(vars.get("ASSIGNED"), Some("new"));
+
+        assert_eq!(
+            expand_command("echo ${UNSET:?custom message} ${EMPTY:?}", vars),
+            Err(String::from("0-shell: UNSET: custom message"))
+        );
+        assert_eq!(
+            expand_command("echo ${EMPTY:?}", vars),
+            Err(String::from("0-shell: EMPTY: parameter null or not set"))
+        );
+    }
+
+    #[test]
+    fn expand_command_substitution() {
+        let vars = &mut Variables::default();
+        assert_eq!(
+            expand(
+                r#"echo $(/usr/bin/printf 'a  b\n\n') "$(/usr/bin/printf 'a  b\n')" x`/bin/echo y`"#,
+                vars
+            ),
+            vec!["echo", "a", "b", "a  b", "xy"],
+            "trailing newlines should be removed, and unquoted output split"
+        );
+
+        expand("echo $(/usr/bin/printf x; exit 3)", vars);
+        assert_eq!(
+            vars.last_status, 3,
+            "the status should be that of the substitution"
+        );
+    }
+
+    #[test]
+    fn expand_globs() {
+        let temp = TempStore::new(1);
+        let root = &temp.store[0];
+        std::fs::create_dir(root).unwrap();
+        std::fs::write(format!("{root}/a*b"), "").unwrap();
+        std::fs::write(format!("{root}/axb"), "").unwrap();
+
+        let vars = &mut Variables::default();
+        vars.set("ROOT", root).unwrap();
+        assert_eq!(
+            expand(r#"ls $ROOT/a*b "$ROOT/a*b" $ROOT/a\*b $ROOT/*.none"#, vars),
+            vec![
+                String::from("ls"),
+                format!("{root}/a*b"),
+                format!("{root}/axb"),
+                format!("{root}/a*b"),
+                format!("{root}/a*b"),
+                format!("{root}/*.none"),
+            ],
+            "only unquoted wildcards should match, and no match should leave the pattern"
+        );
+
+        vars.options.nullglob = true;
+        assert_eq!(expand("ls $ROOT/*.none", vars), vec!["ls"]);
+        vars.options.failglob = true;
+        assert_eq!(
+            expand_command("ls $ROOT/*.none", vars).map(|command| command.args),
+            Err(format!("0-shell: no match: {root}/*.none"))
+        );
+    }
+
+    #[test]
+    fn expand_assignments() {
+        let vars = &mut Variables::default();
+        vars.set("SPACES", "a  b").unwrap();
+
+        let expanded = expand_command("A=$SPACES B= cmd C=1", vars).unwrap();
+        assert_eq!(
+            expanded.assignments,
+            vec![
+                (String::from("A"), String::from("a  b")),
+                (String::from("B"), String::new())
+            ],
+            "assigned values shouldn't be split"
+        );
+        assert_eq!(expanded.args, vec!["cmd", "C=1"]);
+    }
+
+    #[test]
+    fn expand_redirection_targets() {
+        let vars = &mut Variables::default();
+        vars.set("TARGET", "out file").unwrap();
+
+        let expanded = expand_command("echo > $TARGET", vars).unwrap();
+        assert_eq!(expanded.args, vec!["echo"]);
+        assert_eq!(
+            expanded.redirections[0].action,
+            Action::Open(String::from("out file"), crate::redirect::Mode::Write)
+        );
+    }
+
+    #[test]
+    fn fields_of_empty_word() {
+        assert_eq!(
+            fields(
+                &crate::parser::ast::Word(Vec::new()),
+                &mut Variables::default()
+            ),
+            Ok(Vec::new())
+        );
+    }
+}
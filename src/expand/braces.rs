@@ -0,0 +1,208 @@
+// Brace expansion, which turns one word into several before any other
+// expansion: `a{b,c}d` becomes `abd acd`, and a sequence such as `{1..10}`,
+// `{a..e}` or `{01..10..2}` becomes each value in turn. Braces may be nested.
+// Only unquoted braces and commas count, and braces that don't form one of
+// these patterns are left as they are.
+
+use crate::parser::ast::{Segment, Word};
+
+pub fn expand(word: &Word) -> Vec<Word> {
+    // Split unquoted text into characters, so that each brace and comma is a
+    // segment of its own.
+    let mut items = Vec::new();
+    for segment in &word.0 {
+        match segment {
+            Segment::Literal(text) => {
+                items.extend(text.chars().map(|c| Segment::Literal(String::from(c))))
+            }
+            segment => items.push(segment.clone()),
+        }
+    }
+
+    expand_items(&items).into_iter().map(join).collect()
+}
+
+fn expand_items(items: &[Segment]) -> Vec<Vec<Segment>> {
+    for open in 0..items.len() {
+        if !is_char(&items[open], '{') {
+            continue;
+        }
+        let Some((close, alternatives)) = alternatives(items, open) else {
+            continue;
+        };
+
+        // Expand the braces in each result too: those nested in the
+        // alternatives, and any that follow.
+        return alternatives
+            .into_iter()
+            .flat_map(|alternative| {
+                let mut result = items[..open].to_vec();
+                result.extend(alternative);
+                result.extend_from_slice(&items[close + 1..]);
+                expand_items(&result)
+            })
+            .collect();
+    }
+
+    vec![items.to_vec()]
+}
+
+// The alternatives that the braces opening at `open` stand for, and the
+// position of the closing brace, if they form a list or a sequence.
+fn alternatives(items: &[Segment], open: usize) -> Option<(usize, Vec<Vec<Segment>>)> {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+
+    for (i, item) in items.iter().enumerate().skip(open + 1) {
+        if is_char(item, '{') {
+            depth += 1;
+        } else if is_char(item, ',') && depth == 0 {
+            commas.push(i);
+        } else if is_char(item, '}') {
+            if depth > 0 {
+                depth -= 1;
+                continue;
+            }
+            if !commas.is_empty() {
+                let mut starts = vec![open + 1];
+                starts.extend(commas.iter().map(|comma| comma + 1));
+                let mut ends = commas.clone();
+                ends.push(i);
+                let alternatives = starts
+                    .into_iter()
+                    .zip(ends)
+                    .map(|(start, end)| items[start..end].to_vec())
+                    .collect();
+                return Some((i, alternatives));
+            }
+
+            let values = sequence(&literal_text(&items[open + 1..i])?)?;
+            let alternatives = values
+                .into_iter()
+                .map(|value| vec![Segment::Literal(value)])
+                .collect();
+            return Some((i, alternatives));
+        }
+    }
+
+    None
+}
+
+// The values of a sequence such as `1..10`, `a..z..2` or `01..10`. Numbers
+// with a leading zero are padded to the same width.
+fn sequence(text: &str) -> Option<Vec<String>> {
+    let parts: Vec<&str> = text.split("..").collect();
+    let (start, end, step) = match parts.as_slice() {
+        [start, end] => (*start, *end, 1),
+        [start, end, step] => (*start, *end, step.parse::<i64>().ok()?.unsigned_abs()),
+        _ => return None,
+    };
+    let step = usize::try_from(step.max(1)).ok()?;
+
+    if let (Ok(first), Ok(last)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        let is_padded = |n: &str| {
+            let digits = n.trim_start_matches('-');
+            digits.len() > 1 && digits.starts_with('0')
+        };
+        let width = if is_padded(start) || is_padded(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+        let values: Vec<i64> = if first <= last {
+            (first..=last).step_by(step).collect()
+        } else {
+            (last..=first).rev().step_by(step).collect()
+        };
+        return Some(values.into_iter().map(|n| format!("{n:0width$}")).collect());
+    }
+
+    let single = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Some(c),
+            _ => None,
+        }
+    };
+    let (first, last) = (single(start)?, single(end)?);
+    let values: Vec<char> = if first <= last {
+        (first..=last).step_by(step).collect()
+    } else {
+        (last..=first).rev().step_by(step).collect()
+    };
+    Some(values.into_iter().map(String::from).collect())
+}
+
+fn is_char(segment: &Segment, c: char) -> bool {
+    matches!(segment, Segment::Literal(text) if text.len() == 1 && text.starts_with(c))
+}
+
+// The text of segments that are all unquoted, or `None` if any aren't.
+fn literal_text(items: &[Segment]) -> Option<String> {
+    items
+        .iter()
+        .map(|item| match item {
+            Segment::Literal(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Put characters back together into words.
+fn join(items: Vec<Segment>) -> Word {
+    let mut segments: Vec<Segment> = Vec::new();
+    for item in items {
+        match (segments.last_mut(), item) {
+            (Some(Segment::Literal(text)), Segment::Literal(more)) => text.push_str(&more),
+            (_, item) => segments.push(item),
+        }
+    }
+    Word(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+    use crate::{
+        parser::{
+            ast::{Segment, Word},
+            parse,
+        },
+        test_helpers::simple_command,
+    };
+
+    fn braces(input: &str) -> Vec<Word> {
+        let list = parse(input).expect("input should parse");
+        expand(&simple_command(&list, 0, 0).words[0])
+    }
+
+    fn literals(words: &[&str]) -> Vec<Word> {
+        words
+            .iter()
+            .map(|text| Word(vec![Segment::Literal(String::from(*text))]))
+            .collect()
+    }
+
+    #[test]
+    fn braces_expand_lists_and_sequences() {
+        assert_eq!(braces("a{b,c}d"), literals(&["abd", "acd"]));
+        assert_eq!(braces("{a,b{1,2}}x"), literals(&["ax", "b1x", "b2x"]));
+        assert_eq!(braces("{a,b}{1,2}"), literals(&["a1", "a2", "b1", "b2"]));
+        assert_eq!(braces("{1..3}"), literals(&["1", "2", "3"]));
+        assert_eq!(braces("{3..1}"), literals(&["3", "2", "1"]));
+        assert_eq!(braces("{a..g..3}"), literals(&["a", "d", "g"]));
+        assert_eq!(braces("{08..10}"), literals(&["08", "09", "10"]));
+        assert_eq!(braces("{-1..1}"), literals(&["-1", "0", "1"]));
+        assert_eq!(braces("x{,y}"), literals(&["x", "xy"]));
+    }
+
+    #[test]
+    fn braces_left_alone() {
+        assert_eq!(braces("{a}"), literals(&["{a}"]));
+        assert_eq!(braces("{a..}"), literals(&["{a..}"]));
+        assert_eq!(braces("{a,b"), literals(&["{a,b"]));
+        assert_eq!(braces("{}"), literals(&["{}"]));
+        assert_eq!(braces(r#""{a,b}""#).len(), 1, "quoted braces");
+        assert_eq!(braces(r"{a\,b}").len(), 1, "an escaped comma");
+    }
+}
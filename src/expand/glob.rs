@@ -0,0 +1,196 @@
+// Pathname expansion: an argument that contains an unquoted `*`, `?` or `[` is
+// a pattern, replaced by the sorted names of the files it matches. Each part
+// of the path between slashes is matched separately. A name that starts with
+// `.` only matches a pattern that starts with `.` too, unless `dotglob` is set.
+// With `globstar` set, a part that's just `**` matches any number of
+// directories, including none.
+
+use std::{fs, path::Path};
+
+use super::pattern;
+use crate::options::Options;
+
+// Whether `pattern` has any characters that are special, unless escaped.
+pub fn has_wildcards(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '*' | '?' | '[' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+// The paths that match `pattern`, sorted.
+pub fn expand(pattern: &str, options: &Options) -> Vec<String> {
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec![String::from("/")], rest),
+        None => (vec![String::new()], pattern),
+    };
+
+    let parts: Vec<&str> = rest.split('/').collect();
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        paths = paths
+            .iter()
+            .flat_map(|path| matches_in(path, part, is_last, options))
+            .collect();
+    }
+
+    paths.sort();
+    paths
+}
+
+// The paths below `dir` that match one part of a pattern. A trailing slash,
+// which leaves an empty last part, matches only directories.
+fn matches_in(dir: &str, part: &str, is_last: bool, options: &Options) -> Vec<String> {
+    if part.is_empty() {
+        return match is_directory(dir) {
+            true if is_last && !dir.ends_with('/') => vec![format!("{dir}/")],
+            true => vec![String::from(dir)],
+            false => Vec::new(),
+        };
+    }
+
+    if part == "**" && options.globstar {
+        // No directories at all, or, at the end of a pattern, the directory
+        // itself.
+        let mut found = Vec::new();
+        match is_last {
+            false => found.push(String::from(dir)),
+            true if !dir.is_empty() => found.push(join(dir, "")),
+            true => {}
+        }
+        descend(dir, options, is_last, &mut found);
+        return found;
+    }
+
+    if !has_wildcards(part) {
+        let path = join(dir, &unescape(part));
+        return match is_last && fs::symlink_metadata(&path).is_err() {
+            true => Vec::new(),
+            false => vec![path],
+        };
+    }
+
+    entries(dir, options, part.starts_with('.'))
+        .into_iter()
+        .filter(|name| pattern::matches(part, name))
+        .map(|name| join(dir, &name))
+        .filter(|path| is_last || is_directory(path))
+        .collect()
+}
+
+// Every directory below `dir`, for `**`, or, at the end of a pattern, every
+// file and directory. Links to directories aren't followed, so that a loop of
+// them can't go on forever.
+fn descend(dir: &str, options: &Options, files_too: bool, found: &mut Vec<String>) {
+    for name in entries(dir, options, false) {
+        let path = join(dir, &name);
+        let is_dir = fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+        if is_dir || files_too {
+            found.push(path.clone());
+        }
+        if is_dir {
+            descend(&path, options, files_too, found);
+        }
+    }
+}
+
+// The names in a directory, leaving out hidden ones unless they're wanted.
+// Directories that can't be read count as empty.
+fn entries(dir: &str, options: &Options, hidden: bool) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| hidden || options.dotglob || !name.starts_with('.'))
+        .collect()
+}
+
+fn is_directory(path: &str) -> bool {
+    Path::new(if path.is_empty() { "." } else { path }).is_dir()
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() || dir.ends_with('/') {
+        format!("{dir}{name}")
+    } else {
+        format!("{dir}/{name}")
+    }
+}
+
+fn unescape(part: &str) -> String {
+    let mut text = String::new();
+    let mut chars = part.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.extend(chars.next()),
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, has_wildcards};
+    use crate::{options::Options, test_helpers::TempStore};
+
+    #[test]
+    fn glob_matches_files() {
+        let temp = TempStore::new(1);
+        let root = &temp.store[0];
+        for file in [
+            "b.rs",
+            "a.rs",
+            "c.txt",
+            ".hidden.rs",
+            "src/main.rs",
+            "src/sub/x.rs",
+        ] {
+            let path = format!("{root}/{file}");
+            std::fs::create_dir_all(std::path::Path::new(&path).parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        let mut options = Options::default();
+        let glob = |pattern: &str, options: &Options| -> Vec<String> {
+            expand(&format!("{root}/{pattern}"), options)
+                .into_iter()
+                .map(|path| path[root.len() + 1..].to_string())
+                .collect()
+        };
+
+        assert_eq!(glob("*.rs", &options), vec!["a.rs", "b.rs"]);
+        assert_eq!(glob(".*.rs", &options), vec![".hidden.rs"]);
+        assert_eq!(glob("[!a].*", &options), vec!["b.rs", "c.txt"]);
+        assert_eq!(glob("*/*.rs", &options), vec!["src/main.rs"]);
+        assert_eq!(glob("*/", &options), vec!["src/"]);
+        assert_eq!(glob("**/*.rs", &options), vec!["src/main.rs"]);
+        assert_eq!(glob(r"\*.rs", &options), Vec::<String>::new());
+
+        options.dotglob = true;
+        assert_eq!(glob("*.rs", &options), vec![".hidden.rs", "a.rs", "b.rs"]);
+
+        options.dotglob = false;
+        options.globstar = true;
+        assert_eq!(
+            glob("**/*.rs", &options),
+            vec!["a.rs", "b.rs", "src/main.rs", "src/sub/x.rs"]
+        );
+        assert_eq!(
+            glob("src/**", &options),
+            vec!["src/", "src/main.rs", "src/sub", "src/sub/x.rs"]
+        );
+
+        assert!(has_wildcards("a[b"));
+        assert!(!has_wildcards(r"a\*b"));
+    }
+}
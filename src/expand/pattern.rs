@@ -0,0 +1,206 @@
+// Shell patterns, as in `${NAME#pattern}`: `*` matches any string, `?` any
+// one character, and `[...]` any one of the characters listed, which may
+// include ranges such as `a-z`, or any character not listed if the list starts
+// with `!` or `^`. A backslash makes the character after it match only itself.
+
+use crate::parser::ast::ReplaceMode;
+
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text)
+}
+
+// Escape the characters of `text` that are special in patterns, so that it
+// matches only itself.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "*?[]\\".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', rest @ ..] => (0..=text.len()).any(|i| match_from(rest, &text[i..])),
+        ['?', rest @ ..] => !text.is_empty() && match_from(rest, &text[1..]),
+        ['[', rest @ ..] => match text.first().and_then(|&c| bracket(rest, c)) {
+            Some((matched, length)) => matched && match_from(&rest[length..], &text[1..]),
+            None => text.first() == Some(&'[') && match_from(rest, &text[1..]),
+        },
+        ['\\', escaped, rest @ ..] => text.first() == Some(escaped) && match_from(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && match_from(rest, &text[1..]),
+    }
+}
+
+// Match `c` against the bracket expression that starts just after a `[`.
+// Returns whether it matched and the length of the rest of the expression, or
+// `None` if there's no closing `]`, in which case the `[` is an ordinary
+// character.
+fn bracket(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let negated = matches!(pattern.first(), Some('!' | '^'));
+    let mut i = usize::from(negated);
+    let mut matched = false;
+
+    // A `]` straight after the `[` (or `[!`) is one of the characters listed.
+    let mut first = true;
+    loop {
+        let mut start = *pattern.get(i)?;
+        if start == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+
+        if start == '\\' {
+            i += 1;
+            start = *pattern.get(i)?;
+        }
+        if pattern.get(i + 1) == Some(&'-')
+            && let Some(&end) = pattern.get(i + 2)
+            && end != ']'
+        {
+            matched |= (start..=end).contains(&c);
+            i += 3;
+        } else {
+            matched |= start == c;
+            i += 1;
+        }
+    }
+}
+
+// The byte offsets at which characters of `text` start, and its length.
+fn boundaries(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect()
+}
+
+// `text` without the shortest (or longest) prefix that matches `pattern`.
+pub fn remove_prefix<'a>(text: &'a str, pattern: &str, longest: bool) -> &'a str {
+    let mut ends = boundaries(text);
+    if longest {
+        ends.reverse();
+    }
+    match ends.into_iter().find(|&end| matches(pattern, &text[..end])) {
+        Some(end) => &text[end..],
+        None => text,
+    }
+}
+
+// `text` without the shortest (or longest) suffix that matches `pattern`.
+pub fn remove_suffix<'a>(text: &'a str, pattern: &str, longest: bool) -> &'a str {
+    let mut starts = boundaries(text);
+    if !longest {
+        starts.reverse();
+    }
+    match starts
+        .into_iter()
+        .find(|&start| matches(pattern, &text[start..]))
+    {
+        Some(start) => &text[..start],
+        None => text,
+    }
+}
+
+// Replace the longest match of `pattern` in `text`, or every such match, or
+// only one at the start or end, depending on `mode`.
+pub fn replace(text: &str, pattern: &str, replacement: &str, mode: ReplaceMode) -> String {
+    let bounds = boundaries(text);
+
+    match mode {
+        ReplaceMode::Prefix => match bounds
+            .iter()
+            .rev()
+            .find(|&&end| matches(pattern, &text[..end]))
+        {
+            Some(&end) => format!("{replacement}{}", &text[end..]),
+            None => String::from(text),
+        },
+        ReplaceMode::Suffix => match bounds
+            .iter()
+            .find(|&&start| matches(pattern, &text[start..]))
+        {
+            Some(&start) => format!("{}{replacement}", &text[..start]),
+            None => String::from(text),
+        },
+        ReplaceMode::First | ReplaceMode::All => {
+            let mut result = String::new();
+            let mut copied = 0;
+            let mut i = 0;
+
+            // Only non-empty matches count, so that an empty pattern changes
+            // nothing.
+            while i < bounds.len() {
+                let start = bounds[i];
+                let Some(length) = bounds[i + 1..]
+                    .iter()
+                    .rev()
+                    .position(|&end| matches(pattern, &text[start..end]))
+                else {
+                    i += 1;
+                    continue;
+                };
+                let end_index = bounds.len() - 1 - length;
+
+                result.push_str(&text[copied..start]);
+                result.push_str(replacement);
+                copied = bounds[end_index];
+                if mode == ReplaceMode::First {
+                    break;
+                }
+                i = end_index;
+            }
+
+            result.push_str(&text[copied..]);
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, matches, remove_prefix, remove_suffix, replace};
+    use crate::parser::ast::ReplaceMode;
+
+    #[test]
+    fn patterns_match() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "main.rc"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("[a-c]x[!0-9]", "bxy"));
+        assert!(!matches("[a-c]x[!0-9]", "bx1"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[", "["), "an unclosed `[` should be literal");
+        assert!(matches(r"\*", "*"));
+        assert!(!matches(r"\*", "a"));
+        assert!(matches(&escape("a*[b]"), "a*[b]"));
+        assert!(!matches(&escape("a*"), "ab"));
+    }
+
+    #[test]
+    fn patterns_remove_prefixes_and_suffixes() {
+        assert_eq!(remove_prefix("a/b/c", "*/", false), "b/c");
+        assert_eq!(remove_prefix("a/b/c", "*/", true), "c");
+        assert_eq!(remove_suffix("a.tar.gz", ".*", false), "a.tar");
+        assert_eq!(remove_suffix("a.tar.gz", ".*", true), "a");
+        assert_eq!(remove_suffix("a.tar.gz", "x", true), "a.tar.gz");
+    }
+
+    #[test]
+    fn patterns_replace() {
+        assert_eq!(replace("aXbXc", "X", "-", ReplaceMode::First), "a-bXc");
+        assert_eq!(replace("aXbXc", "X", "-", ReplaceMode::All), "a-b-c");
+        assert_eq!(replace("aXbXc", "X*", "-", ReplaceMode::First), "a-");
+        assert_eq!(replace("abcab", "ab", "", ReplaceMode::Prefix), "cab");
+        assert_eq!(replace("abcab", "ab", "!", ReplaceMode::Suffix), "abc!");
+        assert_eq!(replace("héllo", "é", "e", ReplaceMode::All), "hello");
+        assert_eq!(replace("abc", "", "-", ReplaceMode::All), "abc");
+    }
+}
@@ -0,0 +1,81 @@
+// Command substitution: `$(...)` or `` `...` `` is replaced by what the command
+// list inside it writes to stdout, minus any trailing newlines. The list runs
+// in a forked copy of the shell, so, as in other shells, any changes it makes
+// to variables or the working directory don't last.
+
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    os::fd::FromRawFd,
+};
+
+use crate::{
+    commands::{self, jobs::JobTable, trap},
+    fork,
+    parser::ast::List,
+    variables::Variables,
+};
+
+// The output of the list. Its exit status becomes `$?`.
+pub fn output(list: &List, vars: &mut Variables) -> Result<String, String> {
+    // Anything still buffered would otherwise be written twice, once by each
+    // process.
+    let _ = io::stdout().flush();
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(format!(
+            "0-shell: Command substitution: {}",
+            io::Error::last_os_error()
+        ));
+    }
+    let [read_end, write_end] = fds;
+
+    let pid = unsafe { libc::fork() };
+    if pid == -1 {
+        let err = io::Error::last_os_error();
+        unsafe {
+            libc::close(read_end);
+            libc::close(write_end);
+        }
+        return Err(format!("0-shell: Command substitution: {err}"));
+    }
+
+    if pid == 0 {
+        unsafe {
+            libc::close(read_end);
+            libc::dup2(write_end, libc::STDOUT_FILENO);
+            libc::close(write_end);
+        }
+        // The subshell has no job control, and no jobs of its own to begin
+        // with.
+        fork::enter_subshell(vars);
+        vars.options.monitor = false;
+        let mut jobs = JobTable::default();
+        let flow = commands::run_list(list, &mut jobs, &mut true, vars);
+        let status = flow.exit_status(vars.last_status);
+        let status = trap::run_exit(status, &mut jobs, vars);
+        let _ = io::stdout().flush();
+        std::process::exit(status);
+    }
+
+    unsafe {
+        libc::close(write_end);
+    }
+    let mut bytes = Vec::new();
+    let read = unsafe { File::from_raw_fd(read_end) }.read_to_end(&mut bytes);
+
+    let mut status = 0;
+    while unsafe { libc::waitpid(pid, &mut status, 0) } == -1 {
+        if io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            break;
+        }
+    }
+    vars.last_status = fork::exit_status(status);
+    vars.substituted = true;
+
+    read.map_err(|e| format!("0-shell: Command substitution: {e}"))?;
+    let mut text = String::from_utf8_lossy(&bytes).into_owned();
+    text.truncate(text.trim_end_matches('\n').len());
+    Ok(text)
+}
@@ -0,0 +1,185 @@
+// Tilde expansion: an unquoted `~` at the start of a word, up to the first
+// `/`, stands for a directory. `~` alone is the home directory, `~user` that
+// of another user, `~+` the working directory and `~-` the previous one. The
+// directory is treated as quoted, so it's neither split nor matched as a
+// pattern. A prefix that names nothing is left as it is. In the value of an
+// assignment, a `~` after any unquoted `:` is expanded too, as in
+// `PATH=$PATH:~/bin`.
+
+use std::env;
+
+use uzers::os::unix::UserExt;
+
+use crate::{
+    parser::ast::{Segment, Word},
+    variables::Variables,
+};
+
+pub fn expand(word: &Word, vars: &Variables) -> Word {
+    let Some((Segment::Literal(text), rest)) = word.0.split_first() else {
+        return word.clone();
+    };
+    let Some((directory, path)) = prefix(text, rest.is_empty(), vars) else {
+        return word.clone();
+    };
+
+    let mut segments = vec![Segment::Quoted(directory)];
+    push_literal(&mut segments, path);
+    segments.extend_from_slice(rest);
+    Word(segments)
+}
+
+// Expand the value of an assignment, where each part of the value between
+// unquoted colons may start with a tilde prefix.
+pub fn assignment(word: &Word, vars: &Variables) -> Word {
+    let mut segments = Vec::new();
+    let mut at_start = true; // At the start of the value, or after a `:`.
+
+    for (index, segment) in word.0.iter().enumerate() {
+        let Segment::Literal(text) = segment else {
+            segments.push(segment.clone());
+            at_start = false;
+            continue;
+        };
+        let last = index + 1 == word.0.len();
+        let parts: Vec<&str> = text.split(':').collect();
+        for (n, part) in parts.iter().enumerate() {
+            if n > 0 {
+                push_literal(&mut segments, ":");
+            }
+            let ends = n + 1 < parts.len() || last;
+            if (n > 0 || at_start)
+                && let Some((directory, path)) = prefix(part, ends, vars)
+            {
+                segments.push(Segment::Quoted(directory));
+                push_literal(&mut segments, path);
+            } else {
+                push_literal(&mut segments, part);
+            }
+        }
+        at_start = text.ends_with(':');
+    }
+    Word(segments)
+}
+
+// The directory that a tilde prefix at the start of `text` stands for, and
+// the rest of `text` after the prefix. The prefix must be unquoted all the
+// way to the `/`, or else to the end of `text`, which `ends` says is where
+// it may end, so `~"user"` isn't expanded.
+fn prefix<'a>(text: &'a str, ends: bool, vars: &Variables) -> Option<(String, &'a str)> {
+    let after = text.strip_prefix('~')?;
+    let (prefix, path) = match after.find('/') {
+        Some(slash) => after.split_at(slash),
+        None if ends => (after, ""),
+        None => return None,
+    };
+    Some((directory(prefix, vars)?, path))
+}
+
+fn push_literal(segments: &mut Vec<Segment>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match segments.last_mut() {
+        Some(Segment::Literal(last)) => last.push_str(text),
+        _ => segments.push(Segment::Literal(String::from(text))),
+    }
+}
+
+fn directory(prefix: &str, vars: &Variables) -> Option<String> {
+    match prefix {
+        "" => vars
+            .get("HOME")
+            .map(String::from)
+            .or_else(|| Some(home::home_dir()?.to_string_lossy().into_owned())),
+        "+" => vars
+            .get("PWD")
+            .map(String::from)
+            .or_else(|| Some(env::current_dir().ok()?.to_string_lossy().into_owned())),
+        "-" => vars.get("OLDPWD").map(String::from),
+        user => {
+            let user = uzers::get_user_by_name(user)?;
+            Some(user.home_dir().to_string_lossy().into_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assignment, expand};
+    use crate::{
+        parser::{
+            ast::{Segment, Word},
+            parse,
+        },
+        test_helpers::simple_command,
+        variables::Variables,
+    };
+
+    fn tilde(input: &str, vars: &Variables) -> Word {
+        let list = parse(input).expect("input should parse");
+        expand(&simple_command(&list, 0, 0).words[0], vars)
+    }
+
+    #[test]
+    fn tilde_expands_directories() {
+        let mut vars = Variables::default();
+        vars.set("HOME", "/home/me").unwrap();
+        vars.set("OLDPWD", "/old").unwrap();
+
+        let quoted = |text: &str| Segment::Quoted(String::from(text));
+        let literal = |text: &str| Segment::Literal(String::from(text));
+
+        assert_eq!(tilde("~", &vars), Word(vec![quoted("/home/me")]));
+        assert_eq!(
+            tilde("~/a", &vars),
+            Word(vec![quoted("/home/me"), literal("/a")])
+        );
+        assert_eq!(
+            tilde("~-/x", &vars),
+            Word(vec![quoted("/old"), literal("/x")])
+        );
+        assert_eq!(
+            tilde("~root/x", &vars),
+            Word(vec![quoted("/root"), literal("/x")])
+        );
+        assert_eq!(
+            tilde("~no_such_user_here", &vars),
+            Word(vec![literal("~no_such_user_here")])
+        );
+        assert_eq!(
+            tilde(r#""~""#, &vars),
+            Word(vec![quoted("~")]),
+            "a quoted tilde isn't expanded"
+        );
+        assert_eq!(
+            tilde(r#"~"me""#, &vars),
+            Word(vec![literal("~"), quoted("me")])
+        );
+        assert_eq!(tilde("a~", &vars), Word(vec![literal("a~")]));
+    }
+
+    #[test]
+    fn tilde_expands_after_colons_in_assignments() {
+        let mut vars = Variables::default();
+        vars.set("HOME", "/home/me").unwrap();
+
+        let list = parse(r#"A=~/a:b:~:~-x:"~":c~:~/d"#).expect("input should parse");
+        let value = &simple_command(&list, 0, 0).assignments[0].value;
+        let quoted = |text: &str| Segment::Quoted(String::from(text));
+        let literal = |text: &str| Segment::Literal(String::from(text));
+        assert_eq!(
+            assignment(value, &vars),
+            Word(vec![
+                quoted("/home/me"),
+                literal("/a:b:"),
+                quoted("/home/me"),
+                literal(":~-x:"),
+                quoted("~"),
+                literal(":c~:"),
+                quoted("/home/me"),
+                literal("/d"),
+            ])
+        );
+    }
+}
@@ -1,64 +1,253 @@
 mod child;
 mod parent;
 
-use std::{env, ffi::CString, ptr};
+use std::{
+    env,
+    ffi::CString,
+    io::{self, Write},
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    ptr,
+};
 
-use crate::commands::jobs::Job;
+use crate::{
+    commands::{self, jobs::JobTable},
+    expand::ExpandedStage,
+    parser::ast::CompoundCommand,
+    redirect::{self, Prepared},
+    variables::Variables,
+};
 
-// Store both items in a struct to ensure `c_strings` can't be dropped first, leaving
-// the pointers dangling.
+pub use child::enter_subshell;
+pub use parent::{clear_interrupted, exit_status, wait_for_job, was_interrupted};
+
+// Store the strings alongside the pointers to them to ensure the strings can't
+// be dropped first, leaving the pointers dangling.
 struct PreparedCommand {
     ptrs: Vec<*const i8>,
     c_strings: Vec<CString>,
+    env_ptrs: Vec<*const i8>,
+    _env_strings: Vec<CString>,
+    program: Option<CString>, // The file to exec, or `None` if it wasn't found.
+}
+
+// What a child process does once its pipes and redirections are in place:
+// either run one of the shell's builtins in the forked copy of the shell,
+// with any assignments that precede it, run a compound command there, or
+// exec a program.
+enum Stage {
+    Builtin(Vec<String>, Vec<(String, String)>),
+    Compound(CompoundCommand),
+    Exec(PreparedCommand),
 }
 
+// Launch a job: a pipeline of one or more stages, each of which is an expanded
+// command. All stages are placed in a single process group, led by the first
+// stage, so that job control (`fg`, `bg`, `kill`, Ctrl+C, Ctrl+Z) treats the
+// pipeline as a unit. `text` is the pipeline as typed, for the jobs table. The
+// exit status of a foreground job becomes `$?`; the PID of the last stage of a
+// background job becomes `$!`. Programs get the exported variables, plus any
+// assignments before them, as their environment. Without job control, a
+// foreground job stays in the shell's own process group.
 pub fn spawn_job(
-    args: &[String],
-    jobs: &mut Vec<Job>,
+    stages: &[ExpandedStage],
+    text: &str,
+    jobs: &mut JobTable,
     is_background_launch: bool,
-    is_worker: bool,
-    current: &mut usize,
-    previous: &mut usize,
+    vars: &mut Variables,
 ) -> Result<String, String> {
-    // Build argv (command-line arguments) up front so that the child doesn't
-    // have to allocate after the fork.
-    let cmd = prepare_command(is_worker, args)?;
-    let pid = unsafe { libc::fork() };
-
-    match pid {
-        -1 => Err(String::from("Fork failed")),
-        0 => {
-            unsafe { child::run_child(cmd.ptrs, cmd.c_strings) };
-            unreachable!();
+    // Until we know better, assume failure.
+    vars.last_status = 1;
+
+    // Build argv (command-line arguments) up front so that the children don't
+    // have to allocate after the fork. Open the files named in redirections
+    // now too, so that failures can be reported like other errors.
+    let prepared = stages
+        .iter()
+        .map(|stage| {
+            let command = match stage {
+                ExpandedStage::Simple(command) => command,
+                ExpandedStage::Compound { body, redirections } => {
+                    let redirections = redirect::prepare(redirections)?;
+                    return Ok((Stage::Compound(body.clone()), redirections));
+                }
+                // A definition in a pipeline only defines the function in its
+                // own copy of the shell, so there's nothing to do.
+                ExpandedStage::Definition(_) => {
+                    return Ok((Stage::Builtin(Vec::new(), Vec::new()), Vec::new()));
+                }
+            };
+            let args = &command.args;
+            let redirections = redirect::prepare(&command.redirections)?;
+            let stage = if args.is_empty()
+                || commands::is_builtin(&args[0])
+                || vars.function(&args[0]).is_some()
+            {
+                Stage::Builtin(args.clone(), command.assignments.clone())
+            } else {
+                let environment = vars.environment(&command.assignments)?;
+                let is_worker = commands::is_worker(&args[0]);
+                Stage::Exec(prepare_command(
+                    is_worker,
+                    args,
+                    environment,
+                    vars.get("PATH"),
+                )?)
+            };
+            Ok((stage, redirections))
+        })
+        .collect::<Result<Vec<(Stage, Vec<Prepared>)>, String>>()?;
+
+    // Anything still sitting in our `stdout` buffer would otherwise be
+    // duplicated into each child.
+    io::stdout().flush().ok();
+
+    let own_group = vars.options.monitor || is_background_launch;
+    let mut pids: Vec<i32> = Vec::with_capacity(prepared.len());
+    let mut pgid = 0;
+    let mut read_end = -1; // The read end of the previous stage's pipe.
+
+    for (index, (stage, redirections)) in prepared.into_iter().enumerate() {
+        let is_last = index + 1 == stages.len();
+        let mut fds = [-1, -1];
+
+        if !is_last && unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            let err = io::Error::last_os_error();
+            close_fd(read_end);
+            reap_abandoned(&pids);
+            return Err(format!("Pipe failed: {err}"));
+        }
+
+        let pid = unsafe { libc::fork() };
+
+        match pid {
+            -1 => {
+                close_fd(read_end);
+                close_fd(fds[0]);
+                close_fd(fds[1]);
+                reap_abandoned(&pids);
+                return Err(String::from("Fork failed"));
+            }
+            0 => {
+                unsafe {
+                    if own_group {
+                        child::join_process_group(pgid);
+                    }
+                    child::connect_pipes(read_end, fds[0], fds[1]);
+                    child::redirect(&redirections);
+                    match stage {
+                        Stage::Builtin(args, assignments) => {
+                            child::run_builtin(&args, &assignments, jobs, vars)
+                        }
+                        Stage::Compound(body) => child::run_compound(&body, vars),
+                        Stage::Exec(cmd) => child::run_child(cmd),
+                    }
+                };
+                unreachable!();
+            }
+            child_pid => {
+                if pgid == 0 {
+                    pgid = child_pid;
+                }
+
+                // Set the process group here as well as in the child because
+                // we don't know which the OS will choose to run first.
+                if own_group {
+                    unsafe { libc::setpgid(child_pid, pgid) };
+                }
+                pids.push(child_pid);
+
+                close_fd(read_end);
+                close_fd(fds[1]);
+                read_end = fds[0];
+
+                // The child has its own copies of the files now.
+                drop(redirections);
+            }
+        }
+    }
+
+    if is_background_launch {
+        vars.last_background = pids.last().copied();
+    }
+
+    parent::run_parent(text, jobs, is_background_launch, &pids, vars)
+}
+
+fn close_fd(fd: i32) {
+    if fd >= 0 {
+        unsafe { libc::close(fd) };
+    }
+}
+
+// If a later stage of a pipeline couldn't be launched, don't leave the earlier
+// ones running unsupervised.
+fn reap_abandoned(pids: &[i32]) {
+    for &pid in pids {
+        unsafe {
+            libc::kill(pid, libc::SIGKILL);
+            libc::waitpid(pid, ptr::null_mut(), 0);
         }
-        child_pid => parent::run_parent(
-            args,
-            jobs,
-            is_background_launch,
-            current,
-            previous,
-            child_pid,
-        ),
     }
 }
 
-fn prepare_command(is_worker: bool, args: &[String]) -> Result<PreparedCommand, String> {
-    let c_strings = get_c_strings(is_worker, args)?;
+fn prepare_command(
+    is_worker: bool,
+    args: &[String],
+    environment: Vec<String>,
+    path: Option<&str>,
+) -> Result<PreparedCommand, String> {
+    let c_strings = get_c_strings(get_exec_args(is_worker, args))?;
     let mut ptrs: Vec<*const i8> = c_strings.iter().map(|s| s.as_ptr()).collect();
     ptrs.push(ptr::null());
 
-    Ok(PreparedCommand { ptrs, c_strings })
+    let env_strings = get_c_strings(environment)?;
+    let mut env_ptrs: Vec<*const i8> = env_strings.iter().map(|s| s.as_ptr()).collect();
+    env_ptrs.push(ptr::null());
+
+    let program = match find_program(&c_strings[0].to_string_lossy(), path) {
+        Some(program) => Some(get_c_strings(vec![program])?.remove(0)),
+        None => None,
+    };
+
+    Ok(PreparedCommand {
+        ptrs,
+        c_strings,
+        env_ptrs,
+        _env_strings: env_strings,
+        program,
+    })
 }
 
-fn get_c_strings(is_worker: bool, args: &[String]) -> Result<Vec<CString>, String> {
-    let exec_args = get_exec_args(is_worker, args);
-    exec_args
+fn get_c_strings(strings: Vec<String>) -> Result<Vec<CString>, String> {
+    strings
         .into_iter()
         .map(|s| CString::new(s).map_err(|_| String::from("Argument contains interior NUL byte")))
         .collect()
 }
 
-fn get_exec_args(is_worker: bool, args: &[String]) -> Vec<String> {
+// Look a command up in the directories of the shell's `PATH`, rather than the
+// environment 0-shell itself was started with. A name containing a `/` is used
+// as it is.
+fn find_program(name: &str, path: Option<&str>) -> Option<String> {
+    if name.contains('/') {
+        return Some(String::from(name));
+    }
+
+    path?
+        .split(':')
+        .map(|dir| if dir.is_empty() { "." } else { dir })
+        .map(|dir| Path::new(dir).join(name))
+        .find(|candidate| {
+            candidate
+                .metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+        .map(|candidate| candidate.to_string_lossy().into_owned())
+}
+
+// The program to exec and its arguments: for a worker, this program itself.
+pub fn get_exec_args(is_worker: bool, args: &[String]) -> Vec<String> {
     if is_worker {
         let self_path = env::current_exe()
             .unwrap_or_else(|_| std::path::PathBuf::from("./0-shell"))
@@ -1,40 +1,121 @@
-use std::ffi::CString;
+use std::io::{self, Write};
 
-use crate::error;
+use super::PreparedCommand;
+use crate::{
+    commands::{self, jobs::JobTable, trap},
+    compound, error,
+    parser::ast::CompoundCommand,
+    redirect::{self, Prepared},
+    signals,
+    variables::Variables,
+};
 
-pub unsafe fn run_child(ptrs: Vec<*const i8>, c_strings: Vec<CString>) {
+// Make the child a member of the job's process group, or the leader of a new
+// one if it's the first stage (`pgid == 0`). We do this in both parent and
+// child code because we don't know which the OS will choose to run first.
+pub unsafe fn join_process_group(pgid: i32) {
     unsafe {
-        let child_pid = libc::getpid(); // The child learns its own PID.
-
-        // Make the child the leader of a new process group. We place this line
-        // in both parent and child code because we don't know which the OS will
-        // choose to run first.
-        libc::setpgid(child_pid, child_pid);
-
-        restore_default_signal_handlers(); // Stop ignoring signals.
+        libc::setpgid(0, pgid);
+    }
+}
 
-        // Replace the current process image with the target program, keeping
-        // the same PID. (V = vector args; P = path lookup.)
-        libc::execvp(ptrs[0], ptrs.as_ptr());
+// Wire up the child's end of the pipeline: read from the previous stage's pipe
+// (if any) and write into the next stage's pipe (if any). `unused` is the read
+// end of our own output pipe, which belongs to the next stage.
+pub unsafe fn connect_pipes(read_end: i32, unused: i32, write_end: i32) {
+    unsafe {
+        if read_end >= 0 {
+            libc::dup2(read_end, libc::STDIN_FILENO);
+            libc::close(read_end);
+        }
+        if unused >= 0 {
+            libc::close(unused);
+        }
+        if write_end >= 0 {
+            libc::dup2(write_end, libc::STDOUT_FILENO);
+            libc::close(write_end);
+        }
+    }
+}
 
-        // If we reach this line, `execvp` failed.
-        error::print_exec_failure(c_strings[0].as_bytes());
+// Apply redirections after the pipes, so that, e.g., an explicit `< file`
+// takes precedence over the previous stage of a pipeline, and `2>&1` sends
+// stderr into the pipe. If one fails, the command isn't run.
+pub unsafe fn redirect(redirections: &[Prepared]) {
+    if let Err(err) = redirect::apply(redirections) {
+        error::red_println(&err);
         std::process::exit(1);
     }
 }
 
-fn restore_default_signal_handlers() {
+pub unsafe fn run_child(command: PreparedCommand) {
     unsafe {
-        libc::signal(libc::SIGINT, libc::SIG_DFL); // Ctrl+C
-        libc::signal(libc::SIGTSTP, libc::SIG_DFL); // Ctrl+Z
-        libc::signal(libc::SIGQUIT, libc::SIG_DFL); // Ctrl+\
+        signals::restore_defaults(); // Stop ignoring signals.
 
-        // Allow SIGTTIN to suspend us if we try to read from the TTY while in
-        // the background.
-        libc::signal(libc::SIGTTIN, libc::SIG_DFL);
+        // Replace the current process image with the target program, keeping
+        // the same PID. (V = vector args; E = environment.)
+        if let Some(program) = &command.program {
+            libc::execve(
+                program.as_ptr(),
+                command.ptrs.as_ptr(),
+                command.env_ptrs.as_ptr(),
+            );
+        }
 
-        // Allow SIGTTOU to suspend us if we try to write to the TTY or take
-        // control of the terminal while in the background.
-        libc::signal(libc::SIGTTOU, libc::SIG_DFL);
+        // If we reach this line, the program wasn't found or `execve` failed.
+        // As in other shells, the exit status is 127 if there's no such
+        // program and 126 if it exists but can't be run.
+        let (reason, status): (&[u8], i32) = match &command.program {
+            None => (b"command not found", 127),
+            Some(_) => match std::io::Error::last_os_error().raw_os_error() {
+                Some(libc::ENOENT) => (b"no such file or directory", 127),
+                _ => (b"permission denied", 126),
+            },
+        };
+        error::print_exec_failure(reason, command.c_strings[0].as_bytes());
+        std::process::exit(status);
     }
 }
+
+// A builtin that's part of a pipeline runs in this forked copy of the shell, as
+// in other shells, so any changes it makes (e.g. `cd`) don't affect 0-shell
+// itself.
+pub unsafe fn run_builtin(
+    args: &[String],
+    assignments: &[(String, String)],
+    jobs: &mut JobTable,
+    vars: &mut Variables,
+) {
+    enter_subshell(vars);
+    // This is a copy of the shell, so there's nothing to restore afterwards.
+    if let Err(err) = vars.set_temporarily(assignments) {
+        error::red_println(&err);
+        std::process::exit(1);
+    }
+    let code = commands::run_builtin_in_subshell(args, jobs, vars);
+    let code = trap::run_exit(code, jobs, vars);
+    let _ = io::stdout().flush();
+    std::process::exit(code);
+}
+
+// A subshell, or any other compound command that's part of a pipeline or a
+// background job, runs in this forked copy of the shell, which has no job
+// control and no jobs of its own to begin with. Its status is that of the
+// last command.
+pub unsafe fn run_compound(body: &CompoundCommand, vars: &mut Variables) {
+    enter_subshell(vars);
+    vars.options.monitor = false;
+    let mut jobs = JobTable::default();
+    let flow = compound::run(body, &mut jobs, &mut true, vars);
+    let status = flow.exit_status(vars.last_status);
+    let status = trap::run_exit(status, &mut jobs, vars);
+    let _ = io::stdout().flush();
+    std::process::exit(status);
+}
+
+// As in other shells, a subshell keeps the traps that ignore signals, but not
+// those that run commands.
+pub fn enter_subshell(vars: &mut Variables) {
+    signals::restore_defaults();
+    vars.clear_traps();
+}
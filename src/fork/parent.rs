@@ -1,32 +1,56 @@
-use std::{io, sync::atomic::Ordering};
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use crate::commands::{
-    fg::CURRENT_CHILD_PID,
-    jobs::{Job, State},
+use crate::{
+    commands::{
+        fg::CURRENT_CHILD_PID,
+        jobs::{Job, JobTable, State},
+    },
+    variables::Variables,
 };
 
+// Set when a foreground job is killed by Ctrl+C. As in other shells, the rest
+// of the command list, and any loop it's in, is then abandoned.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub fn was_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+// Forget any interruption, before running a new line of input.
+pub fn clear_interrupted() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
 pub fn run_parent(
-    args: &[String],
-    jobs: &mut Vec<Job>,
+    text: &str,
+    jobs: &mut JobTable,
     is_background_launch: bool,
-    current: &mut usize,
-    previous: &mut usize,
-    child_pid: i32,
+    pids: &[i32],
+    vars: &mut Variables,
 ) -> Result<String, String> {
-    unsafe {
-        // Make the child the leader of a new process group. We place this line
-        // in both parent and child code because we don't know which the OS will
-        // choose to run first.
-        libc::setpgid(child_pid, child_pid);
+    // The first stage leads the process group.
+    let pgid = pids[0];
+    let last_pid = pids[pids.len() - 1];
 
+    unsafe {
         match is_background_launch {
             true => {
-                let id = jobs.len() + 1;
-                let cmd_str = display_command(args);
-                jobs.push(Job::new(id, child_pid, cmd_str, State::Running));
-                *previous = *current;
-                *current = id;
-                return Ok(format!("[{}] {}\n", id, child_pid));
+                let id = jobs.add(Job::with_pids(0, pids, String::from(text), State::Running));
+                vars.last_status = 0;
+                // Only job control announces the job's ID.
+                if !vars.options.monitor {
+                    return Ok(String::new());
+                }
+                return Ok(format!("[{}] {}\n", id, last_pid));
+            }
+            // Without job control, as in a subshell, the job shares our
+            // process group and the terminal, and isn't stopped separately.
+            false if !vars.options.monitor => {
+                let mut job = Job::with_pids(0, pids, String::from(text), State::Running);
+                vars.last_status = exit_status(wait(&mut job, false)?);
             }
             false => {
                 // Snapshot the shell's current terminal settings in case a
@@ -38,15 +62,18 @@ pub fn run_parent(
 
                 // Hand terminal control to the foreground child's group.
                 // It will now be be the recipient of any SIGINT or SIGTSTP.
-                libc::tcsetpgrp(libc::STDIN_FILENO, child_pid);
-                CURRENT_CHILD_PID.store(child_pid, Ordering::SeqCst);
+                libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+                CURRENT_CHILD_PID.store(pgid, Ordering::SeqCst);
 
-                let status = wait_for_foreground_child(child_pid)?;
+                let mut job = Job::with_pids(0, pids, String::from(text), State::Running);
+                let status = wait_for_job(&mut job)?;
+                vars.last_status = exit_status(status);
 
                 // If the child was terminated by SIGINT, the cursor to the next
                 // line so that the prompt doesn't overwrite the `^C`.
                 if libc::WIFSIGNALED(status) && libc::WTERMSIG(status) == libc::SIGINT {
                     println!();
+                    INTERRUPTED.store(true, Ordering::SeqCst);
                 }
 
                 // Reclaim the terminal for the shell. At this point, since the
@@ -63,12 +90,11 @@ pub fn run_parent(
 
                 // The child was stopped: keep it in the jobs table.
                 if libc::WIFSTOPPED(status) {
-                    let id = jobs.len() + 1;
-                    let cmd_str = display_command(args);
-                    jobs.push(Job::new(id, child_pid, cmd_str.clone(), State::Stopped));
-                    *previous = *current;
-                    *current = id;
-                    println!("\n[{}]+\tStopped\t\t{}", id, cmd_str);
+                    job.state = State::from_wait_status(status);
+                    let id = jobs.add(job);
+                    if let Some(job) = jobs.get(id) {
+                        println!("\n{}", job.notification(id, jobs.previous()));
+                    }
                 }
             }
         }
@@ -76,46 +102,45 @@ pub fn run_parent(
     Ok(String::new())
 }
 
-fn display_command(args: &[String]) -> String {
-    let mut display_parts: Vec<String> = Vec::with_capacity(args.len());
-    let mut i = 0;
-
-    while i < args.len() {
-        if i + 2 < args.len() {
-            if args[i].parse::<i32>().is_ok() {
-                let op = args[i + 1].as_str();
-                if (op == ">" || op == ">>") && !args[i + 2].is_empty() {
-                    display_parts.push(format!("{}{}{}", args[i], op, args[i + 2]));
-                    i += 3;
-                    continue;
-                }
-            }
-        }
-
-        display_parts.push(args[i].clone());
-        i += 1;
+// Convert a wait status into the exit status that a shell reports: the exit
+// code of a process that exited, or 128 plus the number of the signal that
+// terminated or stopped it.
+pub fn exit_status(status: i32) -> i32 {
+    if libc::WIFEXITED(status) {
+        libc::WEXITSTATUS(status)
+    } else if libc::WIFSIGNALED(status) {
+        128 + libc::WTERMSIG(status)
+    } else if libc::WIFSTOPPED(status) {
+        128 + libc::WSTOPSIG(status)
+    } else {
+        1
     }
+}
 
-    display_parts.join(" ")
+// Block the shell process until the foreground job either finishes (every
+// process in the pipeline has terminated) or is stopped (e.g. by Ctrl+Z).
+// Returns the wait status of the last process in the pipeline, or the status
+// of the process that reported being stopped.
+pub fn wait_for_job(job: &mut Job) -> Result<i32, String> {
+    wait(job, true)
 }
 
-// Block the shell process until the specific foreground child `child_pid`
-// changes state. This handles both termination (Ctrl+C) and suspension (Ctrl+Z).
-fn wait_for_foreground_child(child_pid: i32) -> Result<i32, String> {
-    let mut status = 0;
+// Without job control, the processes aren't in a group of their own, so wait
+// for each in turn, and only for them to finish.
+fn wait(job: &mut Job, job_control: bool) -> Result<i32, String> {
+    while !job.pids.is_empty() {
+        let mut status = 0;
+        let (target, options) = match job_control {
+            true => (-job.pgid, libc::WUNTRACED),
+            false => (job.pids[0], 0),
+        };
 
-    loop {
         // `waitpid` normally only returns when a child terminates.
         // `WUNTRACED` tells it to also return if the child is stopped by a signal
         // (e.g., if the user hits Ctrl+Z). This is essential for Job Control.
-        let res = unsafe { libc::waitpid(child_pid, &mut status, libc::WUNTRACED) };
-
-        // Success: the specific child we were waiting for has changed state.
-        if res == child_pid {
-            return Ok(status);
-        }
+        // A negative first argument means any child in that process group.
+        let res = unsafe { libc::waitpid(target, &mut status, options) };
 
-        // Failure or interruption
         if res == -1 {
             let err = io::Error::last_os_error();
 
@@ -126,12 +151,26 @@ fn wait_for_foreground_child(child_pid: i32) -> Result<i32, String> {
                 continue;
             }
 
-            // Real failure (e.g., the child does not exist).
+            // ECHILD: no children left in the group. Someone else reaped them,
+            // so unless the last one had already been waited for, its status
+            // is unknown, and taken to be 127, as `wait` gives for a process
+            // that isn't the shell's.
+            if err.raw_os_error() == Some(libc::ECHILD) {
+                job.pids.clear();
+                job.status.get_or_insert(libc::W_EXITCODE(127, 0));
+                break;
+            }
+
+            // Real failure.
             return Err(format!("waitpid failed: {}", err));
         }
 
-        // Unexpected: waitpid returned a PID we didn't ask for.
-        // (This should technically be impossible when passing a positive PID).
-        return Err(format!("waitpid returned unexpected pid: {}", res));
+        if libc::WIFSTOPPED(status) {
+            return Ok(status);
+        }
+
+        job.record_termination(res, status);
     }
+
+    Ok(job.status.unwrap_or(0))
 }
@@ -1,5 +1,4 @@
 mod backtrack;
-pub mod split;
 
 use std::{
     collections::VecDeque,
@@ -19,33 +18,66 @@ use whoami;
 
 use crate::{
     ansi::{CLEAR_LINE, FOLDER_COLOR, RESET_FG, USER_COLOR},
-    commands::{ls, rm},
+    commands::{
+        export,
+        jobs::{self, JobTable},
+        ls, rm, shopt,
+    },
+    parser, signals,
 };
 
+const CONTINUATION_PROMPT: &str = "> ";
+
 lazy_static! {
     static ref COMMANDS: Vec<String> = vec![
+        String::from("alias"),
         String::from("bg"),
+        String::from("break"),
         String::from("cat"),
         String::from("cd"),
+        String::from("continue"),
         String::from("cp"),
+        String::from("declare"),
+        String::from("disown"),
         String::from("echo"),
         String::from("exit"),
+        String::from("export"),
         String::from("fg"),
         String::from("jobs"),
         String::from("kill"),
+        String::from("let"),
+        String::from("local"),
         String::from("ls"),
         String::from("mkdir"),
         String::from("man"),
         String::from("mv"),
+        String::from("nohup"),
         String::from("pwd"),
+        String::from("readonly"),
+        String::from("return"),
         String::from("rm"),
+        String::from("set"),
+        String::from("shopt"),
         String::from("sleep"),
+        String::from("source"),
+        String::from("test"),
         String::from("touch"),
+        String::from("trap"),
+        String::from("unalias"),
+        String::from("unset"),
+        String::from("wait"),
     ];
 }
 
-pub fn get_input(history: &mut VecDeque<String>) -> io::Result<Option<String>> {
-    let stdin = io::stdin();
+// Read a command from the terminal. `aliases` are the names of any aliases,
+// which are completed along with the names of commands. Given the jobs, as
+// with `set -b`, report any that finish or stop while waiting for the user,
+// as soon as they do.
+pub fn get_input(
+    history: &mut VecDeque<String>,
+    aliases: &[String],
+    mut jobs: Option<&mut JobTable>,
+) -> io::Result<Option<String>> {
     let mut stdout = io::stdout().into_raw_mode()?;
 
     unsafe {
@@ -57,14 +89,124 @@ pub fn get_input(history: &mut VecDeque<String>) -> io::Result<Option<String>> {
         }
     }
 
+    let prompt = prompt()?;
+    let Some(mut input) = read_line(
+        &mut stdout,
+        history,
+        &prompt,
+        false,
+        aliases,
+        jobs.as_deref_mut(),
+    ) else {
+        stdout.suspend_raw_mode().expect("failed to reset terminal");
+        return Ok(None);
+    };
+
+    // A line recalled from history is already complete, with the bodies of
+    // its here-documents. Ctrl+D ends a command early.
+    if !input.contains('\n') {
+        complete_command(&mut input, || {
+            read_line(
+                &mut stdout,
+                history,
+                CONTINUATION_PROMPT,
+                true,
+                aliases,
+                jobs.as_deref_mut(),
+            )
+        });
+    }
+
+    stdout
+        .suspend_raw_mode()
+        .expect("failed to suspend raw mode");
+
+    Ok(Some(input))
+}
+
+// Read the rest of a command that begins with `input`, taking lines from
+// `next_line`: the body of each here-document, up to and including its
+// delimiter, and further lines while the command isn't finished, as with
+// `if` without its `fi`. Stops early if `next_line` runs out.
+pub fn complete_command(input: &mut String, mut next_line: impl FnMut() -> Option<String>) {
+    let mut line = input.clone();
+    loop {
+        for (delimiter, strip_tabs) in parser::heredoc_delimiters(&line) {
+            loop {
+                let Some(body) = next_line() else {
+                    return;
+                };
+
+                input.push('\n');
+                input.push_str(&body);
+
+                let candidate = if strip_tabs {
+                    body.trim_start_matches('\t')
+                } else {
+                    &body
+                };
+                if candidate == delimiter {
+                    break;
+                }
+            }
+        }
+
+        if !parser::is_incomplete(input) {
+            return;
+        }
+        let Some(next) = next_line() else {
+            return;
+        };
+        input.push('\n');
+        input.push_str(&next);
+        line = next;
+    }
+}
+
+// Read one line of input in raw mode, with editing, history and completion.
+// A continuation line (part of a here-document or of an unfinished command)
+// takes tabs literally and doesn't use the history. Returns `None` on Ctrl+D
+// at the start of a line.
+fn read_line(
+    stdout: &mut RawTerminal<Stdout>,
+    history: &mut VecDeque<String>,
+    prompt: &str,
+    is_continuation: bool,
+    aliases: &[String],
+    mut jobs: Option<&mut JobTable>,
+) -> Option<String> {
+    let mut keys = RawStdin.keys();
     let mut input = String::new();
     let mut cursor = 0;
 
-    let prompt = prompt()?;
+    // Anything that happened before now is reported first.
+    let children = signals::child_notifications();
+    if let (Some(jobs), Some(_)) = (jobs.as_deref_mut(), children) {
+        signals::clear_child_notifications();
+        jobs::check_background_jobs(jobs, true);
+    }
+
     write!(stdout, "\r{}{}", prompt, termion::cursor::Show).expect("failed to write to `stdout`");
     stdout.flush().expect("failed to flush `stdout`");
 
-    for byte in stdin.keys() {
+    loop {
+        if let (Some(jobs), Some(fd)) = (jobs.as_deref_mut(), children)
+            && !wait_for_key(fd)
+        {
+            // Report the jobs on lines of their own, and then start the line
+            // being edited afresh below them.
+            signals::clear_child_notifications();
+            write!(stdout, "\r{}", termion::clear::CurrentLine)
+                .expect("failed to write to `stdout`");
+            stdout.flush().expect("failed to flush `stdout`");
+            jobs::check_background_jobs(jobs, true);
+            redraw(stdout, prompt, &input, cursor);
+            continue;
+        }
+
+        let Some(byte) = keys.next() else {
+            break;
+        };
         match byte {
             Ok(key) => {
                 match key {
@@ -72,8 +214,7 @@ pub fn get_input(history: &mut VecDeque<String>) -> io::Result<Option<String>> {
                         if input.is_empty() {
                             // Show ^D on its own line before any following output.
                             write!(stdout, "\r\n^D\r\n").expect("failed to write to `stdout`");
-                            stdout.suspend_raw_mode().expect("failed to reset terminal");
-                            return Ok(None);
+                            return None;
                         }
                         continue;
                     }
@@ -86,11 +227,21 @@ pub fn get_input(history: &mut VecDeque<String>) -> io::Result<Option<String>> {
                         stdout.flush().expect("failed to flush `stdout`");
                         break;
                     }
-                    Key::Char('\t') => {
-                        if tab_and_should_continue(&mut input, &mut cursor, &mut stdout, &prompt) {
-                            continue;
-                        }
+                    Key::Char('\t')
+                        if !is_continuation
+                            && tab_and_should_continue(
+                                &mut input,
+                                &mut cursor,
+                                stdout,
+                                prompt,
+                                aliases,
+                            ) =>
+                    {
+                        continue;
                     }
+                    // A single match has been filled in, and only needs
+                    // showing.
+                    Key::Char('\t') if !is_continuation => {}
                     Key::Char(c) => {
                         input.insert(cursor, c);
                         cursor += c.len_utf8();
@@ -117,7 +268,7 @@ pub fn get_input(history: &mut VecDeque<String>) -> io::Result<Option<String>> {
                             }
                         }
                     }
-                    Key::Up => {
+                    Key::Up if !is_continuation => {
                         if !history.is_empty() {
                             history.rotate_right(1);
                             if let Some(item) = history.front() {
@@ -126,7 +277,7 @@ pub fn get_input(history: &mut VecDeque<String>) -> io::Result<Option<String>> {
                             }
                         }
                     }
-                    Key::Down => {
+                    Key::Down if !is_continuation => {
                         if !history.is_empty() {
                             history.rotate_left(1);
                             if let Some(item) = history.front() {
@@ -141,17 +292,7 @@ pub fn get_input(history: &mut VecDeque<String>) -> io::Result<Option<String>> {
                     _ => {}
                 }
 
-                write!(stdout, "\r{}{}", prompt, termion::clear::AfterCursor)
-                    .expect("failed to write to `stdout`");
-                write!(stdout, "{}", input).expect("failed to write to `stdout`");
-
-                let chars_remaining = input[cursor..].chars().count();
-                if chars_remaining > 0 {
-                    write!(stdout, "{}", termion::cursor::Left(chars_remaining as u16))
-                        .expect("failed to write to `stdout`");
-                }
-
-                stdout.flush().expect("failed to flush `stdout`");
+                redraw(stdout, prompt, &input, cursor);
             }
             Err(_) => {
                 continue;
@@ -159,11 +300,63 @@ pub fn get_input(history: &mut VecDeque<String>) -> io::Result<Option<String>> {
         }
     }
 
-    stdout
-        .suspend_raw_mode()
-        .expect("failed to suspend raw mode");
+    Some(input)
+}
 
-    Ok(Some(input))
+// Show the line being edited, with the cursor in its place.
+fn redraw(stdout: &mut RawTerminal<Stdout>, prompt: &str, input: &str, cursor: usize) {
+    write!(stdout, "\r{}{}", prompt, termion::clear::AfterCursor)
+        .expect("failed to write to `stdout`");
+    write!(stdout, "{}", input).expect("failed to write to `stdout`");
+
+    let chars_remaining = input[cursor..].chars().count();
+    if chars_remaining > 0 {
+        write!(stdout, "{}", termion::cursor::Left(chars_remaining as u16))
+            .expect("failed to write to `stdout`");
+    }
+
+    stdout.flush().expect("failed to flush `stdout`");
+}
+
+// Wait for a key to be pressed, or for `children` to become readable, which
+// it does when a child process finishes or stops, or the shell catches a
+// signal. Returns whether there's a key to read.
+fn wait_for_key(children: i32) -> bool {
+    let mut fds = [
+        libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: children,
+            events: libc::POLLIN,
+            revents: 0,
+        },
+    ];
+    loop {
+        if unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) } >= 0 {
+            return fds[1].revents & libc::POLLIN == 0;
+        }
+        // A signal, such as SIGCHLD itself, interrupted the wait.
+        if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            return true;
+        }
+    }
+}
+
+// Standard input, read without buffering, so that any input not yet read is
+// still there for `poll` to see.
+struct RawStdin;
+
+impl io::Read for RawStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(n as usize)
+    }
 }
 
 fn prompt() -> io::Result<String> {
@@ -188,6 +381,7 @@ fn tab_and_should_continue(
     cursor: &mut usize,
     stdout: &mut RawTerminal<Stdout>,
     prompt: &str,
+    aliases: &[String],
 ) -> bool {
     let last_char = input.chars().last();
     let current_input = input.clone();
@@ -202,7 +396,7 @@ fn tab_and_should_continue(
         words.is_empty() || (words.len() == 1 && last_char.map_or(false, |c| c != ' '));
 
     let matches = if waiting_for_cmd {
-        check_cmds(&words)
+        check_cmds(&words, aliases)
     } else {
         match check_paths(last_char, &words) {
             Some(v) => v,
@@ -223,14 +417,20 @@ fn tab_and_should_continue(
     }
 }
 
-fn check_cmds(words: &Vec<&str>) -> Vec<String> {
-    let data = &COMMANDS;
+fn check_cmds(words: &Vec<&str>, aliases: &[String]) -> Vec<String> {
+    let mut data = COMMANDS.clone();
+    data.extend(
+        aliases
+            .iter()
+            .filter(|name| !COMMANDS.contains(name))
+            .cloned(),
+    );
     let partial = if words.is_empty() {
         ""
     } else {
         words.last().expect("expected there to be a last word")
     };
-    backtrack::find_matches(data, partial)
+    backtrack::find_matches(&data, partial)
 }
 
 fn check_options(stdout: &mut RawTerminal<Stdout>, prompt: &str, input: &str, cmd: &str) {
@@ -238,6 +438,8 @@ fn check_options(stdout: &mut RawTerminal<Stdout>, prompt: &str, input: &str, cm
     match cmd {
         "rm" => message = rm::OPTIONS_USAGE,
         "ls" => message = ls::OPTIONS_USAGE,
+        "export" => message = export::OPTIONS_USAGE,
+        "shopt" => message = shopt::OPTIONS_USAGE,
         _ => message = "",
     }
     display_usage(stdout, prompt, input, message);
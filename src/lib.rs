@@ -1,10 +1,18 @@
 pub mod ansi;
+pub mod arithmetic;
 pub mod commands;
+pub mod compound;
 pub mod error;
+pub mod expand;
 pub mod fork;
 pub mod input;
+pub mod options;
+pub mod parser;
 pub mod redirect;
 pub mod repl;
+pub mod script;
+pub mod signals;
+pub mod variables;
 
 #[cfg(test)]
 pub mod test_helpers;
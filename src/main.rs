@@ -1,22 +1,39 @@
 use std::env;
 
-use zero_shell::{commands, error, repl};
+use zero_shell::{
+    commands, error, repl,
+    script::{self, Source},
+    signals,
+};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() > 2 {
-        if args[1] == "--internal-worker" {
-            commands::run_command_as_worker(&args);
-        } else {
-            error::red_println("Usage: ./0-shell");
-            return;
+    if args.len() > 2 && args[1] == "--internal-worker" {
+        let code = commands::run_command_as_worker(&args);
+        std::process::exit(code);
+    }
+
+    signals::init();
+
+    let mut invocation = match script::invocation(&args[1..]) {
+        Ok(invocation) => invocation,
+        Err(err) => {
+            error::red_println(&err);
+            std::process::exit(2);
         }
-    } else if args.len() > 1 {
-        // TODO: If I implement scripting, replace this error with e.g.
-        // `run_script(&args[1]);`
-        error::red_println("Usage: ./0-shell");
-        return;
+    };
+
+    // A login shell, e.g. one started by `login`, has a name beginning with
+    // `-`.
+    if args.first().is_some_and(|arg0| arg0.starts_with('-')) {
+        invocation.login = true;
+    }
+
+    // With no script or commands to run, the shell is interactive if it's
+    // reading from a terminal.
+    if invocation.source == Source::Stdin && unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 {
+        repl::repl(invocation);
     } else {
-        repl::repl();
+        std::process::exit(script::run(invocation));
     }
 }
@@ -0,0 +1,71 @@
+// The shell's options, which change how it behaves. Those listed by `shopt`
+// are set and unset by name with `shopt -s` and `shopt -u`. Those with a
+// letter are set with `set -x` and so on, and unset with `set +x`.
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    pub nullglob: bool,       // A pattern that matches nothing expands to nothing.
+    pub failglob: bool,       // A pattern that matches nothing is an error.
+    pub dotglob: bool,        // Patterns match names that start with `.`.
+    pub globstar: bool,       // `**` matches any number of directories.
+    pub expand_aliases: bool, // Aliases are expanded, as they are when interactive.
+    pub huponexit: bool,      // Running jobs are sent SIGHUP when the shell exits.
+    // Job control: each job gets a process group of its own, which is given
+    // the terminal while it's in the foreground. Only the interactive shell
+    // has it, not its subshells.
+    pub monitor: bool,
+    pub errexit: bool, // `-e`: a command that fails ends the shell.
+    pub xtrace: bool,  // `-x`: each command is shown before it runs.
+    pub noexec: bool,  // `-n`: commands are read and parsed but not run.
+    pub notify: bool,  // `-b`: jobs are reported as soon as they finish or stop.
+}
+
+// The options that `shopt` knows about, in the order it lists them.
+pub const SHOPT_NAMES: [&str; 6] = [
+    "dotglob",
+    "expand_aliases",
+    "failglob",
+    "globstar",
+    "huponexit",
+    "nullglob",
+];
+
+impl Options {
+    // Set or unset the option known by the letter `flag`. Returns whether
+    // there is one.
+    pub fn set_flag(&mut self, flag: char, on: bool) -> bool {
+        match flag {
+            'b' => self.notify = on,
+            'e' => self.errexit = on,
+            'n' => self.noexec = on,
+            'x' => self.xtrace = on,
+            _ => return false,
+        }
+        true
+    }
+
+    // The setting of the `shopt` option called `name`, if there is one.
+    pub fn shopt(&self, name: &str) -> Option<bool> {
+        match name {
+            "dotglob" => Some(self.dotglob),
+            "expand_aliases" => Some(self.expand_aliases),
+            "failglob" => Some(self.failglob),
+            "globstar" => Some(self.globstar),
+            "huponexit" => Some(self.huponexit),
+            "nullglob" => Some(self.nullglob),
+            _ => None,
+        }
+    }
+
+    pub fn set_shopt(&mut self, name: &str, on: bool) {
+        match name {
+            "dotglob" => self.dotglob = on,
+            "expand_aliases" => self.expand_aliases = on,
+            "failglob" => self.failglob = on,
+            "globstar" => self.globstar = on,
+            "huponexit" => self.huponexit = on,
+            "nullglob" => self.nullglob = on,
+            _ => {}
+        }
+    }
+}
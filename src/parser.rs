@@ -0,0 +1,1552 @@
+pub mod ast;
+mod lexer;
+
+use std::{collections::BTreeMap, ops::Range, rc::Rc, vec};
+
+use ast::{
+    Assignment, CaseArm, Command, CompoundCommand, Conditional, Connector, Function, List,
+    ListItem, Pipeline, Redirect, RedirectOp, Segment, SimpleCommand, Word,
+};
+use lexer::Token;
+
+use crate::commands::test::{BINARY_OPERATORS, UNARY_OPERATORS};
+
+pub use lexer::is_name;
+
+// Reserved words that start a compound command, and those that end or divide
+// one, which can't start a command at all.
+const COMPOUND_KEYWORDS: [&str; 8] = ["{", "if", "while", "until", "for", "case", "[[", "function"];
+const CLOSING_KEYWORDS: [&str; 8] = ["}", "then", "elif", "else", "fi", "do", "done", "esac"];
+
+// Parse input into a command list: pipelines separated by `;`, `&`, `&&`,
+// `||` or newlines. Any lines after one that introduces here-documents hold
+// their bodies.
+pub fn parse(input: &str) -> Result<List, String> {
+    parse_with_aliases(input, &BTreeMap::new())
+}
+
+// Parse input as `parse` does, replacing the first word of each simple
+// command with its alias, if it has one.
+pub fn parse_with_aliases(input: &str, aliases: &BTreeMap<String, String>) -> Result<List, String> {
+    Parser::new(input, aliases)?.parse()
+}
+
+// Whether input ends partway through a command, as after `if true; then`,
+// `ls &&`, an unterminated quote or a trailing `\`, so that more lines are
+// needed to complete it.
+pub fn is_incomplete(input: &str) -> bool {
+    match lexer::tokenize(input) {
+        Ok(lexed) if lexed.continued => return true,
+        Ok(_) => {}
+        Err(err) => return err.starts_with("Unterminated"),
+    }
+    let aliases = BTreeMap::new();
+    let Ok(mut parser) = Parser::new(input, &aliases) else {
+        return false;
+    };
+    parser.parse().is_err() && parser.position >= parser.tokens.len()
+}
+
+// The delimiters of any here-documents on this line, in order, each paired
+// with whether leading tabs are to be stripped (`<<-`), so that
+// `input::get_input` knows how many lines to read for their bodies.
+pub fn heredoc_delimiters(line: &str) -> Vec<(String, bool)> {
+    lexer::tokenize(line)
+        .map(|lexed| {
+            lexed
+                .pending
+                .into_iter()
+                .map(|heredoc| (heredoc.delimiter, heredoc.strip_tabs))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<(Token, Range<usize>)>,
+    position: usize,
+    heredocs: vec::IntoIter<Word>,
+    aliases: &'a BTreeMap<String, String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, aliases: &'a BTreeMap<String, String>) -> Result<Self, String> {
+        let lexed = lexer::tokenize(input)?;
+        Ok(Parser {
+            input,
+            tokens: lexed.tokens,
+            position: 0,
+            heredocs: lexed.heredocs.into_iter(),
+            aliases,
+        })
+    }
+
+    fn parse(&mut self) -> Result<List, String> {
+        let list = self.list(&[])?;
+        match self.peek() {
+            None => Ok(list),
+            Some(_) => Err(self.error()),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.position += 1;
+        }
+    }
+
+    // The error for an unexpected token, or for input that ends too soon.
+    fn error(&self) -> String {
+        let near = match self.peek() {
+            None | Some(Token::Newline) => String::from("\\n"),
+            Some(Token::Operator(op)) => String::from(*op),
+            Some(Token::Redirect(..)) | Some(Token::Word(_)) | Some(Token::Arithmetic(_)) => {
+                let (_, span) = &self.tokens[self.position];
+                String::from(&self.input[span.clone()])
+            }
+        };
+        format!("Parse error near `{near}'")
+    }
+
+    // Whether the next token ends a command list, being one of `ends`: either
+    // an operator such as `)` or a reserved word such as `}`. Reserved words
+    // are only recognised where a command could start.
+    fn at_end(&self, ends: &[&str]) -> bool {
+        match self.peek() {
+            Some(Token::Operator(op)) => ends.contains(op),
+            Some(token) => is_reserved(token, ends),
+            None => false,
+        }
+    }
+
+    // Consume the operator or reserved word that closes a compound command.
+    fn expect(&mut self, end: &str) -> Result<(), String> {
+        if !self.at_end(&[end]) {
+            return Err(self.error());
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    // A command list, up to the end of the input or the first of `ends`.
+    fn list(&mut self, ends: &[&str]) -> Result<List, String> {
+        let mut list = List::default();
+        let mut connector = Connector::Always;
+
+        self.skip_newlines();
+        while self.peek().is_some() && !self.at_end(ends) {
+            let pipeline = self.pipeline()?;
+            let mut background = false;
+            let mut next_connector = Connector::Always;
+
+            match self.next() {
+                None | Some(Token::Newline) | Some(Token::Operator(";")) => {}
+                Some(Token::Operator("&")) => background = true,
+                Some(Token::Operator("&&")) => next_connector = Connector::And,
+                Some(Token::Operator("||")) => next_connector = Connector::Or,
+                Some(_) => {
+                    self.position -= 1;
+                    if !self.at_end(ends) {
+                        return Err(self.error());
+                    }
+                }
+            }
+
+            list.items.push(ListItem {
+                connector,
+                pipeline,
+                background,
+            });
+            connector = next_connector;
+
+            self.skip_newlines();
+            if connector != Connector::Always && (self.peek().is_none() || self.at_end(ends)) {
+                return Err(self.error());
+            }
+        }
+
+        Ok(list)
+    }
+
+    fn pipeline(&mut self) -> Result<Pipeline, String> {
+        let start = self.position;
+        let mut commands = vec![self.command()?];
+
+        while self.peek() == Some(&Token::Operator("|")) {
+            self.position += 1;
+            self.skip_newlines();
+            commands.push(self.command()?);
+        }
+
+        Ok(Pipeline {
+            commands,
+            text: self.text_since(start),
+        })
+    }
+
+    // The input from the token at `start` up to the last one read.
+    fn text_since(&self, start: usize) -> String {
+        let first = self.tokens[start].1.start;
+        let last = self.tokens[self.position - 1].1.end;
+        String::from(self.input[first..last].trim_end())
+    }
+
+    fn command(&mut self) -> Result<Command, String> {
+        self.expand_alias(self.position)?;
+        let keyword = match self.peek() {
+            Some(Token::Operator("(")) => "(",
+            Some(Token::Word(_))
+                if self.tokens.get(self.position + 1).map(|(token, _)| token)
+                    == Some(&Token::Operator("("))
+                    && self.tokens.get(self.position + 2).map(|(token, _)| token)
+                        == Some(&Token::Operator(")")) =>
+            {
+                return self.function(false);
+            }
+            Some(token) => match reserved_word(token, &COMPOUND_KEYWORDS) {
+                Some(keyword) => keyword,
+                None if is_reserved(token, &CLOSING_KEYWORDS) => return Err(self.error()),
+                None => return Ok(Command::Simple(self.simple_command()?)),
+            },
+            None => return Err(self.error()),
+        };
+        self.position += 1;
+
+        let body = match keyword {
+            "(" => CompoundCommand::Subshell(self.body(&[")"])?),
+            "{" => CompoundCommand::Group(self.body(&["}"])?),
+            "if" => self.if_command()?,
+            "while" | "until" => CompoundCommand::Loop {
+                condition: self.body(&["do"])?,
+                body: self.body(&["done"])?,
+                until: keyword == "until",
+            },
+            "for" => self.for_command()?,
+            "case" => self.case_command()?,
+            "[[" => {
+                let expression = self.conditional_or()?;
+                self.expect("]]")?;
+                CompoundCommand::Conditional(expression)
+            }
+            _ => {
+                self.position -= 1;
+                return self.function(true);
+            }
+        };
+
+        let mut redirects = Vec::new();
+        while let Some(&Token::Redirect(fd, op)) = self.peek() {
+            self.position += 1;
+            redirects.push(self.redirect(fd, op)?);
+        }
+        Ok(Command::Compound { body, redirects })
+    }
+
+    // Replace the word at `index` with the tokens of its alias, if it has one,
+    // and so on for the first of those, unless it's an alias that's already
+    // been replaced, as in `alias ls='ls -F'`. If an alias ends with a blank,
+    // the word after it is replaced too. The tokens take the place of the
+    // word in the input, for error messages and the text of the pipeline.
+    fn expand_alias(&mut self, index: usize) -> Result<(), String> {
+        let mut expanded = Vec::new();
+        while let Some(name) = self.alias_name(index)
+            && !expanded.contains(&name)
+        {
+            let value = &self.aliases[&name];
+            let span = self.tokens[index].1.clone();
+            let tokens: Vec<(Token, Range<usize>)> = lexer::tokenize(value)?
+                .tokens
+                .into_iter()
+                .map(|(token, _)| (token, span.clone()))
+                .collect();
+            let count = tokens.len();
+            self.tokens.splice(index..=index, tokens);
+
+            if value.ends_with([' ', '\t']) {
+                self.expand_alias(index + count)?;
+            }
+            expanded.push(name);
+        }
+        Ok(())
+    }
+
+    // The name of the word at `index`, if it's an unquoted word with an alias
+    // and not a reserved word.
+    fn alias_name(&self, index: usize) -> Option<String> {
+        let (token, _) = self.tokens.get(index)?;
+        let Token::Word(Word(segments)) = token else {
+            return None;
+        };
+        match segments.as_slice() {
+            [Segment::Literal(name)]
+                if self.aliases.contains_key(name)
+                    && !is_reserved(token, &COMPOUND_KEYWORDS)
+                    && !is_reserved(token, &CLOSING_KEYWORDS) =>
+            {
+                Some(name.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // The expression of `[[ ... ]]`, in which `||` binds less tightly than
+    // `&&`, and `&&` less tightly than `!`. A newline may follow either.
+    fn conditional_or(&mut self) -> Result<Conditional, String> {
+        let mut expression = self.conditional_and()?;
+        while self.peek() == Some(&Token::Operator("||")) {
+            self.position += 1;
+            self.skip_newlines();
+            let right = self.conditional_and()?;
+            expression = Conditional::Or(Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn conditional_and(&mut self) -> Result<Conditional, String> {
+        let mut expression = self.conditional_not()?;
+        while self.peek() == Some(&Token::Operator("&&")) {
+            self.position += 1;
+            self.skip_newlines();
+            let right = self.conditional_not()?;
+            expression = Conditional::And(Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn conditional_not(&mut self) -> Result<Conditional, String> {
+        if self.peek().is_some_and(|token| is_reserved(token, &["!"])) {
+            self.position += 1;
+            return Ok(Conditional::Not(Box::new(self.conditional_not()?)));
+        }
+        self.conditional_primary()
+    }
+
+    fn conditional_primary(&mut self) -> Result<Conditional, String> {
+        if self.peek() == Some(&Token::Operator("(")) {
+            self.position += 1;
+            let expression = self.conditional_or()?;
+            self.expect(")")?;
+            return Ok(expression);
+        }
+
+        let first = self.conditional_word()?;
+        if let Some(op) = self.conditional_operator(&BINARY_OPERATORS) {
+            let right = if op == "=~" {
+                self.regex_word()?
+            } else {
+                self.conditional_word()?
+            };
+            return Ok(Conditional::Binary {
+                left: first,
+                op,
+                right,
+            });
+        }
+
+        match reserved_word(&Token::Word(first.clone()), &UNARY_OPERATORS) {
+            Some(op) if !self.at_end(&["]]", "&&", "||", ")"]) => Ok(Conditional::Unary {
+                op: String::from(op),
+                operand: self.conditional_word()?,
+            }),
+            _ => Ok(Conditional::Word(first)),
+        }
+    }
+
+    // An operand in `[[ ... ]]`, which can't be `]]`.
+    fn conditional_word(&mut self) -> Result<Word, String> {
+        match self.peek() {
+            Some(Token::Word(word)) if !self.at_end(&["]]"]) => {
+                let word = word.clone();
+                self.position += 1;
+                Ok(word)
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    // The next token, if it's one of `operators`, where `<` and `>` compare
+    // strings rather than redirecting.
+    fn conditional_operator(&mut self, operators: &[&str]) -> Option<String> {
+        let op = match self.tokens.get(self.position)? {
+            (Token::Redirect(None, _), span) => &self.input[span.clone()],
+            (token, _) => reserved_word(token, operators)?,
+        };
+        if !operators.contains(&op) {
+            return None;
+        }
+        self.position += 1;
+        Some(String::from(op))
+    }
+
+    // The regular expression after `=~`, which may contain characters such as
+    // `|` and `(` that would otherwise end a word. It runs up to a blank
+    // that's not inside parentheses, or to `&&`, `||` or an unmatched `)`.
+    fn regex_word(&mut self) -> Result<Word, String> {
+        if self.at_end(&["]]"]) || matches!(self.peek(), None | Some(Token::Newline)) {
+            return Err(self.error());
+        }
+
+        let mut segments = Vec::new();
+        let mut depth = 0;
+        let mut end: Option<usize> = None;
+        while let Some((token, span)) = self.tokens.get(self.position) {
+            if let Some(end) = end
+                && end != span.start
+            {
+                // A separate `]]` ends the expression even inside parentheses,
+                // for the regular expression to be found invalid.
+                if depth == 0 || self.at_end(&["]]"]) {
+                    break;
+                }
+                push_segment(
+                    &mut segments,
+                    Segment::Literal(String::from(&self.input[end..span.start])),
+                );
+            }
+            match token {
+                Token::Word(Word(word)) => {
+                    for segment in word {
+                        push_segment(&mut segments, segment.clone());
+                    }
+                }
+                Token::Newline => return Err(self.error()),
+                Token::Operator("&&" | "||") if depth == 0 => break,
+                Token::Operator(")") if depth == 0 => break,
+                _ => {
+                    match token {
+                        Token::Operator("(") => depth += 1,
+                        Token::Operator(")") => depth -= 1,
+                        _ => {}
+                    }
+                    let text = String::from(&self.input[span.clone()]);
+                    push_segment(&mut segments, Segment::Literal(text));
+                }
+            }
+            end = Some(span.end);
+            self.position += 1;
+        }
+        Ok(Word(segments))
+    }
+
+    // A function definition: `name() body`, or, with the keyword,
+    // `function name [()] body`, where the body is a compound command.
+    fn function(&mut self, keyword: bool) -> Result<Command, String> {
+        if keyword {
+            self.position += 1;
+        }
+        let name = match self.peek() {
+            Some(Token::Word(Word(segments))) => match segments.as_slice() {
+                [Segment::Literal(name)] if !name.contains('=') => name.clone(),
+                _ => return Err(self.error()),
+            },
+            _ => return Err(self.error()),
+        };
+        self.position += 1;
+
+        if !keyword || self.peek() == Some(&Token::Operator("(")) {
+            self.expect("(")?;
+            self.expect(")")?;
+        }
+
+        self.skip_newlines();
+        let start = self.position;
+        let body = self.command()?;
+        if !matches!(body, Command::Compound { .. }) {
+            self.position = start;
+            return Err(self.error());
+        }
+
+        Ok(Command::Function(Rc::new(Function {
+            name,
+            body: Pipeline {
+                commands: vec![body],
+                text: self.text_since(start),
+            },
+        })))
+    }
+
+    // A command list inside a compound command, which mustn't be empty, up to
+    // and including the token that closes it, which is one of `ends`. Returns
+    // the list and the position of the token in `ends`.
+    fn body_until(&mut self, ends: &[&str]) -> Result<(List, usize), String> {
+        let list = self.list(ends)?;
+        let found = ends.iter().position(|&end| self.at_end(&[end]));
+        match found {
+            Some(index) if !list.items.is_empty() => {
+                self.position += 1;
+                Ok((list, index))
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn body(&mut self, end: &[&str]) -> Result<List, String> {
+        self.body_until(end).map(|(list, _)| list)
+    }
+
+    // `if list; then list; [elif list; then list;]... [else list;] fi`, after
+    // the `if`.
+    fn if_command(&mut self) -> Result<CompoundCommand, String> {
+        let mut branches = Vec::new();
+        loop {
+            let condition = self.body(&["then"])?;
+            let (body, end) = self.body_until(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+            match end {
+                0 => continue,
+                1 => {
+                    let otherwise = self.body(&["fi"])?;
+                    return Ok(CompoundCommand::If {
+                        branches,
+                        otherwise: Some(otherwise),
+                    });
+                }
+                _ => {
+                    return Ok(CompoundCommand::If {
+                        branches,
+                        otherwise: None,
+                    });
+                }
+            }
+        }
+    }
+
+    // `for name [in word...]; do list; done` or
+    // `for ((init; condition; step)); do list; done`, after the `for`.
+    fn for_command(&mut self) -> Result<CompoundCommand, String> {
+        if let Some(Token::Arithmetic(_)) = self.peek()
+            && let Some(Token::Arithmetic(expression)) = self.next()
+        {
+            let Ok([init, condition, step]) = <[Word; 3]>::try_from(split(expression, ';')) else {
+                self.position -= 1;
+                return Err(self.error());
+            };
+            self.end_of_header()?;
+            return Ok(CompoundCommand::ArithmeticFor {
+                init,
+                condition,
+                step,
+                body: self.do_body()?,
+            });
+        }
+
+        let name = match self.peek() {
+            Some(Token::Word(Word(segments))) => match segments.as_slice() {
+                [Segment::Literal(name)] if is_name(name) => name.clone(),
+                _ => return Err(self.error()),
+            },
+            _ => return Err(self.error()),
+        };
+        self.position += 1;
+
+        self.skip_newlines();
+        let mut words = None;
+        if self.peek().is_some_and(|token| is_reserved(token, &["in"])) {
+            self.position += 1;
+            let mut list = Vec::new();
+            while let Some(Token::Word(_)) = self.peek() {
+                if let Some(Token::Word(word)) = self.next() {
+                    list.push(word);
+                }
+            }
+            words = Some(list);
+        }
+        self.end_of_header()?;
+
+        Ok(CompoundCommand::For {
+            name,
+            words,
+            body: self.do_body()?,
+        })
+    }
+
+    // The `;` or newline that ends the first line of a `for` command, which
+    // may be left out before `do`.
+    fn end_of_header(&mut self) -> Result<(), String> {
+        match self.peek() {
+            Some(Token::Operator(";")) | Some(Token::Newline) => self.position += 1,
+            Some(token) if is_reserved(token, &["do"]) => {}
+            _ => return Err(self.error()),
+        }
+        self.skip_newlines();
+        Ok(())
+    }
+
+    fn do_body(&mut self) -> Result<List, String> {
+        self.expect("do")?;
+        self.body(&["done"])
+    }
+
+    // `case word in [(]pattern [| pattern]...) list;; ... esac`, after the
+    // `case`. The `;;` may be left out of the last arm.
+    fn case_command(&mut self) -> Result<CompoundCommand, String> {
+        let Some(Token::Word(word)) = self.peek().cloned() else {
+            return Err(self.error());
+        };
+        self.position += 1;
+        self.skip_newlines();
+        self.expect("in")?;
+        self.skip_newlines();
+
+        let mut arms = Vec::new();
+        while !self.at_end(&["esac"]) {
+            if self.peek() == Some(&Token::Operator("(")) {
+                self.position += 1;
+            }
+            let mut patterns = Vec::new();
+            loop {
+                let Some(Token::Word(pattern)) = self.peek().cloned() else {
+                    return Err(self.error());
+                };
+                self.position += 1;
+                patterns.push(pattern);
+                match self.next() {
+                    Some(Token::Operator("|")) => continue,
+                    Some(Token::Operator(")")) => break,
+                    _ => {
+                        self.position -= 1;
+                        return Err(self.error());
+                    }
+                }
+            }
+
+            let body = self.list(&[";;", "esac"])?;
+            arms.push(CaseArm { patterns, body });
+            if self.peek() == Some(&Token::Operator(";;")) {
+                self.position += 1;
+                self.skip_newlines();
+            } else if !self.at_end(&["esac"]) {
+                return Err(self.error());
+            }
+        }
+        self.position += 1;
+
+        Ok(CompoundCommand::Case { word, arms })
+    }
+
+    fn simple_command(&mut self) -> Result<SimpleCommand, String> {
+        let mut command = SimpleCommand {
+            assignments: Vec::new(),
+            words: Vec::new(),
+            redirects: Vec::new(),
+        };
+
+        // `((...))` is short for `let "..."`, and can only be followed by
+        // redirections.
+        let mut is_arithmetic = false;
+
+        loop {
+            match self.peek() {
+                Some(Token::Arithmetic(_))
+                    if command.words.is_empty() && command.assignments.is_empty() =>
+                {
+                    if let Some(Token::Arithmetic(mut expression)) = self.next() {
+                        if expression.0.is_empty() {
+                            expression.0.push(Segment::Quoted(String::new()));
+                        }
+                        command.words = vec![
+                            Word(vec![Segment::Literal(String::from("let"))]),
+                            expression,
+                        ];
+                        is_arithmetic = true;
+                    }
+                }
+                Some(Token::Word(_)) if is_arithmetic => return Err(self.error()),
+                Some(Token::Word(_)) => {
+                    if let Some(Token::Word(word)) = self.next() {
+                        // Assignments can only come before the command name.
+                        if !command.words.is_empty() {
+                            command.words.push(word);
+                        } else {
+                            match assignment(word) {
+                                Ok(assignment) => command.assignments.push(assignment),
+                                Err(word) => command.words.push(word),
+                            }
+                        }
+                    }
+                }
+                Some(&Token::Redirect(fd, op)) => {
+                    self.position += 1;
+                    let redirect = self.redirect(fd, op)?;
+                    command.redirects.push(redirect);
+                }
+                _ => break,
+            }
+        }
+
+        if command.assignments.is_empty()
+            && command.words.is_empty()
+            && command.redirects.is_empty()
+        {
+            return Err(self.error());
+        }
+
+        Ok(command)
+    }
+
+    // The target of a redirection whose operator has just been read.
+    fn redirect(&mut self, fd: Option<i32>, op: RedirectOp) -> Result<Redirect, String> {
+        let Some(Token::Word(word)) = self.peek().cloned() else {
+            return Err(self.error());
+        };
+        self.position += 1;
+
+        let target = if op == RedirectOp::HereDoc {
+            self.heredocs.next().unwrap_or(Word(Vec::new()))
+        } else {
+            word
+        };
+        Ok(Redirect { fd, op, target })
+    }
+}
+
+// Which of the reserved `words` a token is, if any. It can only be one if it's
+// unquoted and a word of its own.
+fn reserved_word<'a>(token: &Token, words: &[&'a str]) -> Option<&'a str> {
+    match token {
+        Token::Word(Word(segments)) => match segments.as_slice() {
+            [Segment::Literal(text)] => words.iter().copied().find(|word| word == text),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_reserved(token: &Token, words: &[&str]) -> bool {
+    reserved_word(token, words).is_some()
+}
+
+// Split a word at each unquoted or quoted `separator`, for the expression of
+// `for ((...))`, which the lexer treats as quoted text.
+fn split(word: Word, separator: char) -> Vec<Word> {
+    let mut words = vec![Word(Vec::new())];
+    for segment in word.0 {
+        let text = match &segment {
+            Segment::Literal(text) | Segment::Quoted(text) => text,
+            _ => {
+                words.last_mut().unwrap().0.push(segment);
+                continue;
+            }
+        };
+        for (i, part) in text.split(separator).enumerate() {
+            if i > 0 {
+                words.push(Word(Vec::new()));
+            }
+            if !part.is_empty() {
+                let part = String::from(part);
+                words.last_mut().unwrap().0.push(match segment {
+                    Segment::Literal(_) => Segment::Literal(part),
+                    _ => Segment::Quoted(part),
+                });
+            }
+        }
+    }
+    words
+}
+
+// A word is an assignment if it starts with an unquoted `NAME=`.
+fn assignment(word: Word) -> Result<Assignment, Word> {
+    let Some(Segment::Literal(text)) = word.0.first() else {
+        return Err(word);
+    };
+    let Some((name, rest)) = text.split_once('=') else {
+        return Err(word);
+    };
+    if !is_name(name) {
+        return Err(word);
+    }
+
+    let name = String::from(name);
+    let mut value = Vec::new();
+    if !rest.is_empty() {
+        value.push(Segment::Literal(String::from(rest)));
+    }
+    value.extend(word.0.into_iter().skip(1));
+
+    Ok(Assignment {
+        name,
+        value: Word(value),
+    })
+}
+
+// Add a segment to a word, joining it to the one before if both are literal.
+fn push_segment(segments: &mut Vec<Segment>, segment: Segment) {
+    if let (Some(Segment::Literal(last)), Segment::Literal(text)) = (segments.last_mut(), &segment)
+    {
+        last.push_str(text);
+    } else {
+        segments.push(segment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{
+        ast::{
+            Assignment, Command, CompoundCommand, Conditional, Connector, List, ParamOp, Redirect,
+            RedirectOp, ReplaceMode, Segment, SimpleCommand, Word,
+        },
+        heredoc_delimiters, is_incomplete, parse, parse_with_aliases,
+    };
+    use crate::test_helpers::simple_command;
+
+    fn literal(s: &str) -> Word {
+        Word(vec![Segment::Literal(String::from(s))])
+    }
+
+    fn quoted(s: &str) -> Word {
+        Word(vec![Segment::Quoted(String::from(s))])
+    }
+
+    fn parameter(name: &str, quoted: bool) -> Segment {
+        Segment::Parameter {
+            name: String::from(name),
+            op: ParamOp::Value,
+            quoted,
+        }
+    }
+
+    // The words of the only command on the line.
+    fn words(input: &str) -> Vec<Word> {
+        let list = parse(input).expect("input should parse");
+        assert_eq!(list.items.len(), 1, "expected one command in {input:?}");
+        assert_eq!(list.items[0].pipeline.commands.len(), 1);
+        simple_command(&list, 0, 0).words.clone()
+    }
+
+    fn only_command(list: &List) -> &SimpleCommand {
+        simple_command(list, 0, 0)
+    }
+
+    #[test]
+    fn test_parse_words() {
+        assert_eq!(parse(""), Ok(List::default()));
+        assert_eq!(
+            words("echo  foo\tbar"),
+            vec![literal("echo"), literal("foo"), literal("bar")]
+        );
+    }
+
+    #[test]
+    fn test_parse_quotes() {
+        assert_eq!(
+            words(r#"cat "my file" 'a b'"#),
+            vec![literal("cat"), quoted("my file"), quoted("a b")]
+        );
+        assert_eq!(
+            words(r#""a"b'c'd"#),
+            vec![Word(vec![
+                Segment::Quoted(String::from("a")),
+                Segment::Literal(String::from("b")),
+                Segment::Quoted(String::from("c")),
+                Segment::Literal(String::from("d")),
+            ])],
+            "adjacent quoted and unquoted text should be one word"
+        );
+        assert_eq!(
+            words(r#"'' "" 'it''s'"#),
+            vec![quoted(""), quoted(""), quoted("its")],
+            "empty quotes should still make words"
+        );
+        assert_eq!(
+            words(r#"echo '|' ">" "a;b" 'x&&y'"#),
+            vec![
+                literal("echo"),
+                quoted("|"),
+                quoted(">"),
+                quoted("a;b"),
+                quoted("x&&y")
+            ],
+            "quoted operators should be ordinary words"
+        );
+        assert!(parse("echo 'oops").is_err());
+        assert!(parse("echo \"oops").is_err());
+    }
+
+    #[test]
+    fn test_parse_backslashes() {
+        assert_eq!(
+            words(r"a\ b a\\na a\na"),
+            vec![
+                Word(vec![
+                    Segment::Literal(String::from("a")),
+                    Segment::Quoted(String::from(" ")),
+                    Segment::Literal(String::from("b")),
+                ]),
+                Word(vec![
+                    Segment::Literal(String::from("a")),
+                    Segment::Quoted(String::from("\\")),
+                    Segment::Literal(String::from("na")),
+                ]),
+                Word(vec![
+                    Segment::Literal(String::from("a")),
+                    Segment::Quoted(String::from("n")),
+                    Segment::Literal(String::from("a")),
+                ]),
+            ]
+        );
+        assert_eq!(
+            words(r#""a\na" "\$\"\\" '\n'"#),
+            vec![quoted("a\\na"), quoted("$\"\\"), quoted("\\n")],
+            "in double quotes, a backslash should only escape `$`, `` ` ``, `\"` and `\\`"
+        );
+        assert_eq!(words("a\\\nb"), vec![literal("ab")], "line continuation");
+    }
+
+    #[test]
+    fn test_parse_parameters() {
+        assert_eq!(
+            words(r#"$HOME "${USER}x" '$PATH' \$TERM a$"#),
+            vec![
+                Word(vec![parameter("HOME", false)]),
+                Word(vec![
+                    parameter("USER", true),
+                    Segment::Quoted(String::from("x")),
+                ]),
+                quoted("$PATH"),
+                Word(vec![
+                    Segment::Quoted(String::from("$")),
+                    Segment::Literal(String::from("TERM")),
+                ]),
+                literal("a$"),
+            ]
+        );
+        assert_eq!(
+            words("$? $12 ${12} ${#} $@"),
+            vec![
+                Word(vec![parameter("?", false)]),
+                Word(vec![
+                    parameter("1", false),
+                    Segment::Literal(String::from("2")),
+                ]),
+                Word(vec![parameter("12", false)]),
+                Word(vec![parameter("#", false)]),
+                Word(vec![parameter("@", false)]),
+            ],
+            "special and positional parameters"
+        );
+        assert!(parse("echo ${a b}").is_err(), "bad substitution");
+    }
+
+    #[test]
+    fn test_parse_command_substitution() {
+        let command = |input: &str, quoted: bool| Segment::Command {
+            list: parse(input).unwrap(),
+            quoted,
+        };
+
+        assert_eq!(
+            words(r#"$(dirname $(pwd)) "x`echo \`a\` \"b\"`" $(echo ")"; (a))"#),
+            vec![
+                Word(vec![command("dirname $(pwd)", false)]),
+                Word(vec![
+                    Segment::Quoted(String::from("x")),
+                    command(r#"echo `a` "b""#, true),
+                ]),
+                Word(vec![command(r#"echo ")"; (a)"#, false)]),
+            ]
+        );
+        assert!(parse("echo $(echo").is_err());
+        assert!(parse("echo `echo").is_err());
+        assert!(parse("echo $(|)").is_err(), "the inner list should parse");
+    }
+
+    #[test]
+    fn test_parse_arithmetic() {
+        assert_eq!(
+            words(r#"$((1 + (2 * $x))) "$((y))""#),
+            vec![
+                Word(vec![Segment::Arithmetic {
+                    expression: Word(vec![
+                        Segment::Quoted(String::from("1 + (2 * ")),
+                        parameter("x", true),
+                        Segment::Quoted(String::from(")")),
+                    ]),
+                    quoted: false,
+                }]),
+                Word(vec![Segment::Arithmetic {
+                    expression: quoted("y"),
+                    quoted: true,
+                }]),
+            ]
+        );
+        assert_eq!(
+            words("((x += 1)) > /dev/null"),
+            vec![literal("let"), quoted("x += 1")],
+            "`((...))` should be short for `let`"
+        );
+        assert!(parse("(( 1 )) 2").is_err());
+        assert!(parse("echo $((1 + 2)").is_err());
+    }
+
+    #[test]
+    fn test_parse_parameter_operators() {
+        let operator = |input: &str| match &words(input)[0].0[..] {
+            [Segment::Parameter { op, .. }] => op.clone(),
+            other => panic!("expected one parameter in {input:?}, got {other:?}"),
+        };
+
+        assert_eq!(operator("${#HOME}"), ParamOp::Length);
+        assert_eq!(
+            operator("${X:-a b}"),
+            ParamOp::Default {
+                word: literal("a b"),
+                null: true
+            },
+            "the word may contain spaces"
+        );
+        assert_eq!(
+            operator("${X=$Y}"),
+            ParamOp::Assign {
+                word: Word(vec![parameter("Y", false)]),
+                null: false
+            }
+        );
+        assert_eq!(
+            operator("${X:?'no }'}"),
+            ParamOp::Error {
+                message: quoted("no }"),
+                null: true
+            }
+        );
+        assert_eq!(
+            operator("${X:+${Y:-z}}"),
+            ParamOp::Alternative {
+                word: Word(vec![Segment::Parameter {
+                    name: String::from("Y"),
+                    op: ParamOp::Default {
+                        word: literal("z"),
+                        null: true
+                    },
+                    quoted: false,
+                }]),
+                null: true
+            },
+            "parameters should nest"
+        );
+        assert_eq!(
+            operator("${X##*/}"),
+            ParamOp::RemovePrefix {
+                pattern: literal("*/"),
+                longest: true
+            }
+        );
+        assert_eq!(
+            operator("${X%.*}"),
+            ParamOp::RemoveSuffix {
+                pattern: literal(".*"),
+                longest: false
+            }
+        );
+        assert_eq!(
+            operator("${X//a/b c}"),
+            ParamOp::Replace {
+                pattern: literal("a"),
+                replacement: literal("b c"),
+                mode: ReplaceMode::All
+            }
+        );
+        assert_eq!(
+            operator("${X/#a}"),
+            ParamOp::Replace {
+                pattern: literal("a"),
+                replacement: Word(Vec::new()),
+                mode: ReplaceMode::Prefix
+            }
+        );
+
+        assert_eq!(
+            parse("echo ${X:2}"),
+            Err(String::from("Bad substitution: ${X:2}"))
+        );
+        assert_eq!(
+            parse("echo ${X:-oops"),
+            Err(String::from("Unterminated parameter: ${"))
+        );
+    }
+
+    #[test]
+    fn test_parse_assignments() {
+        let list = parse("A=1 B=$X'y' >out cmd C=2").expect("assignments should parse");
+        let command = only_command(&list);
+        assert_eq!(
+            command.assignments,
+            vec![
+                Assignment {
+                    name: String::from("A"),
+                    value: literal("1"),
+                },
+                Assignment {
+                    name: String::from("B"),
+                    value: Word(vec![
+                        parameter("X", false),
+                        Segment::Quoted(String::from("y"))
+                    ]),
+                },
+            ]
+        );
+        assert_eq!(
+            command.words,
+            vec![literal("cmd"), literal("C=2")],
+            "only words before the command name should be assignments"
+        );
+
+        let list = parse("EMPTY= '1A=1' 1A=1").unwrap();
+        let command = only_command(&list);
+        assert_eq!(command.assignments[0].value, Word(Vec::new()));
+        assert_eq!(
+            command.words,
+            vec![quoted("1A=1"), literal("1A=1")],
+            "quoted words and invalid names shouldn't be assignments"
+        );
+    }
+
+    #[test]
+    fn test_parse_comments() {
+        assert_eq!(
+            words("echo a#b # comment"),
+            vec![literal("echo"), literal("a#b")]
+        );
+    }
+
+    #[test]
+    fn test_parse_pipeline() {
+        let list = parse("ls -l|grep foo | wc -l > out").expect("pipeline should parse");
+        let pipeline = &list.items[0].pipeline;
+        assert_eq!(pipeline.commands.len(), 3);
+        assert_eq!(pipeline.text, "ls -l|grep foo | wc -l > out");
+        assert_eq!(
+            simple_command(&list, 0, 2).words,
+            vec![literal("wc"), literal("-l")]
+        );
+        assert!(parse("ls |").is_err(), "a trailing `|` should be an error");
+        assert!(parse("| ls").is_err(), "a leading `|` should be an error");
+    }
+
+    #[test]
+    fn test_parse_command_list() {
+        let list = parse("cd src&&ls||pwd; sleep 1& echo done;\nfalse\n").unwrap();
+        let summary: Vec<(Connector, String, bool)> = list
+            .items
+            .iter()
+            .map(|item| (item.connector, item.pipeline.text.clone(), item.background))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Connector::Always, String::from("cd src"), false),
+                (Connector::And, String::from("ls"), false),
+                (Connector::Or, String::from("pwd"), false),
+                (Connector::Always, String::from("sleep 1"), true),
+                (Connector::Always, String::from("echo done"), false),
+                (Connector::Always, String::from("false"), false),
+            ]
+        );
+
+        assert_eq!(
+            parse("; ls"),
+            Err(String::from("Parse error near `;'")),
+            "a leading `;` should be an error"
+        );
+        assert_eq!(
+            parse("ls && || pwd"),
+            Err(String::from("Parse error near `||'"))
+        );
+        assert_eq!(parse("ls &&"), Err(String::from("Parse error near `\\n'")));
+        assert!(parse("ls &&\npwd").is_ok(), "a newline may follow `&&`");
+        assert!(parse("ls &").is_ok(), "a trailing `&` should be ok");
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        let list = parse("(cd build && make) | wc; { pwd; ls; } > listing").unwrap();
+        assert_eq!(list.items.len(), 2);
+        assert_eq!(list.items[0].pipeline.text, "(cd build && make) | wc");
+
+        let Command::Compound {
+            body: CompoundCommand::Subshell(inner),
+            redirects,
+        } = &list.items[0].pipeline.commands[0]
+        else {
+            panic!("expected a subshell");
+        };
+        assert_eq!(inner.items.len(), 2);
+        assert_eq!(inner.items[1].connector, Connector::And);
+        assert!(redirects.is_empty());
+        assert_eq!(simple_command(&list, 0, 1).words, vec![literal("wc")]);
+
+        let Command::Compound {
+            body: CompoundCommand::Group(inner),
+            redirects,
+        } = &list.items[1].pipeline.commands[0]
+        else {
+            panic!("expected a brace group");
+        };
+        assert_eq!(simple_command(inner, 1, 0).words, vec![literal("ls")]);
+        assert_eq!(redirects[0].target, literal("listing"));
+
+        assert_eq!(
+            words("echo { } a}"),
+            vec![literal("echo"), literal("{"), literal("}"), literal("a}")],
+            "braces are only special where a command could start"
+        );
+        assert!(
+            parse("((echo a); (echo b))").is_err(),
+            "`((` starts arithmetic"
+        );
+        assert!(parse("( (echo a); { echo b; } )").is_ok());
+        assert!(
+            parse("{ echo a }").is_err(),
+            "a closing brace needs a `;` before it"
+        );
+        assert_eq!(parse("( )"), Err(String::from("Parse error near `)'")));
+        assert_eq!(
+            parse("{ ls; } }"),
+            Err(String::from("Parse error near `}'"))
+        );
+        assert_eq!(
+            parse("(ls) pwd"),
+            Err(String::from("Parse error near `pwd'"))
+        );
+        assert_eq!(parse("echo )"), Err(String::from("Parse error near `)'")));
+    }
+
+    #[test]
+    fn test_parse_control_flow() {
+        let compound = |input: &str| match parse(input) {
+            Ok(list) => match &list.items[0].pipeline.commands[0] {
+                Command::Compound { body, .. } => body.clone(),
+                command => panic!("expected a compound command, found {command:?}"),
+            },
+            Err(err) => panic!("{input:?} should parse: {err}"),
+        };
+
+        let CompoundCommand::If {
+            branches,
+            otherwise,
+        } = compound("if a; then b; elif c\nthen d; else e; fi")
+        else {
+            panic!("expected `if`");
+        };
+        assert_eq!(branches.len(), 2);
+        assert!(otherwise.is_some());
+
+        assert!(matches!(
+            compound("until a; do b; done"),
+            CompoundCommand::Loop { until: true, .. }
+        ));
+
+        let CompoundCommand::For {
+            name,
+            words: values,
+            ..
+        } = compound("for f in *.rs $x\ndo cat $f; done")
+        else {
+            panic!("expected `for`");
+        };
+        assert_eq!((name.as_str(), values.map(|v| v.len())), ("f", Some(2)));
+        assert!(matches!(
+            compound("for f do echo $f; done"),
+            CompoundCommand::For { words: None, .. }
+        ));
+
+        let CompoundCommand::ArithmeticFor {
+            init, condition, ..
+        } = compound("for ((i = 0; ; i++)); do echo; done")
+        else {
+            panic!("expected `for ((...))`");
+        };
+        assert_eq!(init, quoted("i = 0"));
+        assert_eq!(condition, quoted(" "));
+
+        let CompoundCommand::Case { word, arms } =
+            compound("case $x in\n(a|b) echo ab;;\n*) ;;\nc) echo c\nesac")
+        else {
+            panic!("expected `case`");
+        };
+        assert_eq!(word, Word(vec![parameter("x", false)]));
+        assert_eq!(arms.len(), 3);
+        assert_eq!(arms[0].patterns, vec![literal("a"), literal("b")]);
+        assert!(arms[1].body.items.is_empty());
+
+        assert_eq!(
+            words("echo if then fi done"),
+            vec![
+                literal("echo"),
+                literal("if"),
+                literal("then"),
+                literal("fi"),
+                literal("done")
+            ],
+            "reserved words are only special where a command could start"
+        );
+        assert_eq!(parse("fi"), Err(String::from("Parse error near `fi'")));
+        assert_eq!(parse("if; then"), Err(String::from("Parse error near `;'")));
+        assert_eq!(
+            parse("while a; do done"),
+            Err(String::from("Parse error near `done'"))
+        );
+        assert!(parse("for 1 in a; do b; done").is_err());
+        assert!(parse("for ((i)); do b; done").is_err());
+        assert!(parse("case a in a) b;; c;; esac").is_err());
+    }
+
+    #[test]
+    fn test_parse_conditionals() {
+        let conditional = |input: &str| match parse(input) {
+            Ok(list) => match &list.items[0].pipeline.commands[0] {
+                Command::Compound {
+                    body: CompoundCommand::Conditional(expression),
+                    ..
+                } => expression.clone(),
+                command => panic!("expected `[[`, found {command:?}"),
+            },
+            Err(err) => panic!("{input:?} should parse: {err}"),
+        };
+        let binary = |left: &str, op: &str, right: Word| Conditional::Binary {
+            left: literal(left),
+            op: String::from(op),
+            right,
+        };
+
+        assert_eq!(
+            conditional("[[ ! -f a || b < c && d ]]"),
+            Conditional::Or(
+                Box::new(Conditional::Not(Box::new(Conditional::Unary {
+                    op: String::from("-f"),
+                    operand: literal("a"),
+                }))),
+                Box::new(Conditional::And(
+                    Box::new(binary("b", "<", literal("c"))),
+                    Box::new(Conditional::Word(literal("d"))),
+                )),
+            )
+        );
+        assert_eq!(
+            conditional("[[ ( x == y* ) ]]"),
+            binary("x", "==", literal("y*"))
+        );
+        assert_eq!(
+            conditional("[[ x =~ ^(a b|c)$ ]]"),
+            binary("x", "=~", literal("^(a b|c)$"))
+        );
+
+        for input in [
+            "[[ ]]",
+            "[[ a == ]]",
+            "[[ -f a b ]]",
+            "[[ (a ]]",
+            "[[ a b ]]",
+        ] {
+            assert!(parse(input).is_err(), "{input:?} should be an error");
+        }
+        assert!(is_incomplete("[[ a &&"));
+    }
+
+    #[test]
+    fn test_parse_function_definitions() {
+        let function = |input: &str| match parse(input) {
+            Ok(list) => match &list.items[0].pipeline.commands[0] {
+                Command::Function(function) => function.clone(),
+                command => panic!("expected a function, found {command:?}"),
+            },
+            Err(err) => panic!("{input:?} should parse: {err}"),
+        };
+
+        let mkcd = function("mkcd() { mkdir -p \"$1\" && cd \"$1\"; } > log");
+        assert_eq!(mkcd.name, "mkcd");
+        assert_eq!(mkcd.body.text, "{ mkdir -p \"$1\" && cd \"$1\"; } > log");
+        assert!(matches!(
+            &mkcd.body.commands[0],
+            Command::Compound { redirects, .. } if redirects.len() == 1
+        ));
+
+        assert_eq!(function("function f { pwd; }").name, "f");
+        assert_eq!(function("function f ()\n(pwd)").body.text, "(pwd)");
+        assert_eq!(function("f ( ) if a; then b; fi").name, "f");
+
+        for input in [
+            "f() pwd",
+            "function { pwd; }",
+            "a=b() { pwd; }",
+            "f(); { pwd; }",
+        ] {
+            assert!(parse(input).is_err(), "{input:?} should be an error");
+        }
+        assert!(is_incomplete("f() {"));
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        let aliases: BTreeMap<String, String> = [
+            ("ll", "ls -l"),
+            ("ls", "ls -F"),
+            ("sudo", "sudo "),
+            ("a", "b"),
+            ("b", "a x"),
+            ("g", "{ pwd; }"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (String::from(name), String::from(value)))
+        .collect();
+        let words = |input: &str| {
+            let list = parse_with_aliases(input, &aliases).expect("input should parse");
+            simple_command(&list, 0, 0).words.clone()
+        };
+
+        assert_eq!(
+            words("ll src"),
+            vec![literal("ls"), literal("-F"), literal("-l"), literal("src")]
+        );
+        assert_eq!(
+            words("sudo ll"),
+            vec![literal("sudo"), literal("ls"), literal("-F"), literal("-l")]
+        );
+        assert_eq!(
+            words("a"),
+            vec![literal("a"), literal("x")],
+            "an alias shouldn't be replaced inside its own replacement"
+        );
+        assert_eq!(words("echo ll"), vec![literal("echo"), literal("ll")]);
+        assert_eq!(words("'ll'"), vec![quoted("ll")]);
+
+        let list = parse_with_aliases("ls | g > out", &aliases).expect("input should parse");
+        assert_eq!(list.items[0].pipeline.text, "ls | g > out");
+        assert!(matches!(
+            &list.items[0].pipeline.commands[1],
+            Command::Compound { body: CompoundCommand::Group(_), redirects } if redirects.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_incomplete_input() {
+        for input in [
+            "if true; then",
+            "ls &&",
+            "for i in 1 2\ndo",
+            "{ pwd",
+            "case a in",
+            "echo \"line one",
+            "echo 'a\nb",
+            "echo $(pwd",
+            "echo one \\",
+        ] {
+            assert!(is_incomplete(input), "{input:?} should be incomplete");
+        }
+        for input in [
+            "if true; then ls; fi",
+            "ls",
+            "ls )",
+            "fi",
+            "",
+            "echo \"a\nb\"",
+            "echo \\\\",
+            "# a \\",
+        ] {
+            assert!(!is_incomplete(input), "{input:?} should be complete");
+        }
+    }
+
+    #[test]
+    fn test_parse_redirections() {
+        let list = parse("cmd 2>err 10>>log &>all &>>more 3<>rw 4<&0 5>&- >|clobber <in")
+            .expect("redirections should parse");
+        let command = only_command(&list);
+        assert_eq!(command.words, vec![literal("cmd")]);
+
+        let ops: Vec<(Option<i32>, RedirectOp, Word)> = command
+            .redirects
+            .iter()
+            .map(|r| (r.fd, r.op, r.target.clone()))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                (Some(2), RedirectOp::Write, literal("err")),
+                (Some(10), RedirectOp::Append, literal("log")),
+                (None, RedirectOp::Both, literal("all")),
+                (None, RedirectOp::BothAppend, literal("more")),
+                (Some(3), RedirectOp::ReadWrite, literal("rw")),
+                (Some(4), RedirectOp::DupIn, literal("0")),
+                (Some(5), RedirectOp::DupOut, literal("-")),
+                (None, RedirectOp::Write, literal("clobber")),
+                (None, RedirectOp::Read, literal("in")),
+            ]
+        );
+
+        assert_eq!(
+            only_command(&parse("echo 2 > f a2>g \"2\">h").unwrap()).words,
+            vec![literal("echo"), literal("2"), literal("a2"), quoted("2")],
+            "only unquoted digits directly before an operator should name a file descriptor"
+        );
+        assert_eq!(
+            only_command(&parse("> out").unwrap()).redirects,
+            vec![Redirect {
+                fd: None,
+                op: RedirectOp::Write,
+                target: literal("out"),
+            }],
+            "a command may consist of redirections alone"
+        );
+        assert!(parse("cat 2>").is_err(), "`2>` should need a target");
+        assert!(parse("cat > | wc").is_err());
+    }
+
+    #[test]
+    fn test_parse_heredocs() {
+        let list = parse("cat <<EOF | wc\nhello $NAME\n\\$HOME \"${NAME}\"\nEOF\necho next")
+            .expect("here-document should parse");
+        assert_eq!(list.items.len(), 2);
+        assert_eq!(list.items[0].pipeline.text, "cat <<EOF | wc");
+        assert_eq!(
+            simple_command(&list, 0, 0).redirects[0].target,
+            Word(vec![
+                Segment::Quoted(String::from("hello ")),
+                parameter("NAME", true),
+                Segment::Quoted(String::from("\n$HOME \"")),
+                parameter("NAME", true),
+                Segment::Quoted(String::from("\"\n")),
+            ])
+        );
+
+        assert_eq!(
+            only_command(&parse("cat <<'EOF'\nhello $NAME\nEOF").unwrap()).redirects[0].target,
+            quoted("hello $NAME\n"),
+            "a quoted delimiter should disable expansion"
+        );
+
+        let list = parse("cat <<-END 3<<X\n\t\tindented\n\tEND\nthird\nX").unwrap();
+        let redirects = &only_command(&list).redirects;
+        assert_eq!(redirects[0].target, quoted("indented\n"));
+        assert_eq!(redirects[1].fd, Some(3));
+        assert_eq!(
+            redirects[1].target,
+            quoted("third\n"),
+            "`<<-` should strip leading tabs, and bodies should follow in order"
+        );
+    }
+
+    #[test]
+    fn test_heredoc_delimiters() {
+        assert_eq!(
+            heredoc_delimiters("cat <<EOF <<-\"END\" <<< word"),
+            vec![(String::from("EOF"), false), (String::from("END"), true)]
+        );
+    }
+}
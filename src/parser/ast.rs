@@ -0,0 +1,237 @@
+// The syntax tree of a line of input, as produced by `parser::parse`.
+
+use std::rc::Rc;
+
+// A word as typed, made up of segments whose quoting decides how each is
+// expanded. For example, `"$HOME"/a\ b` is a quoted parameter followed by the
+// literal `/a` and the quoted (escaped) ` b`, all one word.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word(pub Vec<Segment>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Literal(String), // Unquoted text.
+    Quoted(String),  // Text in single or double quotes, or escaped with `\`.
+    Parameter {
+        // `$NAME` or `${NAME...}`.
+        name: String,
+        op: ParamOp,
+        quoted: bool,
+    },
+    Command {
+        // `$(...)` or `` `...` ``: replaced by the output of the command list.
+        list: List,
+        quoted: bool,
+    },
+    Arithmetic {
+        // `$((...))`: replaced by the value of the expression, once expanded.
+        expression: Word,
+        quoted: bool,
+    },
+}
+
+// What to make of a parameter's value. The forms written with a colon, as in
+// `${NAME:-word}`, treat an empty value as if it were unset; `null` records
+// the colon.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamOp {
+    Value,  // `$NAME` or `${NAME}`
+    Length, // `${#NAME}`
+    Default {
+        word: Word,
+        null: bool,
+    }, // `${NAME-word}`
+    Assign {
+        word: Word,
+        null: bool,
+    }, // `${NAME=word}`
+    Error {
+        message: Word,
+        null: bool,
+    }, // `${NAME?message}`
+    Alternative {
+        word: Word,
+        null: bool,
+    }, // `${NAME+word}`
+    RemovePrefix {
+        pattern: Word,
+        longest: bool,
+    }, // `${NAME#pattern}` or `##`
+    RemoveSuffix {
+        pattern: Word,
+        longest: bool,
+    }, // `${NAME%pattern}` or `%%`
+    Replace {
+        // `${NAME/pattern/string}`
+        pattern: Word,
+        replacement: Word,
+        mode: ReplaceMode,
+    },
+}
+
+// Which matches of the pattern `${NAME/pattern/string}` replaces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaceMode {
+    First,  // `/`
+    All,    // `//`
+    Prefix, // `/#`: only a match at the start.
+    Suffix, // `/%`: only a match at the end.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedirectOp {
+    Read,       // `<`
+    Write,      // `>` or `>|`
+    Append,     // `>>`
+    ReadWrite,  // `<>`
+    HereDoc,    // `<<` or `<<-`: the target is the body.
+    HereString, // `<<<`
+    DupIn,      // `<&`
+    DupOut,     // `>&`
+    Both,       // `&>`
+    BothAppend, // `&>>`
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub fd: Option<i32>, // An explicit file descriptor, as in `2>`.
+    pub op: RedirectOp,
+    pub target: Word,
+}
+
+// `NAME=value`, before the name of a command or on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub name: String,
+    pub value: Word,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimpleCommand {
+    pub assignments: Vec<Assignment>,
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>, // In the order they're to be applied.
+}
+
+// A command that contains command lists of its own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompoundCommand {
+    Subshell(List), // `( list )`: runs in a copy of the shell.
+    Group(List),    // `{ list; }`: runs in the shell itself.
+    If {
+        // The condition and body of the `if` and each `elif`, then `else`.
+        branches: Vec<(List, List)>,
+        otherwise: Option<List>,
+    },
+    Loop {
+        // `while` or, if `until`, `until`.
+        condition: List,
+        body: List,
+        until: bool,
+    },
+    For {
+        // `for name in words`, or, without `in`, the positional parameters.
+        name: String,
+        words: Option<Vec<Word>>,
+        body: List,
+    },
+    ArithmeticFor {
+        // `for ((init; condition; step))`.
+        init: Word,
+        condition: Word,
+        step: Word,
+        body: List,
+    },
+    Case {
+        word: Word,
+        arms: Vec<CaseArm>,
+    },
+    Conditional(Conditional), // `[[ expression ]]`
+}
+
+// The expression of a `[[ ... ]]` command. Its words are expanded without
+// being split into fields or matched against file names.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conditional {
+    Word(Word), // True if it's not empty.
+    Unary {
+        op: String,
+        operand: Word,
+    },
+    Binary {
+        // With `==`, `=` or `!=`, `right` is a pattern, and with `=~`, a
+        // regular expression.
+        left: Word,
+        op: String,
+        right: Word,
+    },
+    Not(Box<Conditional>),
+    And(Box<Conditional>, Box<Conditional>),
+    Or(Box<Conditional>, Box<Conditional>),
+}
+
+// `pattern | pattern) list ;;` in a `case` command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseArm {
+    pub patterns: Vec<Word>,
+    pub body: List,
+}
+
+// One stage of a pipeline. A compound command's redirections apply to
+// everything in it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    Compound {
+        body: CompoundCommand,
+        redirects: Vec<Redirect>,
+    },
+    Function(Rc<Function>), // A definition, which the shell keeps.
+}
+
+// `name() compound-command` or `function name compound-command`. The body is
+// kept as a pipeline of its own, so that calling the function runs it as any
+// other command is run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub body: Pipeline,
+}
+
+// Commands connected by `|`. `text` is the pipeline as typed, for display in
+// the jobs table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+    pub text: String,
+}
+
+// How an element of a command list relates to the one before it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connector {
+    Always, // The first element, or one that follows `;`, `&` or a newline.
+    And,    // `&&`: run only if the previous element succeeded.
+    Or,     // `||`: run only if the previous element failed.
+}
+
+impl Connector {
+    pub fn should_run(self, last_status: i32) -> bool {
+        match self {
+            Connector::Always => true,
+            Connector::And => last_status == 0,
+            Connector::Or => last_status != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListItem {
+    pub connector: Connector,
+    pub pipeline: Pipeline,
+    pub background: bool, // Followed by `&`.
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct List {
+    pub items: Vec<ListItem>,
+}
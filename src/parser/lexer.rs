@@ -0,0 +1,687 @@
+use std::{iter::Peekable, ops::Range, str::CharIndices};
+
+use super::ast::{ParamOp, RedirectOp, ReplaceMode, Segment, Word};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Word(Word),
+    Operator(&'static str), // `|`, `||`, `&`, `&&`, `;`, `;;`, `(` or `)`.
+    Redirect(Option<i32>, RedirectOp),
+    Arithmetic(Word), // `((...))`, a command that evaluates the expression.
+    Newline,
+}
+
+// A here-document whose delimiter has been seen but whose body hasn't.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingHereDoc {
+    pub delimiter: String,
+    pub strip_tabs: bool, // `<<-`
+    pub quoted: bool,     // Quoting any part of the delimiter disables expansion.
+}
+
+// The tokens of some input, each with its position, and the bodies of its
+// here-documents in order. Bodies come from the lines that follow the line
+// where each here-document is introduced. `pending` lists any here-documents
+// whose bodies hadn't begun by the end of the input. `continued` says whether
+// the input ends with a backslash that would join it to the next line.
+pub struct Lexed {
+    pub tokens: Vec<(Token, Range<usize>)>,
+    pub heredocs: Vec<Word>,
+    pub pending: Vec<PendingHereDoc>,
+    pub continued: bool,
+}
+
+pub fn tokenize(input: &str) -> Result<Lexed, String> {
+    let mut lexer = Lexer {
+        input,
+        chars: input.char_indices().peekable(),
+        tokens: Vec::new(),
+        heredocs: Vec::new(),
+        pending: Vec::new(),
+        expecting_delimiter: None,
+        continued: false,
+    };
+    lexer.run()?;
+
+    Ok(Lexed {
+        tokens: lexer.tokens,
+        heredocs: lexer.heredocs,
+        pending: lexer.pending,
+        continued: lexer.continued,
+    })
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    tokens: Vec<(Token, Range<usize>)>,
+    heredocs: Vec<Word>,
+    pending: Vec<PendingHereDoc>,
+    expecting_delimiter: Option<bool>, // After `<<` (false) or `<<-` (true).
+    continued: bool,
+}
+
+impl Lexer<'_> {
+    fn run(&mut self) -> Result<(), String> {
+        while let Some(&(start, c)) = self.chars.peek() {
+            match c {
+                '\n' => {
+                    self.chars.next();
+                    self.push(Token::Newline, start);
+                    self.read_heredoc_bodies()?;
+                }
+                c if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                '\\' if self.peek_second() == Some('\n') => {
+                    // A line continuation between words.
+                    self.chars.next();
+                    self.chars.next();
+                }
+                '#' => {
+                    // A comment runs to the end of the line.
+                    while self.chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                }
+                '(' if self.peek_second() == Some('(') => {
+                    self.chars.next();
+                    self.chars.next();
+                    let text = arithmetic_text(&mut self.chars, "((")?;
+                    self.push(Token::Arithmetic(heredoc_body(&text)?), start);
+                }
+                '<' | '>' => self.redirect(None, start),
+                '&' if self.peek_second() == Some('>') => self.redirect(None, start),
+                '|' | '&' | ';' | '(' | ')' => {
+                    self.chars.next();
+                    let operator = match c {
+                        '|' if self.chars.next_if(|&(_, c)| c == '|').is_some() => "||",
+                        '&' if self.chars.next_if(|&(_, c)| c == '&').is_some() => "&&",
+                        ';' if self.chars.next_if(|&(_, c)| c == ';').is_some() => ";;",
+                        '|' => "|",
+                        '&' => "&",
+                        '(' => "(",
+                        ')' => ")",
+                        _ => ";",
+                    };
+                    self.push(Token::Operator(operator), start);
+                }
+                _ => {
+                    let word = self.word()?;
+
+                    // Digits directly before a redirection operator are the
+                    // file descriptor it applies to, as in `2>`.
+                    if let [Segment::Literal(digits)] = word.0.as_slice()
+                        && digits.chars().all(|c| c.is_ascii_digit())
+                        && matches!(self.chars.peek(), Some((_, '<' | '>')))
+                        && let Ok(fd) = digits.parse()
+                    {
+                        self.redirect(Some(fd), start);
+                        continue;
+                    }
+
+                    if let Some(strip_tabs) = self.expecting_delimiter {
+                        let (delimiter, quoted) = delimiter_text(&word);
+                        self.pending.push(PendingHereDoc {
+                            delimiter,
+                            strip_tabs,
+                            quoted,
+                        });
+                    }
+                    self.push(Token::Word(word), start);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, token: Token, start: usize) {
+        let end = self.position();
+        self.expecting_delimiter = None;
+        self.tokens.push((token, start..end));
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map_or(self.input.len(), |&(index, _)| index)
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next().map(|(_, c)| c)
+    }
+
+    fn next_if_char(&mut self, expected: char) -> bool {
+        self.chars.next_if(|&(_, c)| c == expected).is_some()
+    }
+
+    fn redirect(&mut self, fd: Option<i32>, start: usize) {
+        let Some((_, c)) = self.chars.next() else {
+            return;
+        };
+
+        let mut strip_tabs = None;
+        let op = match c {
+            '&' => {
+                self.chars.next(); // The `>`.
+                if self.next_if_char('>') {
+                    RedirectOp::BothAppend
+                } else {
+                    RedirectOp::Both
+                }
+            }
+            '>' if self.next_if_char('>') => RedirectOp::Append,
+            '>' if self.next_if_char('&') => RedirectOp::DupOut,
+            '>' => {
+                self.next_if_char('|');
+                RedirectOp::Write
+            }
+            _ if self.next_if_char('<') => {
+                if self.next_if_char('<') {
+                    RedirectOp::HereString
+                } else {
+                    strip_tabs = Some(self.next_if_char('-'));
+                    RedirectOp::HereDoc
+                }
+            }
+            _ if self.next_if_char('&') => RedirectOp::DupIn,
+            _ if self.next_if_char('>') => RedirectOp::ReadWrite,
+            _ => RedirectOp::Read,
+        };
+
+        self.push(Token::Redirect(fd, op), start);
+        self.expecting_delimiter = strip_tabs;
+    }
+
+    fn word(&mut self) -> Result<Word, String> {
+        let mut segments = Vec::new();
+
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c.is_whitespace() || "|&;<>()".contains(c) {
+                break;
+            }
+            self.chars.next();
+            match c {
+                '\\' => match self.chars.next() {
+                    Some((_, '\n')) => {} // A line continuation.
+                    Some((_, escaped)) => push_quoted(&mut segments, escaped),
+                    None => {
+                        // Literal at the very end, unless another line follows.
+                        push_literal(&mut segments, '\\');
+                        self.continued = true;
+                    }
+                },
+                '\'' => {
+                    let text = single_quoted(&mut self.chars)?;
+                    push_segment(&mut segments, Segment::Quoted(text));
+                }
+                '"' => double_quoted(&mut self.chars, &mut segments)?,
+                '$' => dollar(&mut self.chars, &mut segments, false)?,
+                '`' => segments.push(backquoted(&mut self.chars, false)?),
+                _ => push_literal(&mut segments, c),
+            }
+        }
+
+        Ok(Word(segments))
+    }
+
+    // Take the bodies of any here-documents introduced on the line just
+    // finished from the lines that follow. A body that isn't terminated by its
+    // delimiter runs to the end of the input.
+    fn read_heredoc_bodies(&mut self) -> Result<(), String> {
+        for heredoc in std::mem::take(&mut self.pending) {
+            let mut body = String::new();
+
+            while self.chars.peek().is_some() {
+                let mut line = String::new();
+                for (_, c) in self.chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                    line.push(c);
+                }
+
+                let line = if heredoc.strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    &line
+                };
+                if line == heredoc.delimiter {
+                    break;
+                }
+                body.push_str(line);
+                body.push('\n');
+            }
+
+            let body = if heredoc.quoted {
+                Word(vec![Segment::Quoted(body)])
+            } else {
+                heredoc_body(&body)?
+            };
+            self.heredocs.push(body);
+        }
+
+        Ok(())
+    }
+}
+
+// The text of a here-document delimiter, with quotes removed, and whether any
+// part of it was quoted.
+fn delimiter_text(word: &Word) -> (String, bool) {
+    let mut text = String::new();
+    let mut quoted = false;
+
+    for segment in &word.0 {
+        match segment {
+            Segment::Literal(s) => text.push_str(s),
+            Segment::Quoted(s) => {
+                text.push_str(s);
+                quoted = true;
+            }
+            Segment::Parameter { name, .. } => {
+                text.push('$');
+                text.push_str(name);
+            }
+            Segment::Command { .. } | Segment::Arithmetic { .. } => {}
+        }
+    }
+
+    (text, quoted)
+}
+
+// The body of a here-document with an unquoted delimiter is expanded as if in
+// double quotes, except that `"` isn't special. A backslash escapes `$`, `` `
+// `` and itself.
+fn heredoc_body(body: &str) -> Result<Word, String> {
+    let mut segments = Vec::new();
+    let mut chars = body.char_indices().peekable();
+
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&(_, '\n')) => {
+                    chars.next();
+                }
+                Some(&(_, c)) if "$`\\".contains(c) => {
+                    chars.next();
+                    push_quoted(&mut segments, c);
+                }
+                _ => push_quoted(&mut segments, '\\'),
+            },
+            '$' => dollar(&mut chars, &mut segments, true)?,
+            '`' => segments.push(backquoted(&mut chars, true)?),
+            _ => push_quoted(&mut segments, c),
+        }
+    }
+
+    Ok(Word(segments))
+}
+
+// The text up to the closing `'`, the opening one having been read.
+fn single_quoted(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+) -> Result<String, String> {
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '\'')) => return Ok(text),
+            Some((_, c)) => text.push(c),
+            None => return Err(String::from("Unterminated quote: '")),
+        }
+    }
+}
+
+// Read up to the closing `"`, the opening one having been read.
+fn double_quoted(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    segments: &mut Vec<Segment>,
+) -> Result<(), String> {
+    // An empty pair of quotes is still an (empty) argument. The marker isn't
+    // needed if a parameter follows, and would get in the way of `"$@"` with
+    // no positional parameters, which is no argument at all.
+    push_segment(segments, Segment::Quoted(String::new()));
+    let marker = segments.len() - 1;
+
+    loop {
+        match chars.next() {
+            Some((_, '"')) => {
+                if segments.len() > marker + 1 && segments[marker] == Segment::Quoted(String::new())
+                {
+                    segments.remove(marker);
+                }
+                return Ok(());
+            }
+            Some((_, '\\')) => match chars.peek() {
+                Some(&(_, '\n')) => {
+                    chars.next();
+                }
+                Some(&(_, c)) if "$`\"\\".contains(c) => {
+                    chars.next();
+                    push_quoted(segments, c);
+                }
+                _ => push_quoted(segments, '\\'),
+            },
+            Some((_, '$')) => dollar(chars, segments, true)?,
+            Some((_, '`')) => segments.push(backquoted(chars, true)?),
+            Some((_, c)) => push_quoted(segments, c),
+            None => return Err(String::from("Unterminated quote: \"")),
+        }
+    }
+}
+
+// Whatever follows a `$`: a parameter, or else the `$` is literal.
+fn dollar(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    segments: &mut Vec<Segment>,
+    quoted: bool,
+) -> Result<(), String> {
+    if chars.next_if(|&(_, c)| c == '{').is_some() {
+        segments.push(braced_parameter(chars, quoted)?);
+        return Ok(());
+    }
+    if chars.next_if(|&(_, c)| c == '(').is_some() {
+        if chars.next_if(|&(_, c)| c == '(').is_some() {
+            let text = arithmetic_text(chars, "$((")?;
+            let expression = heredoc_body(&text)?;
+            segments.push(Segment::Arithmetic { expression, quoted });
+            return Ok(());
+        }
+        let list = super::parse(&substitution_text(chars)?)?;
+        segments.push(Segment::Command { list, quoted });
+        return Ok(());
+    }
+
+    let mut name = String::new();
+    if let Some((_, c)) =
+        chars.next_if(|&(_, c)| SPECIAL_PARAMETERS.contains(c) || c.is_ascii_digit())
+    {
+        // Special and positional parameters, such as `$?` and `$1`, have
+        // one-character names (unless in braces, as in `${10}`).
+        name.push(c);
+    } else {
+        while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_') {
+            name.push(c);
+        }
+    }
+
+    if name.is_empty() {
+        if quoted {
+            push_quoted(segments, '$');
+        } else {
+            push_literal(segments, '$');
+        }
+    } else {
+        segments.push(Segment::Parameter {
+            name,
+            op: ParamOp::Value,
+            quoted,
+        });
+    }
+
+    Ok(())
+}
+
+// The text of a command substitution up to the `)` that closes its `$(`, which
+// has been read. Parentheses within it must balance, except in quotes.
+fn substitution_text(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+) -> Result<String, String> {
+    let mut text = String::new();
+    let mut depth = 0;
+
+    loop {
+        let Some((_, c)) = chars.next() else {
+            return Err(String::from("Unterminated command substitution: $("));
+        };
+        if c == ')' && depth == 0 {
+            return Ok(text);
+        }
+        text.push(c);
+
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '\\' => text.extend(chars.next().map(|(_, c)| c)),
+            '\'' | '"' | '`' => {
+                // Copy the quoted text as it is, for the inner parse.
+                let close = c;
+                loop {
+                    let Some((_, c)) = chars.next() else {
+                        return Err(String::from("Unterminated command substitution: $("));
+                    };
+                    text.push(c);
+                    if c == close {
+                        break;
+                    }
+                    if c == '\\' && close != '\'' {
+                        text.extend(chars.next().map(|(_, c)| c));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// The text of an arithmetic expression up to the `))` that closes its `((` or
+// `$((`, which has been read. Parentheses within it must balance.
+fn arithmetic_text(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    opening: &str,
+) -> Result<String, String> {
+    let mut text = String::new();
+    let mut depth = 0;
+
+    loop {
+        let Some((_, c)) = chars.next() else {
+            return Err(format!("Unterminated arithmetic expression: {opening}"));
+        };
+        match c {
+            ')' if depth == 0 => {
+                if chars.next_if(|&(_, c)| c == ')').is_some() {
+                    return Ok(text);
+                }
+                return Err(format!("Parse error near `)' in {opening}{text})"));
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        text.push(c);
+    }
+}
+
+// A command substitution in backquotes, the opening one having been read.
+// Within them, a backslash only escapes `$`, `` ` `` and `\`, and also `"` if
+// the backquotes are themselves in double quotes.
+fn backquoted(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    quoted: bool,
+) -> Result<Segment, String> {
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '`')) => break,
+            Some((_, '\\')) => {
+                match chars.next_if(|&(_, c)| "$`\\".contains(c) || (quoted && c == '"')) {
+                    Some((_, c)) => text.push(c),
+                    None => text.push('\\'),
+                }
+            }
+            Some((_, c)) => text.push(c),
+            None => return Err(String::from("Unterminated command substitution: `")),
+        }
+    }
+
+    let list = super::parse(&text)?;
+    Ok(Segment::Command { list, quoted })
+}
+
+// The parameters whose names are single punctuation characters, such as `$?`.
+const SPECIAL_PARAMETERS: &str = "?$!-#@*";
+
+// Read a parameter after its `${`, up to the matching `}`.
+fn braced_parameter(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    quoted: bool,
+) -> Result<Segment, String> {
+    let mut length = chars.next_if(|&(_, c)| c == '#').is_some();
+
+    let mut name = String::new();
+    if let Some((_, c)) = chars.next_if(|&(_, c)| SPECIAL_PARAMETERS.contains(c)) {
+        name.push(c);
+    } else {
+        while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_') {
+            name.push(c);
+        }
+    }
+    // In `${#}` or `${#:-word}`, the `#` is the parameter itself.
+    if length && name.is_empty() {
+        length = false;
+        name.push('#');
+    }
+    let bad_substitution = |chars: &mut Peekable<_>, text: String| {
+        let rest: String = chars
+            .by_ref()
+            .map(|(_, c)| c)
+            .take_while(|&c| c != '}')
+            .collect();
+        Err(format!("Bad substitution: ${{{text}{rest}}}"))
+    };
+    // A positional parameter's number can have several digits inside braces.
+    let positional = !name.is_empty() && name.chars().all(|c| c.is_ascii_digit());
+    let special = name.len() == 1 && SPECIAL_PARAMETERS.contains(&name);
+    if !is_name(&name) && !positional && !special {
+        let text = if length { format!("#{name}") } else { name };
+        return bad_substitution(chars, text);
+    }
+
+    let Some((_, c)) = chars.next() else {
+        return Err(String::from("Unterminated parameter: ${"));
+    };
+    let null = c == ':';
+    let c = if null {
+        match chars.next() {
+            Some((_, c)) if "-=?+".contains(c) => c,
+            Some((_, c)) => return bad_substitution(chars, format!("{name}:{c}")),
+            None => return Err(String::from("Unterminated parameter: ${")),
+        }
+    } else {
+        c
+    };
+
+    let op = match c {
+        '}' if length => ParamOp::Length,
+        '}' => ParamOp::Value,
+        _ if length => return bad_substitution(chars, format!("#{name}{c}")),
+        '-' => ParamOp::Default {
+            word: brace_word(chars, quoted, &['}'])?.0,
+            null,
+        },
+        '=' => ParamOp::Assign {
+            word: brace_word(chars, quoted, &['}'])?.0,
+            null,
+        },
+        '?' => ParamOp::Error {
+            message: brace_word(chars, quoted, &['}'])?.0,
+            null,
+        },
+        '+' => ParamOp::Alternative {
+            word: brace_word(chars, quoted, &['}'])?.0,
+            null,
+        },
+        '#' => ParamOp::RemovePrefix {
+            longest: chars.next_if(|&(_, c)| c == '#').is_some(),
+            pattern: brace_word(chars, quoted, &['}'])?.0,
+        },
+        '%' => ParamOp::RemoveSuffix {
+            longest: chars.next_if(|&(_, c)| c == '%').is_some(),
+            pattern: brace_word(chars, quoted, &['}'])?.0,
+        },
+        '/' => {
+            let mode = match chars.next_if(|&(_, c)| "/#%".contains(c)) {
+                Some((_, '/')) => ReplaceMode::All,
+                Some((_, '#')) => ReplaceMode::Prefix,
+                Some((_, '%')) => ReplaceMode::Suffix,
+                _ => ReplaceMode::First,
+            };
+            let (pattern, end) = brace_word(chars, quoted, &['/', '}'])?;
+            let replacement = if end == '/' {
+                brace_word(chars, quoted, &['}'])?.0
+            } else {
+                Word(Vec::new())
+            };
+            ParamOp::Replace {
+                pattern,
+                replacement,
+                mode,
+            }
+        }
+        _ => return bad_substitution(chars, format!("{name}{c}")),
+    };
+
+    Ok(Segment::Parameter { name, op, quoted })
+}
+
+// The word inside `${...}` after an operator, up to any of `ends`, which is
+// returned with it. Quotes, escapes and parameters work as they do anywhere
+// else; inside double quotes, everything is quoted.
+fn brace_word(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    quoted: bool,
+    ends: &[char],
+) -> Result<(Word, char), String> {
+    let mut segments = Vec::new();
+
+    loop {
+        match chars.next() {
+            Some((_, c)) if ends.contains(&c) => return Ok((Word(segments), c)),
+            Some((_, '\\')) => match chars.next() {
+                Some((_, '\n')) => {}
+                Some((_, c)) => {
+                    if quoted && !"$`\"\\}".contains(c) {
+                        push_quoted(&mut segments, '\\');
+                    }
+                    push_quoted(&mut segments, c);
+                }
+                None => break,
+            },
+            Some((_, '\'')) if !quoted => {
+                let text = single_quoted(chars)?;
+                push_segment(&mut segments, Segment::Quoted(text));
+            }
+            Some((_, '"')) => double_quoted(chars, &mut segments)?,
+            Some((_, '$')) => dollar(chars, &mut segments, quoted)?,
+            Some((_, c)) if quoted => push_quoted(&mut segments, c),
+            Some((_, c)) => push_literal(&mut segments, c),
+            None => break,
+        }
+    }
+
+    Err(String::from("Unterminated parameter: ${"))
+}
+
+pub fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn push_segment(segments: &mut Vec<Segment>, segment: Segment) {
+    match (segments.last_mut(), segment) {
+        (Some(Segment::Literal(text)), Segment::Literal(more))
+        | (Some(Segment::Quoted(text)), Segment::Quoted(more)) => text.push_str(&more),
+        (_, segment) => segments.push(segment),
+    }
+}
+
+fn push_literal(segments: &mut Vec<Segment>, c: char) {
+    push_segment(segments, Segment::Literal(String::from(c)));
+}
+
+fn push_quoted(segments: &mut Vec<Segment>, c: char) {
+    push_segment(segments, Segment::Quoted(String::from(c)));
+}
@@ -1,43 +1,486 @@
-pub fn separate_sources_from_targets(input: &[String]) -> (Vec<&String>, Vec<[&String; 2]>) {
-    let mut sources = Vec::new();
-    let mut targets = Vec::new();
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::fs::OpenOptionsExt,
+    },
+};
 
-    for (index, current) in input.iter().enumerate() {
-        if index == 0 || current == ">" || current == ">>" {
-            continue;
+use crate::parser::ast::RedirectOp;
+
+// The lowest file descriptor used for files that the shell opens on behalf of a
+// redirection, keeping them clear of the low numbers that users redirect.
+const FIRST_PRIVATE_FD: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Read,      // `<`
+    Write,     // `>`
+    Append,    // `>>`
+    ReadWrite, // `<>`
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Open(String, Mode),
+    Duplicate(i32), // `n>&m`, `n<&m`: make `n` a copy of `m`.
+    Close,          // `n>&-`, `n<&-`
+    Text(String),   // The contents of a here-document or here-string.
+}
+
+// One redirection: what should happen to file descriptor `fd`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirection {
+    pub fd: i32,
+    pub action: Action,
+}
+
+// A redirection whose file, if any, has been opened, ready to be applied.
+pub enum Prepared {
+    Source(i32, File),
+    Duplicate(i32, i32),
+    Close(i32),
+}
+
+// The redirections that an operator stands for, given its explicit file
+// descriptor, if any, and its expanded target: the name of a file, a file
+// descriptor number, `-`, or the text of a here-document or here-string.
+pub fn resolve(
+    explicit_fd: Option<i32>,
+    op: RedirectOp,
+    target: String,
+) -> Result<Vec<Redirection>, String> {
+    let default_fd = match op {
+        RedirectOp::Read
+        | RedirectOp::ReadWrite
+        | RedirectOp::HereDoc
+        | RedirectOp::HereString
+        | RedirectOp::DupIn => 0,
+        _ => 1,
+    };
+    let fd = explicit_fd.unwrap_or(default_fd);
+    let one = |action| Ok(vec![Redirection { fd, action }]);
+
+    match op {
+        RedirectOp::Read => one(Action::Open(target, Mode::Read)),
+        RedirectOp::Write => one(Action::Open(target, Mode::Write)),
+        RedirectOp::Append => one(Action::Open(target, Mode::Append)),
+        RedirectOp::ReadWrite => one(Action::Open(target, Mode::ReadWrite)),
+        RedirectOp::HereDoc => one(Action::Text(target)),
+        RedirectOp::HereString => one(Action::Text(format!("{target}\n"))),
+        RedirectOp::DupIn | RedirectOp::DupOut => {
+            if target == "-" {
+                one(Action::Close)
+            } else if let Ok(source) = target.parse::<i32>() {
+                one(Action::Duplicate(source))
+            } else if op == RedirectOp::DupOut && explicit_fd.is_none() {
+                // `>& file` is an old synonym for `&> file`.
+                Ok(both(target, Mode::Write))
+            } else {
+                Err(format!("0-shell: Ambiguous redirect: {target}"))
+            }
         }
+        RedirectOp::Both => Ok(both(target, Mode::Write)),
+        RedirectOp::BothAppend => Ok(both(target, Mode::Append)),
+    }
+}
 
-        let previous = if index > 0 {
-            input.get(index - 1)
-        } else {
-            None
-        };
+// `&> file`: equivalent to `> file 2>&1`.
+fn both(target: String, mode: Mode) -> Vec<Redirection> {
+    vec![
+        Redirection {
+            fd: 1,
+            action: Action::Open(target, mode),
+        },
+        Redirection {
+            fd: 2,
+            action: Action::Duplicate(1),
+        },
+    ]
+}
 
-        if let Some(previous) = previous {
-            if previous == ">" || previous == ">>" {
-                targets.push([previous, current]);
+// Open the files named by redirections, and write the text of here-documents
+// and here-strings to anonymous temporary files so that a process can read
+// them however large they are. This happens in the shell before any fork so
+// that failures can be reported like other errors and so that the children
+// needn't allocate.
+pub fn prepare(redirections: &[Redirection]) -> Result<Vec<Prepared>, String> {
+    redirections
+        .iter()
+        .map(|redirection| {
+            let fd = redirection.fd;
+            if fd < 0 {
+                return Err(String::from("0-shell: Invalid file descriptor"));
+            }
+
+            match &redirection.action {
+                Action::Open(path, mode) => Ok(Prepared::Source(fd, open(path, *mode)?)),
+                Action::Text(text) => Ok(Prepared::Source(fd, text_source(text)?)),
+                Action::Duplicate(source) => Ok(Prepared::Duplicate(fd, *source)),
+                Action::Close => Ok(Prepared::Close(fd)),
+            }
+        })
+        .collect()
+}
+
+fn open(path: &str, mode: Mode) -> Result<File, String> {
+    let mut options = OpenOptions::new();
+    match mode {
+        Mode::Read => options.read(true),
+        Mode::Write => options.write(true).create(true).truncate(true),
+        Mode::Append => options.append(true).create(true),
+        Mode::ReadWrite => options.read(true).write(true).create(true),
+    };
+
+    let file = options
+        .custom_flags(libc::O_CLOEXEC)
+        .open(path)
+        .map_err(|e| {
+            let message = if path_is_dir(path) {
+                String::from("Is a directory")
             } else {
-                sources.push(current);
+                e.to_string()
+                    .split(" (os ")
+                    .next()
+                    .unwrap_or(" ")
+                    .to_string()
+            };
+            format!("0-shell: {message}: {path}")
+        })?;
+
+    move_clear_of_user_fds(file)
+}
+
+fn path_is_dir(path: &str) -> bool {
+    std::path::Path::new(path).is_dir()
+}
+
+fn text_source(text: &str) -> Result<File, String> {
+    let mut file = tempfile::tempfile()
+        .map_err(|e| format!("0-shell: Failed to create here-document: {e}"))?;
+    file.write_all(text.as_bytes())
+        .and_then(|_| file.seek(SeekFrom::Start(0)))
+        .map_err(|e| format!("0-shell: Failed to write here-document: {e}"))?;
+    move_clear_of_user_fds(file)
+}
+
+// Renumber an open file so that applying one redirection can't clobber the
+// source of another: in `7> a 3> b`, say, `b` might otherwise have been opened
+// as file descriptor 7. The copy is closed automatically on exec.
+fn move_clear_of_user_fds(file: File) -> Result<File, String> {
+    let fd = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, FIRST_PRIVATE_FD) };
+    if fd == -1 {
+        return Err(format!(
+            "0-shell: Failed to duplicate file descriptor: {}",
+            io::Error::last_os_error()
+        ));
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+// Apply prepared redirections in order. Used in a child process after fork,
+// where there's nothing to restore afterwards.
+pub fn apply(prepared: &[Prepared]) -> Result<(), String> {
+    for redirection in prepared {
+        apply_one(redirection)?;
+    }
+    Ok(())
+}
+
+fn apply_one(redirection: &Prepared) -> Result<(), String> {
+    let res = unsafe {
+        match redirection {
+            Prepared::Source(fd, file) => libc::dup2(file.as_raw_fd(), *fd),
+            Prepared::Duplicate(fd, source) => {
+                if fd == source {
+                    // `dup2` would succeed trivially, but the source must be
+                    // open.
+                    libc::fcntl(*source, libc::F_GETFD)
+                } else {
+                    libc::dup2(*source, *fd)
+                }
+            }
+            Prepared::Close(fd) => {
+                libc::close(*fd);
+                0
             }
-        } else {
-            sources.push(current);
         }
+    };
+
+    if res == -1 {
+        let err = io::Error::last_os_error().to_string();
+        let err = err.split(" (os ").next().unwrap_or(" ");
+        return Err(match redirection {
+            Prepared::Duplicate(_, source) => format!("0-shell: {source}: {err}"),
+            _ => format!("0-shell: {err}"),
+        });
     }
 
-    (sources, targets)
+    Ok(())
+}
+
+// Restores the file descriptors changed by redirections applied within the
+// shell process itself, for builtins, when dropped.
+pub struct SavedFds {
+    saved: Vec<(i32, Option<i32>)>, // Each fd and a copy of it, if it was open.
+}
+
+impl Drop for SavedFds {
+    fn drop(&mut self) {
+        // Make sure everything written while redirected goes where it should.
+        io::stdout().flush().ok();
+
+        for &(fd, copy) in self.saved.iter().rev() {
+            unsafe {
+                match copy {
+                    Some(copy) => {
+                        libc::dup2(copy, fd);
+                        libc::close(copy);
+                    }
+                    None => {
+                        libc::close(fd);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Apply prepared redirections to the shell process itself, saving a copy of
+// each file descriptor first so that the returned guard can restore them.
+pub fn apply_saving(prepared: &[Prepared]) -> Result<SavedFds, String> {
+    // Send anything already buffered to its original destination.
+    io::stdout().flush().ok();
+
+    let mut guard = SavedFds { saved: Vec::new() };
+
+    for redirection in prepared {
+        let fd = match redirection {
+            Prepared::Source(fd, _) | Prepared::Duplicate(fd, _) | Prepared::Close(fd) => *fd,
+        };
+
+        if !guard.saved.iter().any(|&(saved, _)| saved == fd) {
+            let copy = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, FIRST_PRIVATE_FD) };
+            guard.saved.push((fd, (copy != -1).then_some(copy)));
+        }
+
+        // On failure, the guard restores whatever was changed so far.
+        apply_one(redirection)?;
+    }
+
+    Ok(guard)
+}
+
+// Run `f` with redirections applied to the shell process around it.
+pub fn with_redirections<T>(
+    redirections: &[Redirection],
+    f: impl FnOnce() -> T,
+) -> Result<T, String> {
+    let prepared = prepare(redirections)?;
+    let _saved = apply_saving(&prepared)?;
+    Ok(f())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::separate_sources_from_targets;
-    use crate::string_vec;
+    use std::{fs, io::Read};
+
+    use super::{Action, Mode, Prepared, Redirection, apply_saving, prepare, with_redirections};
+    use crate::{
+        expand,
+        parser::parse,
+        test_helpers::{TempStore, simple_command},
+        variables::Variables,
+    };
+
+    // A file descriptor that nothing else in the test process is likely to
+    // be using, so that redirecting it doesn't disturb other tests.
+    const TEST_FD: i32 = 57;
+
+    fn write_fd(fd: i32, text: &str) -> isize {
+        unsafe { libc::write(fd, text.as_ptr() as *const libc::c_void, text.len()) }
+    }
+
+    // The redirections of the only command on a line.
+    fn redirections(line: &str) -> Result<Vec<Redirection>, String> {
+        let list = parse(line)?;
+        expand::command(simple_command(&list, 0, 0), &mut Variables::default())
+            .map(|cmd| cmd.redirections)
+    }
+
+    #[test]
+    fn redirections_resolve_in_order() {
+        let redirections = redirections("cmd a > out b 2>&1 < in 3<> rw 4>&- &>> log <<< 'a b'")
+            .expect("redirections should resolve");
+
+        assert_eq!(
+            redirections,
+            vec![
+                Redirection {
+                    fd: 1,
+                    action: Action::Open(String::from("out"), Mode::Write)
+                },
+                Redirection {
+                    fd: 2,
+                    action: Action::Duplicate(1)
+                },
+                Redirection {
+                    fd: 0,
+                    action: Action::Open(String::from("in"), Mode::Read)
+                },
+                Redirection {
+                    fd: 3,
+                    action: Action::Open(String::from("rw"), Mode::ReadWrite)
+                },
+                Redirection {
+                    fd: 4,
+                    action: Action::Close
+                },
+                Redirection {
+                    fd: 1,
+                    action: Action::Open(String::from("log"), Mode::Append)
+                },
+                Redirection {
+                    fd: 2,
+                    action: Action::Duplicate(1)
+                },
+                Redirection {
+                    fd: 0,
+                    action: Action::Text(String::from("a b\n"))
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn redirect_to_word_is_both_streams() {
+        assert_eq!(
+            redirections("cmd >& file"),
+            Ok(vec![
+                Redirection {
+                    fd: 1,
+                    action: Action::Open(String::from("file"), Mode::Write)
+                },
+                Redirection {
+                    fd: 2,
+                    action: Action::Duplicate(1)
+                },
+            ])
+        );
+        assert!(
+            redirections("cmd 2>&file").is_err(),
+            "`2>&file` should be an ambiguous redirect"
+        );
+    }
+
+    #[test]
+    fn empty_here_string_is_a_newline() {
+        let newline = Ok(vec![Redirection {
+            fd: 0,
+            action: Action::Text(String::from("\n")),
+        }]);
+        assert_eq!(redirections("cat <<< ''"), newline);
+        assert_eq!(redirections("cat <<< $unset"), newline);
+    }
+
+    #[test]
+    fn prepare_reads_here_documents_and_files() {
+        let file = &TempStore::new(1).store[0];
+        fs::write(file, "from file\n").expect("failed to write source file");
+
+        let redirections =
+            redirections(&format!("sort < {file} 3<<END\nbody\nEND")).expect("should resolve");
+        let prepared = prepare(&redirections).expect("prepare should succeed");
+
+        let contents: Vec<String> = prepared
+            .into_iter()
+            .map(|p| match p {
+                Prepared::Source(_, mut source) => {
+                    let mut text = String::new();
+                    source
+                        .read_to_string(&mut text)
+                        .expect("failed to read redirection source");
+                    text
+                }
+                _ => panic!("expected a source"),
+            })
+            .collect();
+        assert_eq!(contents, vec!["from file\n", "body\n"]);
+    }
 
     #[test]
-    fn separate_sources_from_targets_standard_case() {
-        let input = string_vec!["cmd", "a", "b", ">", "c", ">>", "d", ">>", "e", "f"];
-        let (sources, targets) = separate_sources_from_targets(&input);
+    fn prepare_fails_for_missing_source_and_directory_target() {
+        let dir = &TempStore::new(1).store[0];
+        fs::create_dir(dir).expect("failed to create directory");
+
+        let missing = redirections("cat < no_such_file_for_0_shell").unwrap();
+        assert!(prepare(&missing).is_err(), "a missing source should fail");
 
-        assert_eq!(sources, vec!["a", "b", "f"]);
-        assert_eq!(targets, vec![[">", "c"], [">>", "d"], [">>", "e"]]);
+        let to_dir = redirections(&format!("echo > {dir}")).unwrap();
+        let err = prepare(&to_dir)
+            .err()
+            .expect("a directory target should fail");
+        assert!(
+            err.contains("0-shell: Is a directory"),
+            "error should include shell-prefixed directory message, got: {err}"
+        );
+    }
+
+    #[test]
+    fn redirect_write_then_append() {
+        let file = &TempStore::new(1).store[0];
+
+        for (op, text, expected) in [
+            (">", "hello\n", "hello\n"),
+            (">", "world\n", "world\n"),
+            (">>", "again\n", "world\nagain\n"),
+        ] {
+            let redirections = redirections(&format!("echo {TEST_FD}{op} {file}")).unwrap();
+            with_redirections(&redirections, || write_fd(TEST_FD, text))
+                .expect("redirection should succeed");
+
+            let contents = fs::read_to_string(file).expect("failed to read file");
+            assert_eq!(contents, expected);
+        }
+    }
+
+    #[test]
+    fn redirect_to_multiple_targets_writes_to_last() {
+        let temp_store = TempStore::new(2);
+        let u = &temp_store.store[0];
+        let v = &temp_store.store[1];
+
+        let redirections = redirections(&format!("echo {TEST_FD}> {u} {TEST_FD}> {v}")).unwrap();
+        with_redirections(&redirections, || write_fd(TEST_FD, "hello\n"))
+            .expect("redirection should succeed");
+
+        assert_eq!(fs::read_to_string(u).expect("failed to read u"), "");
+        assert_eq!(fs::read_to_string(v).expect("failed to read v"), "hello\n");
+    }
+
+    #[test]
+    fn redirections_are_undone_afterwards() {
+        let file = &TempStore::new(1).store[0];
+        let before = unsafe { libc::fcntl(TEST_FD, libc::F_GETFD) };
+        assert_eq!(before, -1, "test file descriptor should start closed");
+
+        let redirections = redirections(&format!("cmd {TEST_FD}> {file}")).unwrap();
+        let prepared = prepare(&redirections).expect("prepare should succeed");
+
+        {
+            let _saved = apply_saving(&prepared).expect("apply should succeed");
+            assert_eq!(write_fd(TEST_FD, "x"), 1, "redirected fd should be open");
+        }
+
+        let after = unsafe { libc::fcntl(TEST_FD, libc::F_GETFD) };
+        assert_eq!(after, -1, "redirected fd should be closed again");
+    }
+
+    #[test]
+    fn duplicating_a_closed_fd_fails() {
+        let redirections = redirections(&format!("cmd {TEST_FD}>&58")).unwrap();
+        let prepared = prepare(&redirections).expect("prepare should succeed");
+        assert!(apply_saving(&prepared).is_err());
     }
 }
@@ -1,13 +1,17 @@
-use std::{collections::VecDeque, io, mem, ptr};
+use std::{collections::VecDeque, path::Path};
 
 use crate::{
     ansi::{BOLD, RESET},
     commands::{
-        self,
+        self, Flow,
         exit::STOPPED_JOBS_WARNING,
-        jobs::{self, Job},
+        jobs::{self, JobTable},
+        source, trap,
     },
-    error, input,
+    error, fork, input, parser,
+    script::Invocation,
+    signals,
+    variables::Variables,
 };
 
 struct TextStyle;
@@ -56,44 +60,27 @@ impl Drop for TtyGuard {
     }
 }
 
-pub fn repl() {
-    let mut jobs: Vec<Job> = Vec::new();
-    let mut current: usize = 0;
-    let mut previous: usize = 0;
+pub fn repl(invocation: Invocation) {
+    let mut jobs = JobTable::default();
     let mut exit_attempted = false;
-    let mut final_status = 0;
-
-    unsafe {
-        let mut ignore = mem::zeroed::<libc::sigaction>();
-        libc::sigemptyset(&mut ignore.sa_mask);
-        ignore.sa_sigaction = libc::SIG_IGN;
-
-        // Define a closure to register a signal handler if it can and panic if
-        // not.
-        let set_action = |sig: i32, act: &libc::sigaction| {
-            // `null_mut`: This argument is for the "old action". If we passed
-            // a pointer here, the OS would write the previous signal handler
-            // settings into it. Since we don't care what the previous handler
-            // was, we pass a null pointer.
-            if libc::sigaction(sig, act, ptr::null_mut()) != 0 {
-                let err = io::Error::last_os_error();
-                panic!("failed to set signal action `{}`: {}", sig, err);
-            }
-        };
-
-        // Ignore attempts to terminate, suspend, or quit 0-shell.
-        set_action(libc::SIGINT, &ignore);
-        set_action(libc::SIGTSTP, &ignore);
-        set_action(libc::SIGQUIT, &ignore);
-
-        // Prevent 0-shell from being stopped if it attempts a terminal
-        // read or write while backgrounded. The terminal driver fires
-        // `SIGTTIN` (`SIGTTOU`) when a background process group tries to read
-        // from the terminal, or write to it (unless output is piped or
-        // redirected).
-        // Ignore input except control characters.
-        set_action(libc::SIGTTIN, &ignore);
-        set_action(libc::SIGTTOU, &ignore); // Ignore output.
+    let mut final_status;
+    let mut vars = Variables::from_environment();
+    vars.options = invocation.options.clone();
+    vars.interactive = true;
+    vars.options.expand_aliases = true;
+    vars.options.monitor = true;
+
+    // Ignore attempts to terminate, suspend, or quit 0-shell, and prevent it
+    // from being stopped if it attempts a terminal read or write while
+    // backgrounded. The terminal driver fires `SIGTTIN` (`SIGTTOU`) when a
+    // background process group tries to read from the terminal, or write to
+    // it (unless output is piped or redirected). `trap` may change this.
+    signals::ignore_interactive();
+
+    // Have SIGCHLD wake the shell while it waits for input, so that, with
+    // `set -b`, jobs can be reported as soon as they finish or stop.
+    if let Err(err) = signals::watch_children() {
+        error::red_println(&format!("0-shell: SIGCHLD: {err}"));
     }
 
     let mut history = VecDeque::new();
@@ -103,19 +90,53 @@ pub fn repl() {
         let _style = TextStyle::new();
         let _tty_guard = TtyGuard::new();
 
+        // Read the startup files, unless one of them exits.
+        let mut flow = Flow::Next;
+        for file in startup_files(&invocation, &vars) {
+            let args = [String::from("source"), file];
+            flow = match source::source(&args, &mut jobs, &mut exit_attempted, &mut vars) {
+                Ok(flow) => flow,
+                Err(err) => {
+                    error::handle_error("0-shell", err);
+                    Flow::Next
+                }
+            };
+            if let Flow::Exit(_) = flow {
+                break;
+            }
+        }
+
         loop {
-            jobs::check_background_jobs(&mut jobs, &mut current, &mut previous);
+            if let Flow::Exit(code) = flow {
+                final_status = code;
+                break;
+            }
+
+            jobs::check_background_jobs(&mut jobs, true);
+
+            // Signals caught while the last command ran, or since.
+            let caught = trap::run_pending(&mut jobs, &mut exit_attempted, &mut vars);
+            if let Flow::Exit(code) = caught {
+                final_status = code;
+                break;
+            }
 
-            let input_string = match input::get_input(&mut history) {
+            let aliases: Vec<String> = vars.aliases().keys().cloned().collect();
+            let input_string = match input::get_input(
+                &mut history,
+                &aliases,
+                vars.options.notify.then_some(&mut jobs),
+            ) {
                 Ok(Some(ok_input)) => ok_input,
                 Ok(None) => {
                     // Ctrl+D.
-                    let has_stopped = jobs.iter().any(|j| j.state == jobs::State::Stopped);
+                    let has_stopped = jobs.iter().any(|j| j.state.is_stopped());
                     if has_stopped && !exit_attempted {
                         error::red_println(&format!("exit: {STOPPED_JOBS_WARNING}"));
                         exit_attempted = true;
                         continue;
                     }
+                    final_status = vars.last_status;
                     break;
                 }
                 Err(err) => {
@@ -130,44 +151,22 @@ pub fn repl() {
 
             history.push_back(input_string.clone());
 
-            let input_after_splitting: Vec<String>;
-            match input::split::split(&input_string) {
-                Ok(res) => input_after_splitting = res,
+            let list = match parser::parse_with_aliases(&input_string, vars.active_aliases()) {
+                Ok(list) => list,
                 Err(err) => {
                     error::red_println(&format!("0-shell: {err}"));
                     continue;
                 }
-            }
-
-            if input_after_splitting.is_empty() {
-                continue;
-            }
-
-            let (exit_signal, result) = commands::run_command(
-                &input_after_splitting,
-                &mut jobs,
-                &mut current,
-                &mut previous,
-                &mut exit_attempted,
-            );
-
-            match result {
-                Ok(ok) => {
-                    if !ok.is_empty() {
-                        print!("{}", ok);
-                    }
-                }
-                Err(err) => error::handle_error(&input_after_splitting[0], err),
-            }
+            };
 
-            if let Some(code) = exit_signal {
-                final_status = code;
-                break;
-            }
+            fork::clear_interrupted();
+            flow = commands::run_list(&list, &mut jobs, &mut exit_attempted, &mut vars);
         }
+
+        final_status = trap::run_exit(final_status, &mut jobs, &mut vars);
     }
 
-    for job in jobs {
+    for job in jobs.to_hang_up(vars.options.huponexit) {
         unsafe {
             libc::kill(-job.pgid, libc::SIGHUP);
             libc::kill(-job.pgid, libc::SIGCONT);
@@ -176,3 +175,65 @@ pub fn repl() {
 
     std::process::exit(final_status);
 }
+
+// The files to read at startup, those that exist: for a login shell,
+// `~/.0shell_profile`, and then `~/.0shellrc`, or the file given with
+// `--rcfile`, unless `--norc` says not to.
+fn startup_files(invocation: &Invocation, vars: &Variables) -> Vec<String> {
+    let home = vars.get("HOME").unwrap_or_default();
+    let mut files = Vec::new();
+    if invocation.login {
+        files.push(format!("{home}/.0shell_profile"));
+    }
+    if !invocation.norc {
+        match &invocation.rcfile {
+            Some(file) => files.push(file.clone()),
+            None => files.push(format!("{home}/.0shellrc")),
+        }
+    }
+    files.retain(|file| Path::new(file).is_file());
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::startup_files;
+    use crate::{script::invocation, string_vec, test_helpers::TempStore, variables::Variables};
+
+    #[test]
+    fn startup_files_load_profile_then_rc() {
+        let temp_store = TempStore::new(1);
+        fs::create_dir_all(&temp_store.store[0]).expect("failed to create dir");
+        let home = fs::canonicalize(&temp_store.store[0]).expect("failed to find dir");
+        let home = home.to_string_lossy();
+        let (profile, rc) = (
+            format!("{home}/.0shell_profile"),
+            format!("{home}/.0shellrc"),
+        );
+        let team = format!("{home}/team.rc");
+        for file in [&profile, &rc, &team] {
+            fs::write(file, "").expect("failed to create file");
+        }
+        let mut vars = Variables::default();
+        vars.set("HOME", &home).unwrap();
+
+        let files = |args: Vec<String>| {
+            let invocation = invocation(&args).expect("arguments should be valid");
+            startup_files(&invocation, &vars)
+        };
+        assert_eq!(files(string_vec![]), [rc.clone()]);
+        assert_eq!(
+            files(string_vec!["-l"]),
+            [profile.clone(), rc],
+            "a login shell should read its profile first"
+        );
+        assert_eq!(
+            files(string_vec!["-l", "--rcfile", &team]),
+            [profile.clone(), team.clone()]
+        );
+        assert_eq!(files(string_vec!["-l", "--norc"]), [profile]);
+        assert!(files(string_vec!["--norc", "--rcfile", &team]).is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+// Running commands without a terminal: from a script file, from the string
+// given with `-c`, or from standard input when that isn't a terminal. The
+// shell reads one complete command at a time and runs it before reading the
+// next, so that, e.g., a function can be defined and then used. There's no
+// prompt, no line editing and no job control, so every job stays in the
+// shell's own process group, and Ctrl+C interrupts the shell along with it.
+
+use std::{fs, io};
+
+use crate::{
+    commands::{self, Flow, jobs::JobTable, trap},
+    error, input,
+    options::Options,
+    parser,
+    variables::Variables,
+};
+
+pub const USAGE: &str = "Usage:\t0-shell [--norc] [--rcfile FILE] [-lnex] [-c COMMANDS [NAME [ARG ...]] | FILE [ARG ...]]";
+
+// How the shell was asked to run, from its command-line arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub options: Options,
+    pub source: Source,
+    pub arg0: Option<String>, // `$0`, if not the shell's own name.
+    pub positional: Vec<String>,
+    pub login: bool,            // `-l`: read the login profile at startup.
+    pub rcfile: Option<String>, // `--rcfile`: read instead of `~/.0shellrc`.
+    pub norc: bool,             // `--norc`: read no rc file at all.
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Commands(String), // `-c`
+    File(String),
+    Stdin, // Interactive, if standard input is a terminal.
+}
+
+// Read the shell's arguments, not including its own name. Options such as
+// `-ex` may be combined, and `--` ends them. Long options such as `--norc`
+// may come among the others.
+pub fn invocation(args: &[String]) -> Result<Invocation, String> {
+    let mut options = Options::default();
+    let mut commands = false;
+    let mut login = false;
+    let mut rcfile = None;
+    let mut norc = false;
+    let mut index = 0;
+
+    while let Some(arg) = args.get(index) {
+        index += 1;
+        match arg.as_str() {
+            "--" => break,
+            "--login" => login = true,
+            "--norc" => norc = true,
+            "--rcfile" => {
+                let Some(file) = args.get(index) else {
+                    return Err(format!(
+                        "0-shell: --rcfile: Option requires an argument\n{USAGE}"
+                    ));
+                };
+                index += 1;
+                rcfile = Some(file.clone());
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("0-shell: {arg}: Invalid option\n{USAGE}"));
+            }
+            _ => {
+                let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+                    // The first operand.
+                    index -= 1;
+                    break;
+                };
+                for c in flags.chars() {
+                    match c {
+                        'c' => commands = true,
+                        'e' => options.errexit = true,
+                        'l' => login = true,
+                        'n' => options.noexec = true,
+                        'x' => options.xtrace = true,
+                        _ => return Err(format!("0-shell: -{c}: Invalid option\n{USAGE}")),
+                    }
+                }
+            }
+        }
+    }
+
+    let mut operands = args[index..].iter().cloned();
+    let (source, arg0) = if commands {
+        let Some(text) = operands.next() else {
+            return Err(format!("0-shell: -c: Option requires an argument\n{USAGE}"));
+        };
+        (Source::Commands(text), operands.next())
+    } else {
+        match operands.next() {
+            Some(file) => (Source::File(file.clone()), Some(file)),
+            None => (Source::Stdin, None),
+        }
+    };
+
+    Ok(Invocation {
+        options,
+        source,
+        arg0,
+        positional: operands.collect(),
+        login,
+        rcfile,
+        norc,
+    })
+}
+
+// Run a script, or commands from `-c` or standard input, and return the
+// status for the shell to exit with: that of the last command, or 2 if a
+// command can't be parsed. 126 or 127 if the script can't be read, as with
+// a program that can't be run. The `EXIT` trap runs last, and may change
+// it.
+pub fn run(invocation: Invocation) -> i32 {
+    let mut vars = Variables::from_environment();
+    vars.options = invocation.options;
+    vars.command_string = matches!(invocation.source, Source::Commands(_));
+    if let Some(arg0) = invocation.arg0 {
+        vars.arg0 = arg0;
+    }
+    vars.positional = invocation.positional;
+
+    let (name, mut lines) = match invocation.source {
+        Source::Commands(text) => (String::from("-c"), Lines::text(&text)),
+        Source::File(path) => match read_script(&path) {
+            Ok(text) => (path, Lines::text(&text)),
+            Err((err, status)) => {
+                error::red_println(&format!("0-shell: {path}: {err}"));
+                return status;
+            }
+        },
+        Source::Stdin => (String::from("0-shell"), Lines::Stdin),
+    };
+
+    let mut jobs = JobTable::default();
+    let flow = run_lines(
+        &name, &mut lines, &mut jobs, &mut true, // There's no one to warn about stopped jobs.
+        &mut vars,
+    );
+    let status = match flow {
+        Ok(flow) => flow.exit_status(vars.last_status),
+        Err(err) => {
+            error::red_println(&err);
+            2
+        }
+    };
+    trap::run_exit(status, &mut jobs, &mut vars)
+}
+
+// Run the commands in `text` in the shell itself, as `source` does. `name`
+// is where they came from, for error messages. Returns the flow that ended
+// them, or an error if one couldn't be parsed, in which case none after it
+// are run.
+pub fn run_text(
+    name: &str,
+    text: &str,
+    jobs: &mut JobTable,
+    exit_attempted: &mut bool,
+    vars: &mut Variables,
+) -> Result<Flow, String> {
+    let mut lines = Lines::text(text);
+    run_lines(name, &mut lines, jobs, exit_attempted, vars)
+}
+
+fn run_lines(
+    name: &str,
+    lines: &mut Lines,
+    jobs: &mut JobTable,
+    exit_attempted: &mut bool,
+    vars: &mut Variables,
+) -> Result<Flow, String> {
+    let mut number = 0; // The number of the last line read.
+
+    loop {
+        let start = number + 1;
+        let Some(mut text) = lines.next() else {
+            return Ok(Flow::Next);
+        };
+        number += 1;
+        input::complete_command(&mut text, || {
+            let line = lines.next()?;
+            number += 1;
+            Some(line)
+        });
+
+        let list = parser::parse_with_aliases(&text, vars.active_aliases())
+            .map_err(|err| format!("0-shell: {name}: line {start}: {err}"))?;
+        if vars.options.noexec {
+            continue;
+        }
+
+        let flow = commands::run_list(&list, jobs, exit_attempted, vars);
+        if flow != Flow::Next {
+            return Ok(flow);
+        }
+    }
+}
+
+// The text of a script, or an error and the status to exit with.
+pub fn read_script(path: &str) -> Result<String, (&'static str, i32)> {
+    if fs::metadata(path).is_ok_and(|m| m.is_dir()) {
+        return Err(("Is a directory", 126));
+    }
+    let bytes = fs::read(path).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => ("No such file or directory", 127),
+        _ => ("Permission denied", 126),
+    })?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// Where the commands come from, a line at a time. Standard input is read a
+// byte at a time, so that whatever the shell hasn't read yet is left for the
+// commands it runs, as in `0-shell < script` where the script runs `cat`.
+enum Lines {
+    Text(std::vec::IntoIter<String>),
+    Stdin,
+}
+
+impl Lines {
+    fn text(text: &str) -> Self {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        Lines::Text(lines.into_iter())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        match self {
+            Lines::Text(lines) => lines.next(),
+            Lines::Stdin => read_stdin_line(),
+        }
+    }
+}
+
+// `None` at the end of the input.
+fn read_stdin_line() -> Option<String> {
+    let mut line = Vec::new();
+    let mut byte = 0u8;
+    loop {
+        let n = unsafe { libc::read(libc::STDIN_FILENO, (&raw mut byte).cast(), 1) };
+        match n {
+            1 if byte == b'\n' => break,
+            1 => line.push(byte),
+            -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+            _ if line.is_empty() => return None,
+            _ => break,
+        }
+    }
+    Some(String::from_utf8_lossy(&line).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Invocation, Source, invocation, run_text};
+    use crate::{commands::jobs::JobTable, options::Options, string_vec, variables::Variables};
+
+    #[test]
+    fn invocation_reads_arguments() {
+        assert_eq!(
+            invocation(&string_vec!["-ex", "script.sh", "a", "-b"]),
+            Ok(Invocation {
+                options: Options {
+                    errexit: true,
+                    xtrace: true,
+                    ..Options::default()
+                },
+                source: Source::File(String::from("script.sh")),
+                arg0: Some(String::from("script.sh")),
+                positional: string_vec!["a", "-b"],
+                login: false,
+                rcfile: None,
+                norc: false,
+            })
+        );
+        assert_eq!(
+            invocation(&string_vec!["-c", "echo $0 $1", "name", "arg"]),
+            Ok(Invocation {
+                options: Options::default(),
+                source: Source::Commands(String::from("echo $0 $1")),
+                arg0: Some(String::from("name")),
+                positional: string_vec!["arg"],
+                login: false,
+                rcfile: None,
+                norc: false,
+            })
+        );
+        assert_eq!(
+            invocation(&string_vec!["-n", "--", "-file"]).map(|i| i.source),
+            Ok(Source::File(String::from("-file")))
+        );
+        assert_eq!(invocation(&[]).map(|i| i.source), Ok(Source::Stdin));
+        let startup = invocation(&string_vec!["--rcfile", "team.rc", "-l"])
+            .map(|i| (i.source, i.rcfile, i.login));
+        assert_eq!(
+            startup,
+            Ok((Source::Stdin, Some(String::from("team.rc")), true))
+        );
+        assert!(invocation(&string_vec!["--rcfile"]).is_err());
+        assert!(invocation(&string_vec!["--bogus"]).is_err());
+        assert!(invocation(&string_vec!["-c"]).is_err());
+        assert!(invocation(&string_vec!["-q"]).is_err());
+    }
+
+    #[test]
+    fn commands_continue_across_lines() {
+        let mut vars = Variables::default();
+        let text = "out=\"line one\nline two\"; f() { args=$*; }\nf one \\\n  two\nargs=$args\\\n!";
+        let flow = run_text("test", text, &mut JobTable::default(), &mut true, &mut vars);
+        assert!(flow.is_ok(), "{flow:?}");
+        assert_eq!(vars.get("out"), Some("line one\nline two"));
+        assert_eq!(vars.get("args"), Some("one two!"));
+        assert!(
+            run_text(
+                "test",
+                "out='a",
+                &mut JobTable::default(),
+                &mut true,
+                &mut vars
+            )
+            .is_err()
+        );
+    }
+}
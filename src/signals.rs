@@ -0,0 +1,307 @@
+// The signals the shell knows by name, and what it does when it receives
+// them. By default, an interactive shell ignores those sent from the terminal,
+// so that they only reach the job in the foreground. `trap` may instead have
+// the shell catch a signal, in which case the handler below only records that
+// it arrived, and the action runs later, between commands. Signals that were
+// ignored when the shell started, or that `trap ''` ignores, stay ignored in
+// the commands it runs. Any others go back to their default actions there.
+// The shell may also watch for SIGCHLD, which the handler passes on, along
+// with any signal the shell catches, by writing to a pipe, so that the line
+// editor can wait for it along with the keyboard, and `wait` can block until
+// something happens.
+
+use std::{
+    fmt, io, mem, ptr,
+    sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering},
+};
+
+// Each signal's name, number, and description, as job status messages give
+// it.
+pub const SIGNALS: [(&str, i32, &str); 29] = [
+    ("HUP", libc::SIGHUP, "Hangup"),
+    ("INT", libc::SIGINT, "Interrupt"),
+    ("QUIT", libc::SIGQUIT, "Quit"),
+    ("ILL", libc::SIGILL, "Illegal instruction"),
+    ("TRAP", libc::SIGTRAP, "Trace/breakpoint trap"),
+    ("ABRT", libc::SIGABRT, "Aborted"),
+    ("BUS", libc::SIGBUS, "Bus error"),
+    ("FPE", libc::SIGFPE, "Floating point exception"),
+    ("KILL", libc::SIGKILL, "Killed"),
+    ("USR1", libc::SIGUSR1, "User defined signal 1"),
+    ("SEGV", libc::SIGSEGV, "Segmentation fault"),
+    ("USR2", libc::SIGUSR2, "User defined signal 2"),
+    ("PIPE", libc::SIGPIPE, "Broken pipe"),
+    ("ALRM", libc::SIGALRM, "Alarm clock"),
+    ("TERM", libc::SIGTERM, "Terminated"),
+    ("CHLD", libc::SIGCHLD, "Child exited"),
+    ("CONT", libc::SIGCONT, "Continued"),
+    ("STOP", libc::SIGSTOP, "Stopped (signal)"),
+    ("TSTP", libc::SIGTSTP, "Stopped"),
+    ("TTIN", libc::SIGTTIN, "Stopped (tty input)"),
+    ("TTOU", libc::SIGTTOU, "Stopped (tty output)"),
+    ("URG", libc::SIGURG, "Urgent I/O condition"),
+    ("XCPU", libc::SIGXCPU, "CPU time limit exceeded"),
+    ("XFSZ", libc::SIGXFSZ, "File size limit exceeded"),
+    ("VTALRM", libc::SIGVTALRM, "Virtual timer expired"),
+    ("PROF", libc::SIGPROF, "Profiling timer expired"),
+    ("WINCH", libc::SIGWINCH, "Window changed"),
+    ("IO", libc::SIGIO, "I/O possible"),
+    ("SYS", libc::SIGSYS, "Bad system call"),
+];
+
+// Ctrl+C, Ctrl+Z and Ctrl+\, and the signals that would stop the shell for
+// using the terminal while a job has it.
+const INTERACTIVE_IGNORED: [i32; 5] = [
+    libc::SIGINT,
+    libc::SIGTSTP,
+    libc::SIGQUIT,
+    libc::SIGTTIN,
+    libc::SIGTTOU,
+];
+
+// Sets of signals, one bit for each signal number.
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+static IGNORED_ON_ENTRY: AtomicU64 = AtomicU64::new(0);
+static IGNORED: AtomicU64 = AtomicU64::new(0); // Inherited by commands.
+static PENDING: AtomicU64 = AtomicU64::new(0); // Caught but not yet acted on.
+
+// The ends of the pipe that SIGCHLD and caught signals are passed on through,
+// or -1 if the shell isn't watching for them.
+static CHILD_READ: AtomicI32 = AtomicI32::new(-1);
+static CHILD_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+// What `trap` can set an action for: a signal, or one of the shell's own
+// conditions. They're listed in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Condition {
+    Exit,        // The shell exits.
+    Signal(i32), // The shell receives the signal.
+    Debug,       // Before each command.
+    Error,       // A command fails, where `-e` would end the shell.
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Exit => write!(f, "EXIT"),
+            Condition::Signal(sig) => match name(*sig) {
+                Some(name) => write!(f, "SIG{name}"),
+                None => write!(f, "{sig}"),
+            },
+            Condition::Debug => write!(f, "DEBUG"),
+            Condition::Error => write!(f, "ERR"),
+        }
+    }
+}
+
+// A signal's name, without the `SIG` prefix.
+pub fn name(sig: i32) -> Option<&'static str> {
+    SIGNALS
+        .iter()
+        .find(|&&(_, n, _)| n == sig)
+        .map(|&(name, _, _)| name)
+}
+
+// What a signal does, as in `Terminated` or `Killed`, for the message about a
+// job that it terminated or stopped.
+pub fn description(sig: i32) -> String {
+    SIGNALS.iter().find(|&&(_, n, _)| n == sig).map_or_else(
+        || format!("Signal {sig}"),
+        |&(_, _, text)| String::from(text),
+    )
+}
+
+// A signal given by its name, with or without `SIG`, in any case, or by its
+// number.
+pub fn number(spec: &str) -> Option<i32> {
+    if let Ok(sig) = spec.parse::<i32>() {
+        return name(sig).map(|_| sig);
+    }
+    let spec = spec.to_ascii_uppercase();
+    let spec = spec.strip_prefix("SIG").unwrap_or(&spec);
+    SIGNALS
+        .iter()
+        .find(|&&(name, _, _)| name == spec)
+        .map(|&(_, sig, _)| sig)
+}
+
+fn bit(sig: i32) -> u64 {
+    1 << sig
+}
+
+// Note which signals were ignored when the shell started. The Rust runtime
+// ignores SIGPIPE itself before the shell gets going, so that can't be told
+// apart, and is taken to have had its default action.
+pub fn init() {
+    let mut ignored = 0;
+    for (_, sig, _) in SIGNALS {
+        if sig == libc::SIGPIPE {
+            continue;
+        }
+        let mut action = unsafe { mem::zeroed::<libc::sigaction>() };
+        if unsafe { libc::sigaction(sig, ptr::null(), &mut action) } == 0
+            && action.sa_sigaction == libc::SIG_IGN
+        {
+            ignored |= bit(sig);
+        }
+    }
+    IGNORED_ON_ENTRY.store(ignored, Ordering::SeqCst);
+    IGNORED.store(ignored, Ordering::SeqCst);
+}
+
+pub fn was_ignored_on_entry(sig: i32) -> bool {
+    IGNORED_ON_ENTRY.load(Ordering::SeqCst) & bit(sig) != 0
+}
+
+// Ignore attempts to terminate, suspend, or quit the interactive shell.
+pub fn ignore_interactive() {
+    INTERACTIVE.store(true, Ordering::SeqCst);
+    for sig in INTERACTIVE_IGNORED {
+        if let Err(err) = set_action(sig, libc::SIG_IGN) {
+            panic!("failed to set signal action `{sig}`: {err}");
+        }
+    }
+}
+
+// Watch for children that finish or stop, so that the interactive shell can
+// report them straight away. Returns the file descriptor that becomes
+// readable when one does, or a signal is caught.
+pub fn watch_children() -> Result<i32, String> {
+    if let Some(fd) = child_notifications() {
+        return Ok(fd);
+    }
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error().to_string());
+    }
+    CHILD_READ.store(fds[0], Ordering::SeqCst);
+    CHILD_WRITE.store(fds[1], Ordering::SeqCst);
+    set_action(libc::SIGCHLD, record as *const () as libc::sighandler_t)?;
+    Ok(fds[0])
+}
+
+// The file descriptor that becomes readable when a child finishes or stops,
+// or a signal is caught, if the shell is watching for that.
+pub fn child_notifications() -> Option<i32> {
+    let fd = CHILD_READ.load(Ordering::SeqCst);
+    (fd >= 0).then_some(fd)
+}
+
+// Empty the pipe that signals are passed on through, once the children have
+// been checked on.
+pub fn clear_child_notifications() {
+    let fd = CHILD_READ.load(Ordering::SeqCst);
+    if fd < 0 {
+        return;
+    }
+    let mut buffer = [0u8; 64];
+    while unsafe { libc::read(fd, buffer.as_mut_ptr().cast(), buffer.len()) } > 0 {}
+}
+
+// Have the shell catch a signal, for `trap` to act on.
+pub fn catch(sig: i32) -> Result<(), String> {
+    IGNORED.fetch_and(!bit(sig), Ordering::SeqCst);
+    set_action(sig, record as *const () as libc::sighandler_t)
+}
+
+pub fn ignore(sig: i32) -> Result<(), String> {
+    IGNORED.fetch_or(bit(sig), Ordering::SeqCst);
+    set_action(sig, libc::SIG_IGN)
+}
+
+// Give a signal back the action it has in the shell when there's no trap.
+pub fn reset(sig: i32) -> Result<(), String> {
+    IGNORED.fetch_and(!bit(sig), Ordering::SeqCst);
+    let action = if INTERACTIVE.load(Ordering::SeqCst) && INTERACTIVE_IGNORED.contains(&sig) {
+        libc::SIG_IGN
+    } else if sig == libc::SIGCHLD && CHILD_WRITE.load(Ordering::SeqCst) >= 0 {
+        record as *const () as libc::sighandler_t
+    } else {
+        libc::SIG_DFL
+    };
+    set_action(sig, action)
+}
+
+pub fn is_pending(sig: i32) -> bool {
+    PENDING.load(Ordering::SeqCst) & bit(sig) != 0
+}
+
+// Whether the shell has received a signal since it was last asked, in which
+// case the signal is no longer pending.
+pub fn take_pending(sig: i32) -> bool {
+    PENDING.fetch_and(!bit(sig), Ordering::SeqCst) & bit(sig) != 0
+}
+
+// Set the signals back to their default actions, except for those that are
+// to stay ignored, in a command or subshell that the shell has forked. A
+// subshell that catches a signal will have set a trap of its own.
+pub fn restore_defaults() {
+    INTERACTIVE.store(false, Ordering::SeqCst);
+    PENDING.store(0, Ordering::SeqCst);
+    for end in [&CHILD_READ, &CHILD_WRITE] {
+        let fd = end.swap(-1, Ordering::SeqCst);
+        if fd >= 0 {
+            unsafe { libc::close(fd) };
+        }
+    }
+    let ignored = IGNORED.load(Ordering::SeqCst);
+    for (_, sig, _) in SIGNALS {
+        if sig == libc::SIGKILL || sig == libc::SIGSTOP {
+            continue;
+        }
+        let action = if ignored & bit(sig) != 0 {
+            libc::SIG_IGN
+        } else {
+            libc::SIG_DFL
+        };
+        unsafe { libc::signal(sig, action) };
+    }
+}
+
+// Only do what's safe in a signal handler: note that the signal arrived, and
+// pass it on, if the shell is watching. A full pipe already has something to
+// read.
+extern "C" fn record(sig: libc::c_int) {
+    PENDING.fetch_or(bit(sig), Ordering::SeqCst);
+    let fd = CHILD_WRITE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        unsafe {
+            libc::write(fd, [0u8].as_ptr().cast(), 1);
+            *libc::__errno_location() = errno;
+        }
+    }
+}
+
+// Interrupted system calls, such as waiting for a job, carry on afterwards.
+fn set_action(sig: i32, handler: libc::sighandler_t) -> Result<(), String> {
+    unsafe {
+        let mut action = mem::zeroed::<libc::sigaction>();
+        libc::sigemptyset(&mut action.sa_mask);
+        action.sa_sigaction = handler;
+        action.sa_flags = libc::SA_RESTART;
+        if libc::sigaction(sig, &action, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Condition, description, name, number};
+
+    #[test]
+    fn signal_names_and_numbers() {
+        assert_eq!(number("INT"), Some(libc::SIGINT));
+        assert_eq!(number("sigterm"), Some(libc::SIGTERM));
+        assert_eq!(number("9"), Some(libc::SIGKILL));
+        assert_eq!(number("SIGNOPE"), None);
+        assert_eq!(number("0"), None);
+        assert_eq!(name(libc::SIGHUP), Some("HUP"));
+        assert_eq!(description(libc::SIGKILL), "Killed");
+        assert_eq!(description(64), "Signal 64");
+        assert_eq!(Condition::Signal(libc::SIGUSR1).to_string(), "SIGUSR1");
+        assert_eq!(Condition::Error.to_string(), "ERR");
+    }
+}
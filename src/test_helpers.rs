@@ -2,6 +2,8 @@ use std::fs;
 
 use uuid::Uuid;
 
+use crate::parser::ast::{Command, List, SimpleCommand};
+
 pub struct TempStore {
     pub store: Vec<String>,
 }
@@ -27,6 +29,15 @@ impl Drop for TempStore {
     }
 }
 
+// Stage `stage` of the pipeline that's element `item` of a parsed command
+// list, which should be a simple command.
+pub fn simple_command(list: &List, item: usize, stage: usize) -> &SimpleCommand {
+    match &list.items[item].pipeline.commands[stage] {
+        Command::Simple(command) => command,
+        command => panic!("expected a simple command, found {command:?}"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::Path;
@@ -0,0 +1,434 @@
+// The shell's variables. Each is either local to the shell or exported, in
+// which case it's passed in the environment of every command the shell runs.
+// The shell starts with its own environment as exported variables.
+
+use std::{collections::BTreeMap, env, rc::Rc};
+
+use crate::{options::Options, parser::ast::Function, signals::Condition};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Variable {
+    pub value: Option<String>, // `None` if exported or made readonly but never set.
+    pub exported: bool,
+    pub readonly: bool,
+}
+
+// Along with the variables themselves, the store holds the values of the
+// special parameters, such as `$?`, the positional parameters, `$1` and so
+// on, the shell's options, how deeply loops are nested, and the functions,
+// aliases and traps the shell has defined.
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    variables: BTreeMap<String, Variable>, // Sorted, for listing.
+    pub arg0: String,                      // `$0`
+    pub positional: Vec<String>,           // `$1`, `$2`, ...
+    pub last_status: i32,                  // `$?`
+    pub last_background: Option<i32>,      // `$!`: the PID of the last background job.
+    pub interactive: bool,                 // Shown in `$-`.
+    pub command_string: bool,              // Whether commands come from `-c`, also shown.
+    pub options: Options,
+    pub loops: usize,      // How many loops the running command is in, for `break`.
+    pub conditions: usize, // How many conditions it's in, where `-e` doesn't apply.
+    pub sourced: usize,    // How many files `source` is running, for `return`.
+    pub trapping: bool,    // Whether a trap's action is running.
+    pub substituted: bool, // Whether the command has run a command substitution.
+    shell_pid: i32,        // `$$`, which a subshell inherits.
+    functions: BTreeMap<String, Rc<Function>>, // Sorted, for listing.
+    aliases: BTreeMap<String, String>,
+    traps: BTreeMap<Condition, String>, // An empty action ignores the signal.
+    scopes: Vec<Saved>,                 // For each function call, what `local` has hidden.
+}
+
+// What `set_temporarily` changed, so that `restore` can undo it.
+pub type Saved = Vec<(String, Option<Variable>)>;
+
+impl Variables {
+    pub fn from_environment() -> Self {
+        let variables = env::vars()
+            .map(|(name, value)| {
+                let variable = Variable {
+                    value: Some(value),
+                    exported: true,
+                    readonly: false,
+                };
+                (name, variable)
+            })
+            .collect();
+
+        let mut vars = Variables {
+            variables,
+            arg0: env::args()
+                .next()
+                .unwrap_or_else(|| String::from("0-shell")),
+            shell_pid: unsafe { libc::getpid() },
+            ..Variables::default()
+        };
+        // What was inherited may be out of date.
+        if let Ok(dir) = env::current_dir() {
+            let _ = vars.set("PWD", &dir.to_string_lossy());
+        }
+        vars
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables.get(name)?.value.as_deref()
+    }
+
+    // The value of any parameter: a variable, a positional parameter or a
+    // special parameter. `None` if it's unset.
+    pub fn parameter(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.last_status.to_string()),
+            "$" => Some(self.shell_pid.to_string()),
+            "!" => self.last_background.map(|pid| pid.to_string()),
+            "-" => Some(self.option_flags()),
+            "#" => Some(self.positional.len().to_string()),
+            "@" | "*" => Some(self.positional.join(" ")),
+            "0" => Some(self.arg0.clone()),
+            _ if name.chars().all(|c| c.is_ascii_digit()) => {
+                let index = name.parse::<usize>().ok()?.checked_sub(1)?;
+                self.positional.get(index).cloned()
+            }
+            _ => self.get(name).map(String::from),
+        }
+    }
+
+    // The letters of the options in effect, for `$-`, in whatever mode the
+    // shell is running. Brace expansion is always on, so `B` is always there.
+    fn option_flags(&self) -> String {
+        let mut flags = String::new();
+        if self.options.notify {
+            flags.push('b');
+        }
+        if self.options.errexit {
+            flags.push('e');
+        }
+        if self.interactive {
+            flags.push('i');
+        }
+        if self.options.monitor {
+            flags.push('m');
+        }
+        if self.options.noexec {
+            flags.push('n');
+        }
+        if self.options.xtrace {
+            flags.push('x');
+        }
+        flags.push('B');
+        if self.command_string {
+            flags.push('c');
+        }
+        flags
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Variable)> {
+        self.variables.iter()
+    }
+
+    // Give a variable a value. An exported variable stays exported.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let variable = self.variables.entry(String::from(name)).or_default();
+        if variable.readonly {
+            return Err(format!("0-shell: {name}: readonly variable"));
+        }
+        variable.value = Some(String::from(value));
+        Ok(())
+    }
+
+    pub fn unset(&mut self, name: &str) -> Result<(), String> {
+        if self.variables.get(name).is_some_and(|v| v.readonly) {
+            return Err(format!("{name}: cannot unset: readonly variable"));
+        }
+        self.variables.remove(name);
+        Ok(())
+    }
+
+    pub fn export(&mut self, name: &str, exported: bool) {
+        self.variables
+            .entry(String::from(name))
+            .or_default()
+            .exported = exported;
+    }
+
+    pub fn make_readonly(&mut self, name: &str) {
+        self.variables
+            .entry(String::from(name))
+            .or_default()
+            .readonly = true;
+    }
+
+    // Set variables for the duration of a single builtin, as in `FOO=1 cmd`.
+    pub fn set_temporarily(&mut self, assignments: &[(String, String)]) -> Result<Saved, String> {
+        let mut saved = Vec::new();
+        for (name, value) in assignments {
+            let previous = self.variables.get(name).cloned();
+            if let Err(err) = self.set(name, value) {
+                self.restore(saved);
+                return Err(err);
+            }
+            saved.push((name.clone(), previous));
+        }
+        Ok(saved)
+    }
+
+    pub fn restore(&mut self, saved: Saved) {
+        for (name, previous) in saved.into_iter().rev() {
+            match previous {
+                Some(variable) => self.variables.insert(name, variable),
+                None => self.variables.remove(&name),
+            };
+        }
+    }
+
+    pub fn function(&self, name: &str) -> Option<Rc<Function>> {
+        self.functions.get(name).cloned()
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = &Function> {
+        self.functions.values().map(Rc::as_ref)
+    }
+
+    pub fn define_function(&mut self, function: Rc<Function>) {
+        self.functions.insert(function.name.clone(), function);
+    }
+
+    // Returns whether there was such a function.
+    pub fn unset_function(&mut self, name: &str) -> bool {
+        self.functions.remove(name).is_some()
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    // The aliases to expand in the commands the shell reads: none, unless the
+    // `expand_aliases` option is set.
+    pub fn active_aliases(&self) -> &BTreeMap<String, String> {
+        static NONE: BTreeMap<String, String> = BTreeMap::new();
+        if self.options.expand_aliases {
+            &self.aliases
+        } else {
+            &NONE
+        }
+    }
+
+    pub fn set_alias(&mut self, name: &str, value: &str) {
+        self.aliases.insert(String::from(name), String::from(value));
+    }
+
+    // Returns whether there was such an alias.
+    pub fn unset_alias(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some()
+    }
+
+    pub fn clear_aliases(&mut self) {
+        self.aliases.clear();
+    }
+
+    pub fn trap(&self, condition: Condition) -> Option<&str> {
+        self.traps.get(&condition).map(String::as_str)
+    }
+
+    pub fn traps(&self) -> &BTreeMap<Condition, String> {
+        &self.traps
+    }
+
+    // Set the action for a condition, or with `None`, remove it.
+    pub fn set_trap(&mut self, condition: Condition, action: Option<&str>) {
+        match action {
+            Some(action) => self.traps.insert(condition, String::from(action)),
+            None => self.traps.remove(&condition),
+        };
+    }
+
+    // A subshell keeps the traps that ignore signals, but not the others.
+    pub fn clear_traps(&mut self) {
+        self.traps.retain(|_, action| action.is_empty());
+    }
+
+    // How deeply function calls are nested.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    // Start a function call, in which `local` variables hide any others of
+    // the same name until `pop_scope`.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Saved::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        if let Some(saved) = self.scopes.pop() {
+            self.restore(saved);
+        }
+    }
+
+    // Make a variable local to the function that's running: it starts out
+    // unset, and its previous value comes back when the function returns.
+    // `local` gives it a value afterwards, if there is one.
+    pub fn make_local(&mut self, name: &str) -> Result<(), String> {
+        let Some(scope) = self.scopes.last_mut() else {
+            return Err(String::from("Can only be used in a function"));
+        };
+        if self.variables.get(name).is_some_and(|v| v.readonly) {
+            return Err(format!("0-shell: {name}: readonly variable"));
+        }
+        // Already local, in which case it keeps its value.
+        if scope.iter().any(|(saved, _)| saved == name) {
+            return Ok(());
+        }
+        scope.push((String::from(name), self.variables.get(name).cloned()));
+        self.variables.remove(name);
+        Ok(())
+    }
+
+    // The environment for a command, as `NAME=value` strings: the exported
+    // variables plus any assignments that precede the command.
+    pub fn environment(&self, assignments: &[(String, String)]) -> Result<Vec<String>, String> {
+        let mut environment: BTreeMap<&str, &str> = self
+            .variables
+            .iter()
+            .filter(|(_, variable)| variable.exported)
+            .filter_map(|(name, variable)| Some((name.as_str(), variable.value.as_deref()?)))
+            .collect();
+
+        for (name, value) in assignments {
+            if self.variables.get(name).is_some_and(|v| v.readonly) {
+                return Err(format!("0-shell: {name}: readonly variable"));
+            }
+            environment.insert(name, value);
+        }
+
+        Ok(environment
+            .into_iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect())
+    }
+}
+
+// Quote a value, if necessary, so that the shell would read it back as one
+// word, for listings such as `set` and `export -p`.
+pub fn quote(value: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c);
+    if !value.is_empty() && value.chars().all(is_plain) {
+        String::from(value)
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Variables, quote};
+
+    #[test]
+    fn variables_set_unset_and_export() {
+        let mut vars = Variables::default();
+        vars.set("LOCAL", "1").unwrap();
+        vars.set("SHARED", "2").unwrap();
+        vars.export("SHARED", true);
+        vars.export("NO_VALUE", true);
+
+        assert_eq!(vars.get("LOCAL"), Some("1"));
+        assert_eq!(vars.get("NO_VALUE"), None);
+        assert_eq!(vars.environment(&[]), Ok(vec![String::from("SHARED=2")]));
+        assert_eq!(
+            vars.environment(&[(String::from("EXTRA"), String::from("3"))]),
+            Ok(vec![String::from("EXTRA=3"), String::from("SHARED=2")]),
+            "prefix assignments should be exported to the command"
+        );
+
+        vars.set("SHARED", "changed").unwrap();
+        assert_eq!(
+            vars.environment(&[]),
+            Ok(vec![String::from("SHARED=changed")]),
+            "an exported variable should stay exported"
+        );
+
+        vars.unset("SHARED").unwrap();
+        assert_eq!(vars.get("SHARED"), None);
+        assert_eq!(vars.environment(&[]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn readonly_variables_cannot_change() {
+        let mut vars = Variables::default();
+        vars.set("FIXED", "1").unwrap();
+        vars.make_readonly("FIXED");
+
+        assert_eq!(
+            vars.set("FIXED", "2"),
+            Err(String::from("0-shell: FIXED: readonly variable"))
+        );
+        assert!(vars.unset("FIXED").is_err());
+        assert!(
+            vars.environment(&[(String::from("FIXED"), String::from("2"))])
+                .is_err()
+        );
+        assert_eq!(vars.get("FIXED"), Some("1"));
+    }
+
+    #[test]
+    fn temporary_assignments_are_undone() {
+        let mut vars = Variables::default();
+        vars.set("OLD", "old").unwrap();
+
+        let saved = vars
+            .set_temporarily(&[
+                (String::from("OLD"), String::from("new")),
+                (String::from("NEW"), String::from("new")),
+            ])
+            .unwrap();
+        assert_eq!(vars.get("OLD"), Some("new"));
+        assert_eq!(vars.get("NEW"), Some("new"));
+
+        vars.restore(saved);
+        assert_eq!(vars.get("OLD"), Some("old"));
+        assert_eq!(vars.get("NEW"), None);
+    }
+
+    #[test]
+    fn special_and_positional_parameters() {
+        let mut vars = Variables::from_environment();
+        vars.positional = vec![String::from("one"), String::from("two")];
+        vars.last_status = 127;
+        vars.interactive = true;
+
+        assert_eq!(vars.parameter("?").as_deref(), Some("127"));
+        assert_eq!(vars.parameter("$"), Some(std::process::id().to_string()));
+        assert_eq!(
+            vars.parameter("!"),
+            None,
+            "no job has been run in the background"
+        );
+        assert_eq!(vars.parameter("-").as_deref(), Some("iB"));
+        assert_eq!(vars.parameter("#").as_deref(), Some("2"));
+        assert_eq!(vars.parameter("@").as_deref(), Some("one two"));
+        assert_eq!(vars.parameter("2").as_deref(), Some("two"));
+        assert_eq!(vars.parameter("3"), None);
+        assert_eq!(vars.parameter("00"), None);
+
+        vars.interactive = false;
+        vars.command_string = true;
+        vars.options.errexit = true;
+        vars.options.xtrace = true;
+        assert_eq!(
+            vars.parameter("-").as_deref(),
+            Some("exBc"),
+            "`$-` should show the options when running `-c` commands too"
+        );
+    }
+
+    #[test]
+    fn quote_values() {
+        assert_eq!(quote("/usr/bin:/bin"), "/usr/bin:/bin");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+}
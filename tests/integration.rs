@@ -1,7 +1,14 @@
-use std::path::{MAIN_SEPARATOR, Path};
+use std::{
+    io::{self, Write},
+    path::{MAIN_SEPARATOR, Path},
+};
 
-use zero_shell::commands::{
-    cat::cat, cd::cd, cp::cp, echo::echo, mkdir::mkdir, mv::mv, rm::rm, touch::touch,
+use zero_shell::{
+    commands::{cat::cat, cd::cd, cp::cp, echo::echo, mkdir::mkdir, mv::mv, rm::rm, touch::touch},
+    expand,
+    parser::{ast::Command, parse},
+    redirect::with_redirections,
+    variables::Variables,
 };
 
 #[test]
@@ -32,18 +39,29 @@ fn audit_question() {
     );
 
     assert!(
-        cd(&vec![String::from("cd"), String::from("new_folder1")]).is_ok(),
+        cd(
+            &vec![String::from("cd"), String::from("new_folder1")],
+            &Variables::default()
+        )
+        .is_ok(),
         "result of cd-ing into first folder should be ok"
     );
 
+    // Redirection is done by the shell, around the command. (`print!` would
+    // be captured by the test harness, so write to `stdout` directly.)
+    let list = parse("echo hello > new_doc.txt").expect("failed to parse");
+    let Command::Simple(command) = &list.items[0].pipeline.commands[0] else {
+        panic!("expected a simple command");
+    };
+    let expanded = expand::command(command, &mut Variables::default()).expect("failed to expand");
+    let result = with_redirections(&expanded.redirections, || {
+        let output = echo(&expanded.args)?;
+        io::stdout()
+            .write_all(output.as_bytes())
+            .map_err(|e| e.to_string())
+    });
     assert!(
-        echo(&vec![
-            String::from("echo"),
-            String::from("hello"),
-            String::from(">"),
-            String::from("new_doc.txt"),
-        ])
-        .is_ok(),
+        matches!(result, Ok(Ok(()))),
         "result of echoing text to file should be ok"
     );
 
@@ -58,7 +76,11 @@ fn audit_question() {
     );
 
     assert!(
-        cd(&vec![String::from("cd"), String::from("..")]).is_ok(),
+        cd(
+            &vec![String::from("cd"), String::from("..")],
+            &Variables::default()
+        )
+        .is_ok(),
         "result of cd-ing back out of first folder should be ok"
     );
 